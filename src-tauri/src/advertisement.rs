@@ -0,0 +1,245 @@
+// Structured advertisement data
+//
+// Manufacturer and service data are passed to the frontend as raw bytes (hex and base64)
+// with the company or service name where it is well known. Device profiles can add
+// decoders that pull values such as battery level or serial number out of those payloads,
+// so they show up in scan results without connecting.
+//
+// btleplug 0.10 does not expose the advertisement flags (AD type 0x01), so they cannot be
+// reported.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use btleplug::api::PeripheralProperties;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::device_profiles::{Endianness, FieldType};
+use crate::transport::{BATTERY_SERVICE_UUID, BLUETOOTH_BASE_UUID};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManufacturerDataRecord {
+    pub company_id: u16,
+    pub company_name: Option<&'static str>,
+    pub hex: String,
+    pub base64: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceDataRecord {
+    pub uuid: String,
+    pub service_name: Option<&'static str>,
+    pub hex: String,
+    pub base64: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AdvertisedValue {
+    Number(f64),
+    Text(String),
+}
+
+// Where an advertised field is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdvertisementSource {
+    // Manufacturer specific data of this company id
+    Manufacturer(u16),
+    Service(Uuid),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AdvertisedFormat {
+    // raw * scale + offset, like packet layout fields
+    Number {
+        field_type: FieldType,
+        #[serde(default = "default_endianness")]
+        endianness: Endianness,
+        #[serde(default = "default_scale")]
+        scale: f64,
+        #[serde(default)]
+        offset: f64,
+    },
+    // Printable ASCII up to `length` bytes (or the end of the payload), trailing NULs removed
+    Ascii {
+        #[serde(default)]
+        length: Option<usize>,
+    },
+    Hex {
+        #[serde(default)]
+        length: Option<usize>,
+    },
+}
+
+fn default_endianness() -> Endianness {
+    Endianness::Little
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+// Decoders a device profile attaches to its advertisements
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decoder", rename_all = "snake_case")]
+pub enum AdvertisementDecoder {
+    // Battery Level (percent) from Battery Service data, reported as "battery_level"
+    BatteryService,
+    // One value at a fixed position of manufacturer or service data
+    Field {
+        name: String,
+        source: AdvertisementSource,
+        #[serde(default)]
+        position: usize,
+        format: AdvertisedFormat,
+    },
+}
+
+impl AdvertisementDecoder {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            AdvertisementDecoder::BatteryService => Ok(()),
+            AdvertisementDecoder::Field { name, format, .. } => {
+                if name.trim().is_empty() {
+                    return Err("Advertisement field name is empty".to_string());
+                }
+                match format {
+                    AdvertisedFormat::Number { scale, offset, .. } if !scale.is_finite() || !offset.is_finite() => {
+                        Err(format!("Advertisement field '{}' has a non-finite scale or offset", name))
+                    }
+                    AdvertisedFormat::Ascii { length: Some(0) } | AdvertisedFormat::Hex { length: Some(0) } => {
+                        Err(format!("Advertisement field '{}' has zero length", name))
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+    }
+
+    // None when the payload is missing or too short
+    fn decode(&self, props: &PeripheralProperties) -> Option<(String, AdvertisedValue)> {
+        match self {
+            // Battery Service data carries the Battery Level characteristic value
+            AdvertisementDecoder::BatteryService => {
+                let level = *props.service_data.get(&BATTERY_SERVICE_UUID)?.first()?;
+                Some(("battery_level".to_string(), AdvertisedValue::Number(level as f64)))
+            }
+            AdvertisementDecoder::Field { name, source, position, format } => {
+                let payload = match source {
+                    AdvertisementSource::Manufacturer(company_id) => props.manufacturer_data.get(company_id)?,
+                    AdvertisementSource::Service(uuid) => props.service_data.get(uuid)?,
+                };
+                let bytes = payload.get(*position..)?;
+                let take = |length: &Option<usize>| match length {
+                    Some(length) => bytes.get(..*length),
+                    None => Some(bytes),
+                };
+                let value = match format {
+                    AdvertisedFormat::Number { field_type, endianness, scale, offset } => {
+                        if bytes.len() < field_type.size() {
+                            return None;
+                        }
+                        AdvertisedValue::Number(field_type.read(bytes, *endianness) * scale + offset)
+                    }
+                    AdvertisedFormat::Ascii { length } => {
+                        let text: String = take(length)?
+                            .iter()
+                            .take_while(|&&b| b != 0)
+                            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '?' })
+                            .collect();
+                        AdvertisedValue::Text(text)
+                    }
+                    AdvertisedFormat::Hex { length } => AdvertisedValue::Text(to_hex(take(length)?)),
+                };
+                Some((name.clone(), value))
+            }
+        }
+    }
+}
+
+pub fn decode_advertisement(decoders: &[AdvertisementDecoder], props: &PeripheralProperties) -> BTreeMap<String, AdvertisedValue> {
+    decoders.iter().filter_map(|decoder| decoder.decode(props)).collect()
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Sorted by company id so records keep a stable order between advertisements
+pub fn manufacturer_records(props: &PeripheralProperties) -> Vec<ManufacturerDataRecord> {
+    let mut records: Vec<ManufacturerDataRecord> = props
+        .manufacturer_data
+        .iter()
+        .map(|(&company_id, data)| ManufacturerDataRecord {
+            company_id,
+            company_name: company_name(company_id),
+            hex: to_hex(data),
+            base64: BASE64.encode(data),
+        })
+        .collect();
+    records.sort_by_key(|record| record.company_id);
+    records
+}
+
+pub fn service_data_records(props: &PeripheralProperties) -> Vec<ServiceDataRecord> {
+    let mut records: Vec<ServiceDataRecord> = props
+        .service_data
+        .iter()
+        .map(|(uuid, data)| ServiceDataRecord {
+            uuid: uuid.to_string(),
+            service_name: service_name(uuid),
+            hex: to_hex(data),
+            base64: BASE64.encode(data),
+        })
+        .collect();
+    records.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+    records
+}
+
+// Bluetooth SIG company identifiers commonly seen around a clinic (phones, headsets,
+// wearables) and in sensor boards
+fn company_name(company_id: u16) -> Option<&'static str> {
+    Some(match company_id {
+        0x0002 => "Intel Corp.",
+        0x0006 => "Microsoft",
+        0x000A => "Qualcomm Technologies International, Ltd.",
+        0x000D => "Texas Instruments Inc.",
+        0x000F => "Broadcom Corporation",
+        0x001D => "Qualcomm",
+        0x0030 => "ST Microelectronics",
+        0x0046 => "MediaTek, Inc.",
+        0x004C => "Apple, Inc.",
+        0x0059 => "Nordic Semiconductor ASA",
+        0x0075 => "Samsung Electronics Co. Ltd.",
+        0x0087 => "Garmin International, Inc.",
+        0x00E0 => "Google",
+        0x0171 => "Amazon.com Services, Inc.",
+        0x02E5 => "Espressif Incorporated",
+        0x0499 => "Ruuvi Innovations Ltd.",
+        0x05A7 => "Sonos Inc",
+        0x0822 => "Adafruit Industries",
+        0xFFFF => "Reserved for testing",
+        _ => return None,
+    })
+}
+
+// 16-bit SIG services that show up in service data or on our sensors
+pub fn service_name(uuid: &Uuid) -> Option<&'static str> {
+    let value = uuid.as_u128();
+    let short = value >> 96;
+    if short > 0xFFFF || value & ((1 << 96) - 1) != BLUETOOTH_BASE_UUID {
+        return None;
+    }
+    Some(match short {
+        0x180A => "Device Information",
+        0x180D => "Heart Rate",
+        0x180F => "Battery Service",
+        0x181A => "Environmental Sensing",
+        0xFE9F => "Google",
+        0xFEAA => "Eddystone",
+        _ => return None,
+    })
+}
@@ -0,0 +1,190 @@
+// Offline alignment of devices in a saved session
+//
+// Live streams are put on a common time base, but each device still has its own radio
+// latency. A sync event that every sensor feels at the same moment (a tap or a stomp
+// with both feet) shows up as a sharp transient in the accelerometer magnitude; cross
+// correlating those transients gives the residual offset of one device against another.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::transport::replay::ReplaySample;
+
+// Resampling step for the correlation grid
+const GRID_STEP_MS: f64 = 2.0;
+// Longest stretch of data correlated, measured from the start of the overlap
+const MAX_WINDOW_S: f64 = 60.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceAlignment {
+    pub device_id: String,
+    // Add to this device's timestamps to line it up with the reference device
+    pub offset_ms: f64,
+    // Peak normalized cross-correlation (1.0 = identical transients)
+    pub correlation: f64,
+}
+
+// Accelerometer magnitude on a uniform grid, linearly interpolated and clamped at the ends
+fn resample_magnitude(samples: &[ReplaySample], start_ms: f64, step_ms: f64, len: usize) -> Vec<f64> {
+    let magnitude = |s: &ReplaySample| {
+        let [_, _, _, x, y, z] = s.values;
+        ((x * x + y * y + z * z) as f64).sqrt()
+    };
+
+    let mut out = Vec::with_capacity(len);
+    let mut j = 0;
+    for i in 0..len {
+        let t = start_ms + i as f64 * step_ms;
+        while j + 1 < samples.len() && (samples[j + 1].timestamp_ms as f64) < t {
+            j += 1;
+        }
+        let a = &samples[j];
+        let value = match samples.get(j + 1) {
+            Some(b) if (a.timestamp_ms as f64) <= t && b.timestamp_ms > a.timestamp_ms => {
+                let frac = (t - a.timestamp_ms as f64) / (b.timestamp_ms - a.timestamp_ms) as f64;
+                magnitude(a) + (magnitude(b) - magnitude(a)) * frac.min(1.0)
+            }
+            _ => magnitude(a),
+        };
+        out.push(value);
+    }
+    out
+}
+
+// Absolute first difference: emphasises impacts over posture and slow movement
+fn transients(signal: &[f64]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(signal.len());
+    out.push(0.0);
+    out.extend(signal.windows(2).map(|w| (w[1] - w[0]).abs()));
+    out
+}
+
+fn pearson(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    if var_a <= 0.0 || var_b <= 0.0 {
+        0.0
+    } else {
+        cov / (var_a * var_b).sqrt()
+    }
+}
+
+// Offset (ms) to add to `other` so its accelerometer transients line up with `reference`
+pub fn align_to_reference(
+    reference: &[ReplaySample],
+    other: &[ReplaySample],
+    max_lag_ms: f64,
+) -> Result<(f64, f64), String> {
+    if reference.len() < 2 || other.len() < 2 {
+        return Err("Not enough samples to align".to_string());
+    }
+    if !max_lag_ms.is_finite() || max_lag_ms <= 0.0 {
+        return Err(format!("Invalid maximum lag: {}", max_lag_ms));
+    }
+
+    let start = reference[0].timestamp_ms.max(other[0].timestamp_ms) as f64;
+    let end = reference[reference.len() - 1].timestamp_ms.min(other[other.len() - 1].timestamp_ms) as f64;
+    let end = end.min(start + MAX_WINDOW_S * 1000.0);
+    let max_lag = (max_lag_ms / GRID_STEP_MS).ceil() as usize;
+    let len = ((end - start) / GRID_STEP_MS) as usize;
+    if len < 2 * max_lag + 2 {
+        return Err("Devices do not overlap long enough to align".to_string());
+    }
+
+    let reference_signal = transients(&resample_magnitude(reference, start, GRID_STEP_MS, len));
+    // The other device is resampled with extra margin on both sides for the lag search
+    let margin = max_lag as f64 * GRID_STEP_MS;
+    let other_signal = transients(&resample_magnitude(other, start - margin, GRID_STEP_MS, len + 2 * max_lag));
+
+    // Lag k: other's event at t + k*step matches reference's event at t
+    let correlations: Vec<f64> = (0..=2 * max_lag)
+        .map(|shift| pearson(&reference_signal, &other_signal[shift..shift + len]))
+        .collect();
+    let (best, &peak) = correlations
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .expect("lag range is never empty");
+
+    // Parabolic interpolation between neighbouring lags for sub-grid resolution
+    let refinement = if best > 0 && best < correlations.len() - 1 {
+        let (l, c, r) = (correlations[best - 1], peak, correlations[best + 1]);
+        let denominator = l - 2.0 * c + r;
+        if denominator.abs() > f64::EPSILON {
+            (0.5 * (l - r) / denominator).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+
+    let lag_ms = (best as f64 - max_lag as f64 + refinement) * GRID_STEP_MS;
+    Ok((-lag_ms, peak))
+}
+
+// A timestamp (ms) moved by an alignment offset, clamped at zero
+pub fn shift_ms(timestamp_ms: u64, offset_ms: f64) -> u64 {
+    (timestamp_ms as f64 + offset_ms).round().max(0.0) as u64
+}
+
+// Moves each device's samples by its stored offset; devices without one are left as they are
+pub fn apply_time_offsets(per_device: &mut BTreeMap<String, Vec<ReplaySample>>, time_offsets_ms: &HashMap<String, f64>) {
+    for (device_id, samples) in per_device.iter_mut() {
+        let Some(&offset_ms) = time_offsets_ms.get(device_id).filter(|offset| **offset != 0.0) else {
+            continue;
+        };
+        for sample in samples.iter_mut() {
+            sample.timestamp_ms = shift_ms(sample.timestamp_ms, offset_ms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 100 Hz of gravity with a tap at `tap_ms`
+    fn device(first_ms: u64, tap_ms: u64) -> Vec<ReplaySample> {
+        (0..1000)
+            .map(|i| {
+                let timestamp_ms = first_ms + i * 10;
+                let z = if timestamp_ms.abs_diff(tap_ms) < 15 { 3.0 } else { 1.0 };
+                ReplaySample { timestamp_ms, values: [0.0, 0.0, 0.0, 0.0, 0.0, z] }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn applied_offsets_line_devices_up() {
+        let reference = device(0, 4000);
+        // The second device stamped the same tap 60 ms late
+        let other = device(5, 4060);
+        let (offset_ms, correlation) = align_to_reference(&reference, &other, 500.0).unwrap();
+        assert!((offset_ms + 60.0).abs() < 5.0, "offset {}", offset_ms);
+        assert!(correlation > 0.5, "correlation {}", correlation);
+
+        let mut per_device = BTreeMap::from([("left".to_string(), reference), ("right".to_string(), other)]);
+        let offsets = HashMap::from([("left".to_string(), 0.0), ("right".to_string(), offset_ms)]);
+        apply_time_offsets(&mut per_device, &offsets);
+        assert_eq!(per_device["left"][0].timestamp_ms, 0);
+        assert_eq!(per_device["right"][0].timestamp_ms, shift_ms(5, offset_ms));
+        let (residual_ms, _) = align_to_reference(&per_device["left"], &per_device["right"], 500.0).unwrap();
+        assert!(residual_ms.abs() < 5.0, "residual {}", residual_ms);
+    }
+
+    #[test]
+    fn shifts_clamp_at_zero() {
+        assert_eq!(shift_ms(1000, -12.4), 988);
+        assert_eq!(shift_ms(1000, 12.6), 1013);
+        assert_eq!(shift_ms(5, -10.0), 0);
+    }
+}
@@ -0,0 +1,461 @@
+// C3D export of saved sessions
+//
+// Motion analysis software reads C3D trials: a 512-byte header block, parameter blocks
+// of named groups, then frames of 3D points followed by analog samples. A session has no
+// points, so every frame holds one sample of each analog channel; each device channel
+// (r1, r2, r3, x, y, z) is one analog channel. All analog channels share one rate, so every
+// device is resampled by linear interpolation onto the highest of the devices' measured
+// rates, starting at the session's first sample (devices recorded at that rate only have
+// their timing evened out). Samples are stored as Intel floats (POINT:SCALE negative)
+// with unit scale and zero offset, so they are the physical values. Besides the required
+// POINT, ANALOG and TRIAL groups, SUBJECTS names the subject and the SESSION and DEVICES
+// groups carry the session name, start time and notes and each device's id, alias and
+// measured rate.
+
+use chrono::{DateTime, Local};
+
+use crate::edf_export::{mean_rate_hz, resample};
+use crate::session_file::CHANNELS;
+use crate::transport::replay::ReplaySample;
+
+const BLOCK_BYTES: usize = 512;
+const PARAMETER_START_BLOCK: u8 = 2;
+const KEY: u8 = 0x50;
+// Intel byte order and IEEE floats
+const PROCESSOR_INTEL: u8 = 84;
+// Dimensions are single bytes, so strings and arrays hold at most this many entries
+const MAX_DIMENSION: usize = 255;
+// The header's frame numbers are 16-bit; TRIAL:ACTUAL_END_FIELD holds longer trials
+const MAX_HEADER_FRAME: usize = u16::MAX as usize;
+// POINT:FRAMES is a signed parameter, which readers differ on taking as unsigned, so it
+// stops at i16::MAX; readers take longer trials from the header or TRIAL:ACTUAL_END_FIELD
+const MAX_POINT_FRAMES: usize = i16::MAX as usize;
+
+pub struct C3dDevice {
+    pub id: String,
+    // Alias, or a short form of the id, used in channel labels
+    pub label: String,
+    // In time order
+    pub samples: Vec<ReplaySample>,
+}
+
+pub struct C3dSession {
+    pub subject_id: String,
+    pub session_name: String,
+    pub notes: String,
+    pub devices: Vec<C3dDevice>,
+}
+
+enum Value {
+    Int(i16),
+    Float(f32),
+    Ints(Vec<i16>),
+    Floats(Vec<f32>),
+    Text(String),
+    Texts(Vec<String>),
+}
+
+// Parameter text is ASCII, at most one dimension long
+fn ascii(value: &str) -> String {
+    value.chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '_' })
+        .take(MAX_DIMENSION)
+        .collect()
+}
+
+// Groups and parameters are linked by the offset from each one's offset field to the next;
+// the last one's offset is 0
+struct Parameters {
+    bytes: Vec<u8>,
+    last_offset: Option<usize>,
+}
+
+impl Parameters {
+    fn new() -> Self {
+        Parameters { bytes: Vec::new(), last_offset: None }
+    }
+
+    fn item(&mut self, name: &str, id: i8, body: &[u8]) -> Result<(), String> {
+        let next = i16::try_from(2 + body.len())
+            .map_err(|_| format!("C3D parameter {} is too large", name))?;
+        self.bytes.push(name.len() as u8);
+        self.bytes.push(id as u8);
+        self.bytes.extend_from_slice(name.as_bytes());
+        self.last_offset = Some(self.bytes.len());
+        self.bytes.extend_from_slice(&next.to_le_bytes());
+        self.bytes.extend_from_slice(body);
+        Ok(())
+    }
+
+    fn group(&mut self, id: i8, name: &str, description: &str) -> Result<(), String> {
+        let description = ascii(description);
+        let mut body = vec![description.len() as u8];
+        body.extend_from_slice(description.as_bytes());
+        self.item(name, -id, &body)
+    }
+
+    fn parameter(&mut self, group: i8, name: &str, value: Value, description: &str) -> Result<(), String> {
+        let (data_type, dimensions, data): (i8, Vec<usize>, Vec<u8>) = match value {
+            Value::Int(v) => (2, vec![], v.to_le_bytes().to_vec()),
+            Value::Float(v) => (4, vec![], v.to_le_bytes().to_vec()),
+            Value::Ints(v) => (2, vec![v.len()], v.iter().flat_map(|x| x.to_le_bytes()).collect()),
+            Value::Floats(v) => (4, vec![v.len()], v.iter().flat_map(|x| x.to_le_bytes()).collect()),
+            Value::Text(v) => {
+                let v = ascii(&v);
+                (-1, vec![v.len()], v.into_bytes())
+            }
+            Value::Texts(v) => {
+                // Fixed-width columns, padded with spaces
+                let v: Vec<String> = v.iter().map(|s| ascii(s)).collect();
+                let width = v.iter().map(String::len).max().unwrap_or(0).max(1);
+                let mut data = Vec::with_capacity(width * v.len());
+                for s in &v {
+                    data.extend_from_slice(s.as_bytes());
+                    data.extend(std::iter::repeat(b' ').take(width - s.len()));
+                }
+                (-1, vec![width, v.len()], data)
+            }
+        };
+        if dimensions.iter().any(|&d| d > MAX_DIMENSION) {
+            return Err(format!("C3D parameter {} has more than {} entries", name, MAX_DIMENSION));
+        }
+
+        let description = ascii(description);
+        let mut body = vec![data_type as u8, dimensions.len() as u8];
+        body.extend(dimensions.iter().map(|&d| d as u8));
+        body.extend_from_slice(&data);
+        body.push(description.len() as u8);
+        body.extend_from_slice(description.as_bytes());
+        self.item(name, group, &body)
+    }
+
+    // The parameter section: its header, the items and padding to whole blocks
+    fn into_blocks(mut self) -> Vec<u8> {
+        if let Some(offset) = self.last_offset {
+            self.bytes[offset..offset + 2].copy_from_slice(&0i16.to_le_bytes());
+        }
+        let blocks = (4 + self.bytes.len()).div_ceil(BLOCK_BYTES);
+        let mut section = vec![1, KEY, blocks as u8, PROCESSOR_INTEL];
+        section.extend_from_slice(&self.bytes);
+        section.resize(blocks * BLOCK_BYTES, 0);
+        section
+    }
+}
+
+struct Channel {
+    label: String,
+    description: String,
+    unit: &'static str,
+    values: Vec<f64>,
+}
+
+fn parameters(session: &C3dSession, devices: &[&C3dDevice], channels: &[Channel], rate_hz: usize, frames: usize,
+    start: &DateTime<Local>, data_start: u16) -> Result<Parameters, String> {
+    let rate = rate_hz as f32;
+    let mut p = Parameters::new();
+
+    p.group(1, "POINT", "3-D point parameters")?;
+    p.parameter(1, "USED", Value::Int(0), "Number of 3-D points")?;
+    p.parameter(1, "SCALE", Value::Float(-1.0), "Negative: samples are floats")?;
+    p.parameter(1, "RATE", Value::Float(rate), "Frame rate (Hz)")?;
+    p.parameter(1, "DATA_START", Value::Int(data_start as i16), "First block of sample data")?;
+    p.parameter(1, "FRAMES", Value::Int(frames.min(MAX_POINT_FRAMES) as i16), "Number of frames")?;
+    p.parameter(1, "UNITS", Value::Text("mm".to_string()), "3-D point units")?;
+
+    p.group(2, "ANALOG", "Analog data parameters")?;
+    p.parameter(2, "USED", Value::Int(channels.len() as i16), "Number of analog channels")?;
+    p.parameter(2, "LABELS", Value::Texts(channels.iter().map(|c| c.label.clone()).collect()), "Channel labels")?;
+    p.parameter(2, "DESCRIPTIONS", Value::Texts(channels.iter().map(|c| c.description.clone()).collect()),
+        "Channel descriptions")?;
+    p.parameter(2, "GEN_SCALE", Value::Float(1.0), "Scale for all channels")?;
+    p.parameter(2, "SCALE", Value::Floats(vec![1.0; channels.len()]), "Scale per channel")?;
+    p.parameter(2, "OFFSET", Value::Ints(vec![0; channels.len()]), "Offset per channel")?;
+    p.parameter(2, "UNITS", Value::Texts(channels.iter().map(|c| c.unit.to_string()).collect()), "Channel units")?;
+    p.parameter(2, "RATE", Value::Float(rate), "Analog sample rate (Hz)")?;
+    p.parameter(2, "FORMAT", Value::Text("SIGNED".to_string()), "Sample format")?;
+    p.parameter(2, "BITS", Value::Int(16), "Converter resolution")?;
+
+    p.group(3, "TRIAL", "Trial parameters")?;
+    p.parameter(3, "ACTUAL_START_FIELD", Value::Ints(vec![1, 0]), "First frame (low and high word)")?;
+    p.parameter(3, "ACTUAL_END_FIELD", Value::Ints(vec![frames as u16 as i16, (frames >> 16) as u16 as i16]),
+        "Last frame (low and high word)")?;
+    p.parameter(3, "CAMERA_RATE", Value::Float(rate), "Frame rate (Hz)")?;
+
+    p.group(4, "SUBJECTS", "Subjects")?;
+    p.parameter(4, "USED", Value::Int(1), "Number of subjects")?;
+    p.parameter(4, "NAMES", Value::Texts(vec![session.subject_id.clone()]), "Subject ids")?;
+
+    p.group(5, "SESSION", "Recording session")?;
+    p.parameter(5, "NAME", Value::Text(session.session_name.clone()), "Session name")?;
+    p.parameter(5, "DATE", Value::Text(start.format("%Y-%m-%d").to_string()), "Date of the first frame")?;
+    p.parameter(5, "TIME", Value::Text(start.format("%H:%M:%S%.3f").to_string()), "Local time of the first frame")?;
+    p.parameter(5, "NOTES", Value::Text(session.notes.clone()), "Session notes")?;
+
+    p.group(6, "DEVICES", "Sensor devices, 6 analog channels each")?;
+    p.parameter(6, "USED", Value::Int(devices.len() as i16), "Number of devices")?;
+    p.parameter(6, "IDS", Value::Texts(devices.iter().map(|d| d.id.clone()).collect()), "Device ids")?;
+    p.parameter(6, "ALIASES", Value::Texts(devices.iter().map(|d| d.label.clone()).collect()), "Device aliases")?;
+    p.parameter(6, "RATES", Value::Floats(devices.iter().map(|d| mean_rate_hz(&d.samples) as f32).collect()),
+        "Measured sample rate per device (Hz)")?;
+
+    p.group(7, "MANUFACTURER", "Writer of the file")?;
+    p.parameter(7, "COMPANY", Value::Text("GaitMonitor".to_string()), "Company")?;
+    p.parameter(7, "SOFTWARE", Value::Text("GaitMonitor".to_string()), "Software")?;
+    p.parameter(7, "VERSION", Value::Text(env!("CARGO_PKG_VERSION").to_string()), "Software version")?;
+    Ok(p)
+}
+
+pub fn encode(session: &C3dSession) -> Result<Vec<u8>, String> {
+    let devices: Vec<&C3dDevice> = session.devices.iter().filter(|d| !d.samples.is_empty()).collect();
+    if devices.is_empty() {
+        return Err("No samples to export".to_string());
+    }
+    let start_ms = devices.iter().map(|d| d.samples[0].timestamp_ms).min().unwrap_or(0);
+    let last_ms = devices.iter().map(|d| d.samples[d.samples.len() - 1].timestamp_ms).max().unwrap_or(0);
+    let rate_hz = devices.iter().map(|d| mean_rate_hz(&d.samples)).max().unwrap_or(1);
+    let frames = ((last_ms - start_ms) as f64 * rate_hz as f64 / 1000.0).round() as usize + 1;
+
+    let mut channels = Vec::new();
+    for device in &devices {
+        for (channel, (name, unit)) in CHANNELS.iter().enumerate() {
+            channels.push(Channel {
+                label: format!("{}_{}", device.label, name).replace(' ', "_"),
+                description: format!("{} {} of {}", if *unit == "g" { "Acceleration" } else { "Resistance" }, name, device.id),
+                unit: if *unit == "g" { "g" } else { "Ohm" },
+                values: resample(&device.samples, channel, start_ms, rate_hz, frames),
+            });
+        }
+    }
+    if channels.len() > MAX_DIMENSION {
+        return Err(format!("C3D files hold at most {} analog channels", MAX_DIMENSION));
+    }
+
+    let start = DateTime::from_timestamp_millis(start_ms as i64)
+        .ok_or("Session start time is out of range")?
+        .with_timezone(&Local);
+    // DATA_START is a fixed-size value, so sizing the section with a placeholder is exact
+    let parameter_blocks = parameters(session, &devices, &channels, rate_hz, frames, &start, 0)?
+        .into_blocks().len() / BLOCK_BYTES;
+    let data_start = PARAMETER_START_BLOCK as usize + parameter_blocks;
+    let data_start = u16::try_from(data_start).map_err(|_| "C3D parameter section is too large".to_string())?;
+    let parameter_section = parameters(session, &devices, &channels, rate_hz, frames, &start, data_start)?.into_blocks();
+
+    let data_bytes = frames * channels.len() * 4;
+    let mut bytes = Vec::with_capacity(BLOCK_BYTES + parameter_section.len() + data_bytes.next_multiple_of(BLOCK_BYTES));
+    // Header block, in 16-bit words
+    let mut header = [0u8; BLOCK_BYTES];
+    header[0] = PARAMETER_START_BLOCK;
+    header[1] = KEY;
+    header[2..4].copy_from_slice(&0u16.to_le_bytes());
+    header[4..6].copy_from_slice(&(channels.len() as u16).to_le_bytes());
+    header[6..8].copy_from_slice(&1u16.to_le_bytes());
+    header[8..10].copy_from_slice(&(frames.min(MAX_HEADER_FRAME) as u16).to_le_bytes());
+    header[10..12].copy_from_slice(&0u16.to_le_bytes());
+    header[12..16].copy_from_slice(&(-1.0f32).to_le_bytes());
+    header[16..18].copy_from_slice(&data_start.to_le_bytes());
+    header[18..20].copy_from_slice(&1u16.to_le_bytes());
+    header[20..24].copy_from_slice(&(rate_hz as f32).to_le_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&parameter_section);
+
+    // One analog sample per channel per frame
+    for frame in 0..frames {
+        for channel in &channels {
+            bytes.extend_from_slice(&(channel.values[frame] as f32).to_le_bytes());
+        }
+    }
+    bytes.resize(bytes.len().next_multiple_of(BLOCK_BYTES), 0);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // A parameter's dimensions and raw data, as stored
+    struct ReadParameter {
+        dimensions: Vec<usize>,
+        data: Vec<u8>,
+    }
+
+    impl ReadParameter {
+        fn int(&self) -> i16 {
+            i16::from_le_bytes(self.data[..2].try_into().unwrap())
+        }
+
+        fn ints(&self) -> Vec<i16> {
+            self.data.chunks_exact(2).map(|v| i16::from_le_bytes(v.try_into().unwrap())).collect()
+        }
+
+        fn float(&self) -> f32 {
+            f32::from_le_bytes(self.data[..4].try_into().unwrap())
+        }
+
+        fn texts(&self) -> Vec<String> {
+            self.data.chunks(self.dimensions[0]).map(|s| String::from_utf8_lossy(s).trim_end().to_string()).collect()
+        }
+    }
+
+    struct ReadFile {
+        channels: usize,
+        first_frame: u16,
+        last_frame: u16,
+        rate: f32,
+        data_start: u16,
+        // "GROUP:NAME"
+        parameters: HashMap<String, ReadParameter>,
+        // Analog samples, frame by frame
+        frames: Vec<Vec<f32>>,
+    }
+
+    impl ReadFile {
+        fn parameter(&self, name: &str) -> &ReadParameter {
+            self.parameters.get(name).unwrap_or_else(|| panic!("missing {}", name))
+        }
+    }
+
+    fn word(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    // Parses the header words, follows the parameter section's linked list of groups and
+    // parameters, and reads the float analog frames
+    fn read(bytes: &[u8]) -> ReadFile {
+        assert_eq!(bytes.len() % BLOCK_BYTES, 0);
+        assert_eq!(bytes[1], KEY);
+        let channels = word(bytes, 4) as usize;
+        assert_eq!(word(bytes, 6), 1, "analog samples per frame");
+        let data_start = word(bytes, 16);
+
+        // Follow each item's offset field to the next; groups are negative ids
+        let section = (bytes[0] as usize - 1) * BLOCK_BYTES;
+        assert_eq!(bytes[section + 3], PROCESSOR_INTEL);
+        let mut at = section + 4;
+        let mut groups = HashMap::new();
+        let mut items = Vec::new();
+        loop {
+            let name_len = (bytes[at] as i8).unsigned_abs() as usize;
+            let id = bytes[at + 1] as i8;
+            let name = String::from_utf8(bytes[at + 2..at + 2 + name_len].to_vec()).unwrap();
+            let offset_at = at + 2 + name_len;
+            let next = i16::from_le_bytes(bytes[offset_at..offset_at + 2].try_into().unwrap());
+            let body = &bytes[offset_at + 2..];
+            if id < 0 {
+                groups.insert(-id, name);
+            } else {
+                let element = (body[0] as i8).unsigned_abs() as usize;
+                let dimensions: Vec<usize> = body[2..2 + body[1] as usize].iter().map(|&d| d as usize).collect();
+                let len = element * dimensions.iter().product::<usize>();
+                let data = body[2 + dimensions.len()..2 + dimensions.len() + len].to_vec();
+                items.push((id, name, ReadParameter { dimensions, data }));
+            }
+            if next == 0 {
+                break;
+            }
+            at = offset_at + next as usize;
+        }
+        let parameters = items.into_iter().map(|(id, name, parameter)| (format!("{}:{}", groups[&id], name), parameter)).collect();
+
+        let data = &bytes[(data_start as usize - 1) * BLOCK_BYTES..];
+        let frames = (word(bytes, 8) - word(bytes, 6) + 1) as usize;
+        let frames = (0..frames)
+            .map(|frame| (0..channels)
+                .map(|c| {
+                    let at = (frame * channels + c) * 4;
+                    f32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+                })
+                .collect())
+            .collect();
+
+        ReadFile {
+            channels,
+            first_frame: word(bytes, 6),
+            last_frame: word(bytes, 8),
+            rate: f32::from_le_bytes(bytes[20..24].try_into().unwrap()),
+            data_start,
+            parameters,
+            frames,
+        }
+    }
+
+    const START_MS: u64 = 1_700_000_000_000;
+
+    fn device(id: &str, label: &str, period_ms: u64, count: u64, value: impl Fn(u64, usize) -> f32) -> C3dDevice {
+        C3dDevice {
+            id: id.to_string(),
+            label: label.to_string(),
+            samples: (0..count)
+                .map(|i| ReplaySample { timestamp_ms: START_MS + i * period_ms, values: std::array::from_fn(|c| value(i, c)) })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        // 100 Hz, and 50 Hz with values linear in time so interpolating onto 100 Hz is exact
+        let session = C3dSession {
+            subject_id: "Subject 7".to_string(),
+            session_name: "Walk test".to_string(),
+            notes: String::new(),
+            devices: vec![
+                device("02:5A:17:00:B0:01", "Left foot", 10, 200, |i, c| (i as f32 * 0.37 + c as f32).sin() * 100.0),
+                device("02:5A:17:00:B0:02", "B0:02", 20, 100, |i, c| (i * 2) as f32 * (c + 1) as f32),
+            ],
+        };
+        let file = read(&encode(&session).unwrap());
+
+        assert_eq!(file.channels, 12);
+        assert_eq!((file.first_frame, file.last_frame), (1, 200));
+        assert_eq!(file.rate, 100.0);
+        assert_eq!(file.parameter("POINT:DATA_START").int() as u16, file.data_start);
+        assert_eq!(file.parameter("POINT:USED").int(), 0);
+        assert_eq!(file.parameter("POINT:SCALE").float(), -1.0);
+        assert_eq!(file.parameter("POINT:FRAMES").int(), 200);
+        assert_eq!(file.parameter("ANALOG:USED").int(), 12);
+        assert_eq!(file.parameter("ANALOG:RATE").float(), 100.0);
+        let labels = file.parameter("ANALOG:LABELS").texts();
+        assert_eq!(labels.len(), 12);
+        assert_eq!(labels[0], "Left_foot_r1");
+        assert_eq!(labels[5], "Left_foot_z");
+        assert_eq!(labels[6], "B0:02_r1");
+        let units = file.parameter("ANALOG:UNITS").texts();
+        assert_eq!((units[0].as_str(), units[3].as_str()), ("Ohm", "g"));
+        assert_eq!(file.parameter("TRIAL:ACTUAL_END_FIELD").ints(), vec![200, 0]);
+        assert_eq!(file.parameter("SUBJECTS:NAMES").texts(), vec!["Subject 7"]);
+        assert_eq!(file.parameter("DEVICES:ALIASES").texts(), vec!["Left foot", "B0:02"]);
+
+        assert_eq!(file.frames.len(), 200);
+        for (frame, values) in file.frames.iter().enumerate() {
+            for channel in 0..6 {
+                let expected = session.devices[0].samples[frame].values[channel];
+                assert!((values[channel] - expected).abs() < 1e-3, "frame {} channel {}", frame, channel);
+            }
+            // The 50 Hz device ends at frame 198 and is held after its last sample
+            for channel in 0..6 {
+                let expected = frame.min(198) as f32 * (channel + 1) as f32;
+                assert!((values[6 + channel] - expected).abs() < 1e-3, "frame {} channel {}", frame, 6 + channel);
+            }
+        }
+    }
+
+    #[test]
+    fn long_trials_keep_their_frame_count_in_the_trial_group() {
+        let session = C3dSession {
+            subject_id: "S".to_string(),
+            session_name: "Long".to_string(),
+            notes: String::new(),
+            devices: vec![device("02:5A:17:00:B0:01", "L", 10, 70_000, |_, _| 0.0)],
+        };
+        let bytes = encode(&session).unwrap();
+        let file = read(&bytes);
+        assert_eq!(file.last_frame, u16::MAX);
+        assert_eq!(file.parameter("POINT:FRAMES").int(), i16::MAX);
+        let end = file.parameter("TRIAL:ACTUAL_END_FIELD").ints();
+        assert_eq!(end[0] as u16 as usize + ((end[1] as u16 as usize) << 16), 70_000);
+        // Every frame is stored, beyond what the header can count
+        let data_bytes: usize = 70_000 * 6 * 4;
+        assert_eq!(bytes.len(), (file.data_start as usize - 1) * BLOCK_BYTES + data_bytes.next_multiple_of(BLOCK_BYTES));
+    }
+}
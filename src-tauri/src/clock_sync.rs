@@ -0,0 +1,225 @@
+// Host/device clock model
+//
+// Notifications reach the host in connection-event batches, so host arrival times carry
+// several milliseconds of jitter. When packets include a device timestamp we fit
+// host_ms = offset + (1 + drift) * device_ms over a sliding window and map each sample's
+// device time through that line instead. BLE latency is never negative, so after the
+// least-squares fit the line is shifted down onto the fastest-arriving packets (lower
+// envelope); the reconstructed times then track when samples were taken rather than the
+// average arrival delay.
+//
+// Integer device timestamps wrap around (a u16 millisecond counter every ~65 s), so each
+// one is unwrapped onto a continuous axis by taking the wrap nearest the previous device
+// time. A device clock that jumps back, or forward to a time the fitted line puts after
+// the sample's arrival (which it cannot precede), was reset and starts a new model; the
+// second case catches resets that unwrapping alone would read as a forward jump.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+// Keep at most one fit point per this much device time
+const MIN_POINT_SPACING_MS: f64 = 20.0;
+// ~40 s of history at the minimum spacing
+const MAX_POINTS: usize = 2000;
+// Device time needs to span this long before drift is estimated
+const MIN_DRIFT_SPAN_MS: f64 = 2000.0;
+// A device clock jumping back, or ahead of the host, by more than this is treated as a
+// device reset
+const RESET_THRESHOLD_MS: f64 = 1000.0;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClockSyncStats {
+    // host_ms - device_ms at the most recent device time
+    pub offset_ms: f64,
+    // Device clock rate error in parts per million (positive: device runs slow)
+    pub drift_ppm: f64,
+    // RMS distance of arrivals from the fitted line (arrival jitter)
+    pub residual_ms: f64,
+    // Average arrival delay above the lower envelope
+    pub mean_latency_ms: f64,
+    pub points: usize,
+    pub resets: u64,
+}
+
+pub struct ClockModel {
+    // Range of the device timestamp field, None for clocks that do not wrap
+    wrap_ms: Option<f64>,
+    // (device_ms, host_ms) relative to the anchor below
+    points: VecDeque<(f64, f64)>,
+    anchor: Option<(f64, f64)>,
+    last_device_ms: Option<f64>,
+    // host_rel = intercept + slope * device_rel
+    intercept: f64,
+    slope: f64,
+    stats: ClockSyncStats,
+}
+
+impl ClockModel {
+    pub fn new(wrap_ms: Option<f64>) -> Self {
+        Self {
+            wrap_ms: wrap_ms.filter(|&wrap| wrap > 0.0),
+            points: VecDeque::new(),
+            anchor: None,
+            last_device_ms: None,
+            intercept: 0.0,
+            slope: 1.0,
+            stats: ClockSyncStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &ClockSyncStats {
+        &self.stats
+    }
+
+    // Adds one (device time, host arrival time) observation and refits when it is kept
+    pub fn record(&mut self, device_ms: f64, host_ms: f64) {
+        let mut unwrapped = self.unwrap(device_ms);
+        if let Some(last) = self.last_device_ms {
+            let ahead_of_host = self.host_ms_at(unwrapped).is_some_and(|predicted| predicted > host_ms + RESET_THRESHOLD_MS);
+            if unwrapped < last - RESET_THRESHOLD_MS || ahead_of_host {
+                self.reset();
+                unwrapped = device_ms;
+            } else if unwrapped < last + MIN_POINT_SPACING_MS {
+                return;
+            }
+        }
+        let device_ms = unwrapped;
+        self.last_device_ms = Some(device_ms);
+
+        let (anchor_device, anchor_host) = *self.anchor.get_or_insert((device_ms, host_ms));
+        self.points.push_back((device_ms - anchor_device, host_ms - anchor_host));
+        while self.points.len() > MAX_POINTS {
+            self.points.pop_front();
+        }
+        self.fit();
+    }
+
+    // Reconstructed host time for a device timestamp, once at least one point is known
+    pub fn to_host_ms(&self, device_ms: f64) -> Option<f64> {
+        self.host_ms_at(self.unwrap(device_ms))
+    }
+
+    // The fitted line at an unwrapped device time
+    fn host_ms_at(&self, device_ms: f64) -> Option<f64> {
+        let (anchor_device, anchor_host) = self.anchor?;
+        Some(anchor_host + self.intercept + self.slope * (device_ms - anchor_device))
+    }
+
+    // A raw device timestamp on the continuous axis: the wrap nearest the last device time
+    fn unwrap(&self, device_ms: f64) -> f64 {
+        match (self.wrap_ms, self.last_device_ms) {
+            (Some(wrap), Some(last)) => device_ms + wrap * ((last - device_ms) / wrap).round(),
+            _ => device_ms,
+        }
+    }
+
+    fn reset(&mut self) {
+        let resets = self.stats.resets + 1;
+        *self = Self::new(self.wrap_ms);
+        self.stats.resets = resets;
+    }
+
+    fn fit(&mut self) {
+        let n = self.points.len() as f64;
+        let (first_device, _) = self.points[0];
+        let (last_device, _) = self.points[self.points.len() - 1];
+
+        // Least-squares slope once the window is long enough; until then assume equal rates
+        self.slope = if last_device - first_device >= MIN_DRIFT_SPAN_MS {
+            let mean_x = self.points.iter().map(|p| p.0).sum::<f64>() / n;
+            let mean_y = self.points.iter().map(|p| p.1).sum::<f64>() / n;
+            let (sxx, sxy) = self.points.iter().fold((0.0, 0.0), |(sxx, sxy), &(x, y)| {
+                (sxx + (x - mean_x) * (x - mean_x), sxy + (x - mean_x) * (y - mean_y))
+            });
+            if sxx > 0.0 {
+                sxy / sxx
+            } else {
+                1.0
+            }
+        } else {
+            1.0
+        };
+
+        // Residuals against the slope through the origin, then shift onto the lower envelope
+        let residuals: Vec<f64> = self.points.iter().map(|&(x, y)| y - self.slope * x).collect();
+        let mean_residual = residuals.iter().sum::<f64>() / n;
+        let min_residual = residuals.iter().copied().fold(f64::INFINITY, f64::min);
+        self.intercept = min_residual;
+
+        let variance = residuals.iter().map(|r| (r - mean_residual).powi(2)).sum::<f64>() / n;
+        let (anchor_device, anchor_host) = self.anchor.unwrap_or_default();
+        let latest_device = anchor_device + last_device;
+        self.stats.offset_ms = anchor_host + self.intercept + self.slope * last_device - latest_device;
+        self.stats.drift_ppm = (self.slope - 1.0) * 1e6;
+        self.stats.residual_ms = variance.sqrt();
+        self.stats.mean_latency_ms = mean_residual - min_residual;
+        self.stats.points = self.points.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Arrival delay of the i-th packet: never below `min_ms`, often well above it
+    fn latency_ms(i: u64, min_ms: f64) -> f64 {
+        min_ms + [0.0, 7.5, 3.0, 15.0, 1.0][(i % 5) as usize]
+    }
+
+    #[test]
+    fn fits_the_lower_envelope_and_drift() {
+        let mut model = ClockModel::new(None);
+        // Device clock 200 ppm slow, 1000 s behind the host, 5 ms minimum BLE latency
+        for i in 0..1000 {
+            let device_ms = 5_000.0 + i as f64 * 25.0;
+            model.record(device_ms, 1_000_000.0 + device_ms * 1.0002 + latency_ms(i, 5.0));
+        }
+
+        let stats = model.stats();
+        assert!((stats.drift_ppm - 200.0).abs() < 20.0, "drift {}", stats.drift_ppm);
+        assert!(stats.mean_latency_ms > 3.0 && stats.mean_latency_ms < 8.0, "latency {}", stats.mean_latency_ms);
+        assert_eq!(stats.resets, 0);
+        // Maps onto the fastest arrivals rather than the average one
+        let device_ms = 20_000.0;
+        let expected = 1_000_000.0 + device_ms * 1.0002 + 5.0;
+        let host_ms = model.to_host_ms(device_ms).unwrap();
+        assert!((host_ms - expected).abs() < 1.0, "{} vs {}", host_ms, expected);
+    }
+
+    #[test]
+    fn unwraps_a_wrapping_counter() {
+        // u16 millisecond counter
+        let mut model = ClockModel::new(Some(65_536.0));
+        let mut device_ms = 60_000.0;
+        for i in 0..400 {
+            model.record(device_ms % 65_536.0, 2_000_000.0 + device_ms + latency_ms(i, 4.0));
+            device_ms += 50.0;
+        }
+
+        assert_eq!(model.stats().resets, 0);
+        // 79 950 ms of device time reads 14 414 after the wrap
+        let host_ms = model.to_host_ms(14_414.0).unwrap();
+        assert!((host_ms - (2_000_000.0 + 79_950.0 + 4.0)).abs() < 1.0, "{}", host_ms);
+        // A sample from just before the wrap still maps before it
+        let host_ms = model.to_host_ms(65_500.0).unwrap();
+        assert!((host_ms - (2_000_000.0 + 65_500.0 + 4.0)).abs() < 1.0, "{}", host_ms);
+    }
+
+    #[test]
+    fn resets_when_the_device_clock_jumps_back() {
+        let mut model = ClockModel::new(Some(65_536.0));
+        for i in 0..100 {
+            model.record(30_000.0 + i as f64 * 50.0, 500_000.0 + i as f64 * 50.0);
+        }
+        // Device rebooted 35 s in: unwrapped, its clock seems to leap 30 s ahead of the host
+        model.record(100.0, 505_000.0);
+        model.record(150.0, 505_050.0);
+
+        let stats = model.stats();
+        assert_eq!(stats.resets, 1);
+        assert_eq!(stats.points, 2);
+        let host_ms = model.to_host_ms(200.0).unwrap();
+        assert!((host_ms - 505_100.0).abs() < 1e-6, "{}", host_ms);
+    }
+}
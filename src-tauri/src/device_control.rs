@@ -0,0 +1,237 @@
+// Sensor control channel
+//
+// Commands are written with response to the control characteristic of the device's
+// profile as an opcode byte and a sequence byte followed by its arguments. The firmware
+// applies them and reports the outcome in a status value, which is read back after every
+// write:
+//
+//   [last opcode, result, flags (bit 0: sampling), sample rate in Hz (u16 LE), last sequence]
+//
+// A command only counts as done once the status names its opcode and sequence with a zero
+// result and shows the requested state. The sequence tells a repeated command (zeroing
+// twice, say) apart from the status the previous one left behind.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+use btleplug::api::{CharPropFlags, WriteType};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::device_info::find_characteristic;
+use crate::transport::{SensorDevice, GAIT_CONTROL_CHARACTERISTIC_UUID};
+
+const STATUS_LEN: usize = 6;
+// The firmware may still be applying the command when the status is first read
+const STATUS_READ_ATTEMPTS: u32 = 3;
+const STATUS_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+// Sequence byte of the next command, shared by all devices; repeats every 256 commands
+static NEXT_SEQUENCE: AtomicU8 = AtomicU8::new(0);
+
+pub const DEFAULT_IDENTIFY_SECONDS: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlOpcodes {
+    pub start_sampling: u8,
+    pub stop_sampling: u8,
+    pub set_sample_rate: u8,
+    pub identify: u8,
+    pub zero: u8,
+}
+
+impl Default for ControlOpcodes {
+    fn default() -> Self {
+        Self {
+            start_sampling: 0x01,
+            stop_sampling: 0x02,
+            set_sample_rate: 0x03,
+            identify: 0x04,
+            zero: 0x05,
+        }
+    }
+}
+
+fn default_min_sample_rate() -> u16 {
+    1
+}
+
+fn default_max_sample_rate() -> u16 {
+    1000
+}
+
+// Control characteristic of a device profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlSpec {
+    pub characteristic_uuid: Uuid,
+    // Characteristic holding the status; the control characteristic itself when unset
+    #[serde(default)]
+    pub status_characteristic_uuid: Option<Uuid>,
+    #[serde(default)]
+    pub opcodes: ControlOpcodes,
+    #[serde(default = "default_min_sample_rate")]
+    pub min_sample_rate_hz: u16,
+    #[serde(default = "default_max_sample_rate")]
+    pub max_sample_rate_hz: u16,
+}
+
+impl ControlSpec {
+    // Control characteristic of the gaitBLE firmware
+    pub fn gaitble_default() -> Self {
+        Self {
+            characteristic_uuid: GAIT_CONTROL_CHARACTERISTIC_UUID,
+            status_characteristic_uuid: None,
+            opcodes: ControlOpcodes::default(),
+            min_sample_rate_hz: default_min_sample_rate(),
+            max_sample_rate_hz: default_max_sample_rate(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_sample_rate_hz == 0 || self.min_sample_rate_hz > self.max_sample_rate_hz {
+            return Err(format!(
+                "Invalid control sample rate range: {} - {} Hz",
+                self.min_sample_rate_hz, self.max_sample_rate_hz
+            ));
+        }
+        let o = &self.opcodes;
+        let mut opcodes = vec![o.start_sampling, o.stop_sampling, o.set_sample_rate, o.identify, o.zero];
+        opcodes.sort_unstable();
+        opcodes.dedup();
+        if opcodes.len() != 5 {
+            return Err("Control opcodes must be distinct".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    StartSampling,
+    StopSampling,
+    SetSampleRate(u16),
+    // Blink the LED for this many seconds
+    Identify(u8),
+    // Tare the resistive channels
+    Zero,
+}
+
+impl ControlCommand {
+    fn opcode(self, opcodes: &ControlOpcodes) -> u8 {
+        match self {
+            ControlCommand::StartSampling => opcodes.start_sampling,
+            ControlCommand::StopSampling => opcodes.stop_sampling,
+            ControlCommand::SetSampleRate(_) => opcodes.set_sample_rate,
+            ControlCommand::Identify(_) => opcodes.identify,
+            ControlCommand::Zero => opcodes.zero,
+        }
+    }
+
+    fn encode(self, opcodes: &ControlOpcodes, sequence: u8) -> Vec<u8> {
+        let mut data = vec![self.opcode(opcodes), sequence];
+        match self {
+            ControlCommand::SetSampleRate(rate) => data.extend_from_slice(&rate.to_le_bytes()),
+            ControlCommand::Identify(seconds) => data.push(seconds),
+            _ => {}
+        }
+        data
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ControlStatus {
+    pub last_opcode: u8,
+    pub result: u8,
+    pub sampling: bool,
+    pub sample_rate_hz: u16,
+    // Sequence byte of the last command
+    pub sequence: u8,
+}
+
+impl ControlStatus {
+    pub fn parse(value: &[u8]) -> Result<Self, String> {
+        if value.len() < STATUS_LEN {
+            return Err(format!("Invalid control status length: {} (expected {})", value.len(), STATUS_LEN));
+        }
+        Ok(Self {
+            last_opcode: value[0],
+            result: value[1],
+            sampling: value[2] & 0x01 != 0,
+            sample_rate_hz: u16::from_le_bytes([value[3], value[4]]),
+            sequence: value[5],
+        })
+    }
+
+    // Whether the status reflects the command, or why it does not
+    fn verify(&self, command: ControlCommand) -> Result<(), String> {
+        match self.result {
+            0 => {}
+            1 => return Err("Device does not support this command".to_string()),
+            2 => return Err("Device rejected the command arguments".to_string()),
+            code => return Err(format!("Device reported error code {}", code)),
+        }
+        match command {
+            ControlCommand::StartSampling if !self.sampling => Err("Device did not start sampling".to_string()),
+            ControlCommand::StopSampling if self.sampling => Err("Device did not stop sampling".to_string()),
+            ControlCommand::SetSampleRate(rate) if self.sample_rate_hz != rate => Err(format!(
+                "Device reports {} Hz after requesting {} Hz",
+                self.sample_rate_hz, rate
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Writes a command and confirms it through the status read-back
+pub async fn send_control_command(
+    device: &SensorDevice,
+    spec: &ControlSpec,
+    command: ControlCommand,
+) -> Result<ControlStatus, String> {
+    if let ControlCommand::SetSampleRate(rate) = command {
+        if !(spec.min_sample_rate_hz..=spec.max_sample_rate_hz).contains(&rate) {
+            return Err(format!(
+                "Sample rate {} Hz is outside the supported range {} - {} Hz",
+                rate, spec.min_sample_rate_hz, spec.max_sample_rate_hz
+            ));
+        }
+    }
+
+    if device.services().is_empty() {
+        device.discover_services().await?;
+    }
+    let control = find_characteristic(device, spec.characteristic_uuid)
+        .ok_or_else(|| format!("Device {} has no control characteristic {}", device.id(), spec.characteristic_uuid))?;
+    if !control.properties.contains(CharPropFlags::WRITE) {
+        return Err(format!("Control characteristic {} does not accept writes with response", control.uuid));
+    }
+    let status_characteristic = match spec.status_characteristic_uuid {
+        Some(uuid) => find_characteristic(device, uuid)
+            .ok_or_else(|| format!("Device {} has no status characteristic {}", device.id(), uuid))?,
+        None => control.clone(),
+    };
+
+    let opcode = command.opcode(&spec.opcodes);
+    let sequence = NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    device.write(&control, &command.encode(&spec.opcodes, sequence), WriteType::WithResponse).await
+        .map_err(|e| format!("Failed to send control command: {}", e))?;
+
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let value = device.read(&status_characteristic).await
+            .map_err(|e| format!("Failed to read control status: {}", e))?;
+        let status = ControlStatus::parse(&value)?;
+        if status.last_opcode == opcode && status.sequence == sequence {
+            status.verify(command)?;
+            return Ok(status);
+        }
+        if attempts >= STATUS_READ_ATTEMPTS {
+            return Err(format!(
+                "Device did not acknowledge command 0x{:02x} #{} (status reports 0x{:02x} #{})",
+                opcode, sequence, status.last_opcode, status.sequence
+            ));
+        }
+        async_std::task::sleep(STATUS_RETRY_DELAY).await;
+    }
+}
@@ -0,0 +1,95 @@
+// Battery Service (0x180F) and Device Information Service (0x180A) readout
+//
+// Read once after connecting; the battery level is then kept current through
+// notifications when the sensor supports them. Boards without these services simply
+// report nothing.
+
+use btleplug::api::{CharPropFlags, Characteristic};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::transport::{
+    SensorDevice, BATTERY_LEVEL_UUID, FIRMWARE_REVISION_UUID, HARDWARE_REVISION_UUID, MANUFACTURER_NAME_UUID,
+    MODEL_NUMBER_UUID, SERIAL_NUMBER_UUID,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    // Percent
+    pub battery_level: Option<u8>,
+    // Epoch milliseconds of the last battery reading or notification
+    pub battery_updated_at: Option<u64>,
+    pub manufacturer_name: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub hardware_revision: Option<String>,
+}
+
+// What a saved session records about each sensor
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    pub serial_number: Option<String>,
+    pub firmware_revision: Option<String>,
+}
+
+impl DeviceInfo {
+    pub fn identity(&self) -> DeviceIdentity {
+        DeviceIdentity {
+            serial_number: self.serial_number.clone(),
+            firmware_revision: self.firmware_revision.clone(),
+        }
+    }
+}
+
+pub fn find_characteristic(device: &SensorDevice, uuid: Uuid) -> Option<Characteristic> {
+    device
+        .services()
+        .into_iter()
+        .flat_map(|service| service.characteristics)
+        .find(|characteristic| characteristic.uuid == uuid)
+}
+
+// The Battery Level characteristic, if the device can notify level changes
+pub fn battery_notify_characteristic(device: &SensorDevice) -> Option<Characteristic> {
+    find_characteristic(device, BATTERY_LEVEL_UUID)
+        .filter(|characteristic| characteristic.properties.contains(CharPropFlags::NOTIFY))
+}
+
+// Battery Level is a single uint8 percentage; values above 100 are reserved
+pub fn parse_battery_level(value: &[u8]) -> Option<u8> {
+    value.first().copied().filter(|level| *level <= 100)
+}
+
+// UTF-8 strings, often padded with NULs by firmware
+fn parse_text(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value).trim_end_matches('\0').trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+async fn read_characteristic(device: &SensorDevice, uuid: Uuid) -> Option<Vec<u8>> {
+    let characteristic = find_characteristic(device, uuid)?;
+    match device.read(&characteristic).await {
+        Ok(value) => Some(value),
+        Err(e) => {
+            println!("[DeviceInfo] Failed to read {} from {}: {}", uuid, device.id(), e);
+            None
+        }
+    }
+}
+
+// Reads every characteristic present; services must already be discovered
+pub async fn read_device_info(device: &SensorDevice, now_ms: u64) -> DeviceInfo {
+    let text = |value: Option<Vec<u8>>| value.as_deref().and_then(parse_text);
+    let battery_level = read_characteristic(device, BATTERY_LEVEL_UUID).await.as_deref().and_then(parse_battery_level);
+
+    DeviceInfo {
+        battery_level,
+        battery_updated_at: battery_level.map(|_| now_ms),
+        manufacturer_name: text(read_characteristic(device, MANUFACTURER_NAME_UUID).await),
+        model_number: text(read_characteristic(device, MODEL_NUMBER_UUID).await),
+        serial_number: text(read_characteristic(device, SERIAL_NUMBER_UUID).await),
+        firmware_revision: text(read_characteristic(device, FIRMWARE_REVISION_UUID).await),
+        hardware_revision: text(read_characteristic(device, HARDWARE_REVISION_UUID).await),
+    }
+}
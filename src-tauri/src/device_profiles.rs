@@ -0,0 +1,666 @@
+// Device profile registry
+//
+// A profile describes how to talk to one kind of sensor board: which advertised name it
+// uses, where the data characteristic lives and how its packets are laid out. The
+// built-in profile reproduces the original gaitBLE firmware; user profiles are persisted
+// as JSON in the app data directory.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::advertisement::AdvertisementDecoder;
+use crate::device_control::ControlSpec;
+
+pub const DEFAULT_PROFILE_ID: &str = "gaitble-v1";
+const PROFILES_FILE: &str = "device_profiles.json";
+// Channels the gait pipeline records; a profile channel outside this set would be dropped
+pub const GAIT_CHANNELS: [&str; 6] = ["r1", "r2", "r3", "x", "y", "z"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+}
+
+impl FieldType {
+    pub fn size(self) -> usize {
+        match self {
+            FieldType::I8 | FieldType::U8 => 1,
+            FieldType::I16 | FieldType::U16 => 2,
+            FieldType::I32 | FieldType::U32 | FieldType::F32 => 4,
+        }
+    }
+
+    // Number of distinct values, i.e. where a counter of this type wraps around
+    pub fn modulus(self) -> u64 {
+        1u64 << (8 * self.size())
+    }
+
+    pub fn read(self, bytes: &[u8], endianness: Endianness) -> f64 {
+        macro_rules! read_as {
+            ($t:ty, $n:expr) => {{
+                let mut raw = [0u8; $n];
+                raw.copy_from_slice(&bytes[..$n]);
+                match endianness {
+                    Endianness::Little => <$t>::from_le_bytes(raw) as f64,
+                    Endianness::Big => <$t>::from_be_bytes(raw) as f64,
+                }
+            }};
+        }
+        match self {
+            FieldType::I8 => bytes[0] as i8 as f64,
+            FieldType::U8 => bytes[0] as f64,
+            FieldType::I16 => read_as!(i16, 2),
+            FieldType::U16 => read_as!(u16, 2),
+            FieldType::I32 => read_as!(i32, 4),
+            FieldType::U32 => read_as!(u32, 4),
+            FieldType::F32 => read_as!(f32, 4),
+        }
+    }
+}
+
+// What a decoded field is used for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldRole {
+    // Sample value for the named channel
+    #[default]
+    Channel,
+    // Packet/sample counter, used as an unscaled integer
+    Sequence,
+    // Device clock in milliseconds after scale/offset are applied
+    Timestamp,
+    // Reserved bytes that are skipped
+    Padding,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PacketField {
+    // Channel this field decodes into (must name one of the profile's channels for channel fields)
+    #[serde(default)]
+    pub channel: String,
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub role: FieldRole,
+    // Decoded value = raw * scale + offset
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+impl PacketField {
+    pub fn channel(name: &str, field_type: FieldType) -> Self {
+        Self {
+            channel: name.to_string(),
+            field_type,
+            role: FieldRole::Channel,
+            scale: 1.0,
+            offset: 0.0,
+        }
+    }
+}
+
+// How many sample blocks follow the packet header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleBlocks {
+    // Exactly this many blocks per packet
+    Fixed(usize),
+    // As many whole blocks as the notification carries (at least one)
+    Variable,
+}
+
+impl Default for SampleBlocks {
+    fn default() -> Self {
+        SampleBlocks::Fixed(1)
+    }
+}
+
+// Packet layout: an optional header decoded once per packet followed by one or more
+// sample blocks, with fields packed back to back in the listed order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PacketLayout {
+    pub endianness: Endianness,
+    #[serde(default)]
+    pub header: Vec<PacketField>,
+    // Fields of one sample block
+    pub fields: Vec<PacketField>,
+    #[serde(default)]
+    pub samples: SampleBlocks,
+    // Device time between sample blocks; spreads a header timestamp (taken as the first
+    // block's time) over blocks that carry no timestamp of their own
+    #[serde(default)]
+    pub sample_interval_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedSample<'a> {
+    // Per-sample sequence/timestamp, inherited from the header when the block has none
+    pub sequence: Option<u64>,
+    pub device_timestamp_ms: Option<f64>,
+    pub values: Vec<(&'a str, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedPacket<'a> {
+    pub sequence: Option<u64>,
+    pub device_timestamp_ms: Option<f64>,
+    pub samples: Vec<DecodedSample<'a>>,
+}
+
+// Values read from one run of fields (the header or a single sample block)
+struct DecodedFields<'a> {
+    sequence: Option<u64>,
+    device_timestamp_ms: Option<f64>,
+    values: Vec<(&'a str, f64)>,
+}
+
+impl PacketLayout {
+    pub fn header_size(&self) -> usize {
+        self.header.iter().map(|f| f.field_type.size()).sum()
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.fields.iter().map(|f| f.field_type.size()).sum()
+    }
+
+    // Range of the sequence counter, when the layout has one
+    pub fn sequence_modulus(&self) -> Option<u64> {
+        self.header
+            .iter()
+            .chain(self.fields.iter())
+            .find(|f| f.role == FieldRole::Sequence)
+            .map(|f| f.field_type.modulus())
+    }
+
+    // Range of the device timestamp in milliseconds, for integer fields that wrap around
+    pub fn timestamp_wrap_ms(&self) -> Option<f64> {
+        self.header
+            .iter()
+            .chain(self.fields.iter())
+            .find(|f| f.role == FieldRole::Timestamp && f.field_type != FieldType::F32)
+            .map(|f| f.field_type.modulus() as f64 * f.scale.abs())
+    }
+
+    // Whether the sequence counter is in the sample block, counting samples rather than packets
+    pub fn sequence_per_block(&self) -> bool {
+        self.fields.iter().any(|f| f.role == FieldRole::Sequence)
+    }
+
+    // Human readable description of the accepted notification length
+    pub fn expected_len(&self) -> String {
+        match self.samples {
+            SampleBlocks::Fixed(count) => (self.header_size() + count * self.block_size()).to_string(),
+            SampleBlocks::Variable => format!("{} + n*{}", self.header_size(), self.block_size()),
+        }
+    }
+
+    fn block_count(&self, len: usize) -> Option<usize> {
+        let header_size = self.header_size();
+        let block_size = self.block_size();
+        if len < header_size || block_size == 0 {
+            return None;
+        }
+        let body = len - header_size;
+        match self.samples {
+            SampleBlocks::Fixed(count) => (body == count * block_size).then_some(count),
+            SampleBlocks::Variable => (body > 0 && body % block_size == 0).then_some(body / block_size),
+        }
+    }
+
+    fn decode_fields<'a>(&self, fields: &'a [PacketField], data: &[u8]) -> DecodedFields<'a> {
+        let mut decoded = DecodedFields {
+            sequence: None,
+            device_timestamp_ms: None,
+            values: Vec::with_capacity(fields.len()),
+        };
+        let mut offset = 0;
+        for field in fields {
+            let raw = field.field_type.read(&data[offset..], self.endianness);
+            offset += field.field_type.size();
+            match field.role {
+                FieldRole::Channel => decoded.values.push((field.channel.as_str(), raw * field.scale + field.offset)),
+                FieldRole::Sequence => decoded.sequence = Some((raw as i64).rem_euclid(field.field_type.modulus() as i64) as u64),
+                FieldRole::Timestamp => decoded.device_timestamp_ms = Some(raw * field.scale + field.offset),
+                FieldRole::Padding => {}
+            }
+        }
+        decoded
+    }
+
+    pub fn validate(&self, channels: &[ChannelSpec]) -> Result<(), String> {
+        if self.block_size() == 0 {
+            return Err("Packet layout must contain at least one sample field".to_string());
+        }
+        if self.samples == SampleBlocks::Fixed(0) {
+            return Err("Packet layout must contain at least one sample block".to_string());
+        }
+        if self.sample_interval_ms.is_some_and(|ms| !ms.is_finite() || ms < 0.0) {
+            return Err("Sample interval must be a non-negative number of milliseconds".to_string());
+        }
+
+        // Channel values are per sample; a header carries only what is shared by its blocks
+        if self.header.iter().any(|f| f.role == FieldRole::Channel) {
+            return Err("Packet header cannot contain channel fields".to_string());
+        }
+        let all_fields = || self.header.iter().chain(self.fields.iter());
+        for field in all_fields() {
+            if field.role == FieldRole::Sequence && field.field_type == FieldType::F32 {
+                return Err("Sequence field must be an integer type".to_string());
+            }
+            if field.role == FieldRole::Channel && !channels.iter().any(|c| c.name == field.channel) {
+                return Err(format!("Packet field refers to unknown channel '{}'", field.channel));
+            }
+            if !field.scale.is_finite() || field.scale == 0.0 || !field.offset.is_finite() {
+                return Err(format!("Invalid scale/offset for packet field '{}'", field.channel));
+            }
+        }
+        for role in [FieldRole::Sequence, FieldRole::Timestamp] {
+            if all_fields().filter(|f| f.role == role).count() > 1 {
+                return Err(format!("Packet layout may contain at most one {:?} field", role).to_lowercase());
+            }
+        }
+        if !self.fields.iter().any(|f| f.role == FieldRole::Channel) {
+            return Err("Sample block must contain at least one channel field".to_string());
+        }
+        Ok(())
+    }
+
+    // Decodes one notification into its header values and sample blocks
+    pub fn decode<'a>(&'a self, data: &[u8]) -> Result<DecodedPacket<'a>, String> {
+        let blocks = self.block_count(data.len()).ok_or_else(|| {
+            format!("Invalid data length: {} (expected {})", data.len(), self.expected_len())
+        })?;
+
+        let header_size = self.header_size();
+        let block_size = self.block_size();
+        let header = self.decode_fields(&self.header, &data[..header_size]);
+        let samples = (0..blocks)
+            .map(|i| {
+                let start = header_size + i * block_size;
+                let block = self.decode_fields(&self.fields, &data[start..start + block_size]);
+                DecodedSample {
+                    sequence: block.sequence.or(header.sequence),
+                    device_timestamp_ms: block.device_timestamp_ms.or_else(|| {
+                        header.device_timestamp_ms.map(|t| t + i as f64 * self.sample_interval_ms.unwrap_or(0.0))
+                    }),
+                    values: block.values,
+                }
+            })
+            .collect();
+
+        Ok(DecodedPacket {
+            sequence: header.sequence,
+            device_timestamp_ms: header.device_timestamp_ms,
+            samples,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelSpec {
+    // One of GAIT_CHANNELS
+    pub name: String,
+    pub unit: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub id: String,
+    pub name: String,
+    // Glob matched against the advertised local name ('*' and '?' wildcards)
+    pub name_pattern: String,
+    pub service_uuid: Uuid,
+    pub characteristic_uuid: Uuid,
+    pub packet_layout: PacketLayout,
+    pub channels: Vec<ChannelSpec>,
+    // Values decoded from advertisements and shown in scan results
+    #[serde(default)]
+    pub advertisement: Vec<AdvertisementDecoder>,
+    // Command channel; profiles without one cannot be controlled from the app
+    #[serde(default)]
+    pub control: Option<ControlSpec>,
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+impl DeviceProfile {
+    // The original gaitBLE firmware: six little-endian f32s (R1, R2, R3, X, Y, Z) in 24 bytes
+    pub fn gaitble_default() -> Self {
+        let channel = |name: &str, unit: &str| ChannelSpec {
+            name: name.to_string(),
+            unit: unit.to_string(),
+        };
+        let channels = vec![
+            channel("r1", "Ω"),
+            channel("r2", "Ω"),
+            channel("r3", "Ω"),
+            channel("x", "g"),
+            channel("y", "g"),
+            channel("z", "g"),
+        ];
+        let fields = channels.iter().map(|c| PacketField::channel(&c.name, FieldType::F32)).collect();
+
+        Self {
+            id: DEFAULT_PROFILE_ID.to_string(),
+            name: "GaitBLE (24-byte float)".to_string(),
+            name_pattern: "GaitBLE_*".to_string(),
+            service_uuid: Uuid::from_u128(0x48877734_d012_40c4_81de_3ab006f71189),
+            characteristic_uuid: Uuid::from_u128(0x8c4711b4_571b_41ba_a240_73e6884a85eb),
+            packet_layout: PacketLayout {
+                endianness: Endianness::Little,
+                header: vec![],
+                fields,
+                samples: SampleBlocks::Fixed(1),
+                sample_interval_ms: None,
+            },
+            channels,
+            advertisement: vec![AdvertisementDecoder::BatteryService],
+            control: Some(ControlSpec::gaitble_default()),
+            builtin: true,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("Profile id cannot be empty".to_string());
+        }
+        if self.name.trim().is_empty() {
+            return Err("Profile name cannot be empty".to_string());
+        }
+        for (i, channel) in self.channels.iter().enumerate() {
+            if !GAIT_CHANNELS.contains(&channel.name.as_str()) {
+                return Err(format!(
+                    "Unknown channel '{}' (expected one of {})",
+                    channel.name,
+                    GAIT_CHANNELS.join(", ")
+                ));
+            }
+            if self.channels[..i].iter().any(|c| c.name == channel.name) {
+                return Err(format!("Channel '{}' is listed more than once", channel.name));
+            }
+        }
+        for decoder in &self.advertisement {
+            decoder.validate()?;
+        }
+        if let Some(control) = &self.control {
+            control.validate()?;
+        }
+        self.packet_layout.validate(&self.channels)
+    }
+
+    // 2 for a name match, 1 for an advertised service match, 0 otherwise
+    fn match_score(&self, local_name: Option<&str>, advertised_services: &[Uuid]) -> u8 {
+        if local_name.is_some_and(|name| glob_match(&self.name_pattern, name)) {
+            2
+        } else if advertised_services.contains(&self.service_uuid) {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+// Minimal glob matcher supporting '*' (any run) and '?' (any single character)
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+pub struct ProfileRegistry {
+    file_path: PathBuf,
+    user_profiles: Vec<DeviceProfile>,
+}
+
+impl ProfileRegistry {
+    // Loads user profiles from the app data directory; a missing or unreadable file
+    // leaves only the built-in profile available
+    pub fn load(app_data_dir: &Path) -> Self {
+        let file_path = app_data_dir.join(PROFILES_FILE);
+        let user_profiles = match std::fs::read_to_string(&file_path) {
+            Ok(content) => serde_json::from_str::<Vec<DeviceProfile>>(&content).unwrap_or_else(|e| {
+                println!("[Profiles] Failed to parse {:?}: {}", file_path, e);
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        println!("[Profiles] Loaded {} user profile(s)", user_profiles.len());
+        Self { file_path, user_profiles }
+    }
+
+    pub fn list(&self) -> Vec<DeviceProfile> {
+        let mut profiles = vec![DeviceProfile::gaitble_default()];
+        profiles.extend(self.user_profiles.iter().cloned());
+        profiles
+    }
+
+    pub fn get(&self, profile_id: &str) -> Option<DeviceProfile> {
+        self.list().into_iter().find(|p| p.id == profile_id)
+    }
+
+    // Picks the best profile for an advertising device; user profiles win ties over the
+    // built-in one, and the built-in profile is the fallback when nothing matches
+    pub fn resolve(&self, local_name: Option<&str>, advertised_services: &[Uuid]) -> DeviceProfile {
+        let mut best: Option<(u8, &DeviceProfile)> = None;
+        for profile in &self.user_profiles {
+            let score = profile.match_score(local_name, advertised_services);
+            if score > 0 && best.map_or(true, |(s, _)| score > s) {
+                best = Some((score, profile));
+            }
+        }
+
+        let default = DeviceProfile::gaitble_default();
+        match best {
+            Some((score, profile)) if score >= default.match_score(local_name, advertised_services) => profile.clone(),
+            _ => default,
+        }
+    }
+
+    pub async fn create(&mut self, mut profile: DeviceProfile) -> Result<DeviceProfile, String> {
+        profile.builtin = false;
+        profile.validate()?;
+        if self.get(&profile.id).is_some() {
+            return Err(format!("A profile with id '{}' already exists", profile.id));
+        }
+        let mut user_profiles = self.user_profiles.clone();
+        user_profiles.push(profile.clone());
+        self.persist(user_profiles).await?;
+        Ok(profile)
+    }
+
+    pub async fn update(&mut self, mut profile: DeviceProfile) -> Result<DeviceProfile, String> {
+        profile.builtin = false;
+        profile.validate()?;
+        let mut user_profiles = self.user_profiles.clone();
+        let existing = user_profiles
+            .iter_mut()
+            .find(|p| p.id == profile.id)
+            .ok_or_else(|| Self::not_editable(&profile.id))?;
+        *existing = profile.clone();
+        self.persist(user_profiles).await?;
+        Ok(profile)
+    }
+
+    pub async fn delete(&mut self, profile_id: &str) -> Result<(), String> {
+        let mut user_profiles = self.user_profiles.clone();
+        user_profiles.retain(|p| p.id != profile_id);
+        if user_profiles.len() == self.user_profiles.len() {
+            return Err(Self::not_editable(profile_id));
+        }
+        self.persist(user_profiles).await
+    }
+
+    fn not_editable(profile_id: &str) -> String {
+        if profile_id == DEFAULT_PROFILE_ID {
+            "The built-in profile cannot be modified".to_string()
+        } else {
+            format!("Profile not found: {}", profile_id)
+        }
+    }
+
+    // Writes `user_profiles` and only then makes them current, so a failed write leaves the
+    // registry as it was on disk
+    async fn persist(&mut self, user_profiles: Vec<DeviceProfile>) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&user_profiles)
+            .map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+        tokio::fs::write(&self.file_path, content).await
+            .map_err(|e| format!("Failed to write profiles: {}", e))?;
+        self.user_profiles = user_profiles;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(endianness: Endianness, header: Vec<PacketField>, fields: Vec<PacketField>, samples: SampleBlocks) -> PacketLayout {
+        PacketLayout { endianness, header, fields, samples, sample_interval_ms: None }
+    }
+
+    fn field(role: FieldRole, field_type: FieldType) -> PacketField {
+        PacketField { role, ..PacketField::channel("", field_type) }
+    }
+
+    fn channels() -> Vec<ChannelSpec> {
+        DeviceProfile::gaitble_default().channels
+    }
+
+    #[test]
+    fn integer_fields_in_both_byte_orders() {
+        let fields = vec![
+            PacketField::channel("r1", FieldType::I16),
+            PacketField::channel("r2", FieldType::U16),
+            PacketField::channel("r3", FieldType::I32),
+        ];
+        let little = layout(Endianness::Little, vec![], fields.clone(), SampleBlocks::Fixed(1));
+        let big = layout(Endianness::Big, vec![], fields, SampleBlocks::Fixed(1));
+
+        let mut le = Vec::new();
+        le.extend_from_slice(&(-2i16).to_le_bytes());
+        le.extend_from_slice(&60_000u16.to_le_bytes());
+        le.extend_from_slice(&(-100_000i32).to_le_bytes());
+        let mut be = Vec::new();
+        be.extend_from_slice(&(-2i16).to_be_bytes());
+        be.extend_from_slice(&60_000u16.to_be_bytes());
+        be.extend_from_slice(&(-100_000i32).to_be_bytes());
+
+        let expected = vec![("r1", -2.0), ("r2", 60_000.0), ("r3", -100_000.0)];
+        assert_eq!(little.decode(&le).unwrap().samples[0].values, expected);
+        assert_eq!(big.decode(&be).unwrap().samples[0].values, expected);
+        assert_ne!(little.decode(&be).unwrap().samples[0].values, expected);
+    }
+
+    #[test]
+    fn scale_and_offset() {
+        let fields = vec![PacketField { scale: 0.5, offset: -10.0, ..PacketField::channel("x", FieldType::I16) }];
+        let layout = layout(Endianness::Little, vec![], fields, SampleBlocks::Fixed(1));
+        assert_eq!(layout.decode(&100i16.to_le_bytes()).unwrap().samples[0].values, vec![("x", 40.0)]);
+    }
+
+    #[test]
+    fn variable_blocks_follow_the_notification_length() {
+        let layout = layout(
+            Endianness::Little,
+            vec![field(FieldRole::Sequence, FieldType::U8)],
+            vec![PacketField::channel("z", FieldType::I16)],
+            SampleBlocks::Variable,
+        );
+        assert_eq!(layout.decode(&[7, 1, 0]).unwrap().samples.len(), 1);
+        let packet = layout.decode(&[7, 1, 0, 2, 0, 3, 0]).unwrap();
+        assert_eq!(packet.samples.iter().map(|s| s.values[0].1).collect::<Vec<_>>(), vec![1.0, 2.0, 3.0]);
+
+        // A partial block, or a header without blocks
+        assert_eq!(layout.decode(&[7, 1, 0, 2]).unwrap_err(), "Invalid data length: 4 (expected 1 + n*2)");
+        assert!(layout.decode(&[7]).is_err());
+    }
+
+    #[test]
+    fn header_timestamp_is_spread_over_the_blocks() {
+        let mut layout = layout(
+            Endianness::Little,
+            vec![field(FieldRole::Timestamp, FieldType::U32)],
+            vec![PacketField::channel("x", FieldType::I8)],
+            SampleBlocks::Fixed(3),
+        );
+        layout.sample_interval_ms = Some(10.0);
+        let packet = layout.decode(&[0xe8, 0x03, 0, 0, 1, 2, 3]).unwrap();
+        assert_eq!(packet.device_timestamp_ms, Some(1000.0));
+        assert_eq!(packet.samples.iter().map(|s| s.device_timestamp_ms).collect::<Vec<_>>(),
+            vec![Some(1000.0), Some(1010.0), Some(1020.0)]);
+    }
+
+    #[test]
+    fn block_sequence_overrides_the_header_sequence() {
+        let fields = vec![PacketField::channel("x", FieldType::I8)];
+        let header_sequence = layout(Endianness::Little, vec![field(FieldRole::Sequence, FieldType::U16)], fields.clone(), SampleBlocks::Fixed(2));
+        assert!(!header_sequence.sequence_per_block());
+        let packet = header_sequence.decode(&[5, 0, 1, 2]).unwrap();
+        assert_eq!(packet.sequence, Some(5));
+        assert_eq!(packet.samples.iter().map(|s| s.sequence).collect::<Vec<_>>(), vec![Some(5), Some(5)]);
+
+        let mut block_fields = vec![field(FieldRole::Sequence, FieldType::U8)];
+        block_fields.extend(fields);
+        let block_sequence = layout(Endianness::Little, vec![], block_fields, SampleBlocks::Fixed(2));
+        assert!(block_sequence.sequence_per_block());
+        assert_eq!(block_sequence.sequence_modulus(), Some(256));
+        let packet = block_sequence.decode(&[255, 1, 0, 2]).unwrap();
+        assert_eq!(packet.sequence, None);
+        assert_eq!(packet.samples.iter().map(|s| s.sequence).collect::<Vec<_>>(), vec![Some(255), Some(0)]);
+    }
+
+    #[test]
+    fn rejects_header_channels_and_float_sequences() {
+        let fields = vec![PacketField::channel("x", FieldType::I16)];
+        let header_channel = layout(Endianness::Little, vec![PacketField::channel("y", FieldType::I16)], fields.clone(), SampleBlocks::Fixed(1));
+        assert_eq!(header_channel.validate(&channels()).unwrap_err(), "Packet header cannot contain channel fields");
+
+        for float_sequence in [
+            layout(Endianness::Little, vec![field(FieldRole::Sequence, FieldType::F32)], fields.clone(), SampleBlocks::Fixed(1)),
+            layout(Endianness::Little, vec![], vec![field(FieldRole::Sequence, FieldType::F32), fields[0].clone()], SampleBlocks::Fixed(1)),
+        ] {
+            assert_eq!(float_sequence.validate(&channels()).unwrap_err(), "Sequence field must be an integer type");
+        }
+
+        assert!(DeviceProfile::gaitble_default().validate().is_ok());
+    }
+}
@@ -0,0 +1,516 @@
+// EDF+ and BDF+ export of saved sessions
+//
+// Biomedical viewers expect every signal sampled at a fixed rate, in data records of fixed
+// duration. Each device channel (r1, r2, r3, x, y, z) becomes one signal, resampled by
+// linear interpolation onto a grid at the device's mean rate (rounded to whole samples per
+// one-second record), starting at the whole second before the first sample. The physical
+// range of a signal is the range of its values, rounded outward to what the 8-character
+// header fields can hold; EDF stores 16-bit and BDF 24-bit digital values. Annotations
+// (link gaps and other session events) go into the "EDF Annotations" signal as TALs, each
+// in the record its onset falls in; every record starts with its time-keeping TAL.
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::session_file::CHANNELS;
+use crate::transport::replay::ReplaySample;
+
+const RECORD_DURATION_S: u64 = 1;
+const HEADER_BYTES: usize = 256;
+const SIGNAL_HEADER_BYTES: usize = 256;
+// Bytes of the time-keeping TAL that opens every record, with room for long recordings
+const TIMEKEEPING_TAL_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdfFormat {
+    Edf,
+    Bdf,
+}
+
+impl EdfFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            EdfFormat::Edf => "edf",
+            EdfFormat::Bdf => "bdf",
+        }
+    }
+
+    fn sample_bytes(self) -> usize {
+        match self {
+            EdfFormat::Edf => 2,
+            EdfFormat::Bdf => 3,
+        }
+    }
+
+    fn digital_range(self) -> (i32, i32) {
+        match self {
+            EdfFormat::Edf => (i16::MIN as i32, i16::MAX as i32),
+            EdfFormat::Bdf => (-(1 << 23), (1 << 23) - 1),
+        }
+    }
+
+    fn annotations_label(self) -> &'static str {
+        match self {
+            EdfFormat::Edf => "EDF Annotations",
+            EdfFormat::Bdf => "BDF Annotations",
+        }
+    }
+}
+
+pub struct EdfDevice {
+    pub id: String,
+    // Alias, or a short form of the id, used in signal labels
+    pub label: String,
+    // In time order
+    pub samples: Vec<ReplaySample>,
+}
+
+pub struct EdfAnnotation {
+    // Epoch milliseconds
+    pub onset_ms: u64,
+    pub duration_ms: Option<u64>,
+    pub text: String,
+}
+
+pub struct EdfSession {
+    pub subject_id: String,
+    pub session_name: String,
+    pub devices: Vec<EdfDevice>,
+    pub annotations: Vec<EdfAnnotation>,
+}
+
+struct Signal {
+    label: String,
+    transducer: String,
+    dimension: &'static str,
+    // As written to the header, and parsed back for scaling
+    physical_fields: (String, String),
+    physical: (f64, f64),
+    samples_per_record: usize,
+    values: Vec<f64>,
+}
+
+// Header fields are ASCII, left aligned and padded with spaces
+fn push_field(bytes: &mut Vec<u8>, value: &str, width: usize) {
+    let ascii: String = value.chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '_' })
+        .take(width)
+        .collect();
+    bytes.extend_from_slice(ascii.as_bytes());
+    bytes.extend(std::iter::repeat(b' ').take(width - ascii.len()));
+}
+
+// EDF+ subfields are separated by spaces, so spaces within one become underscores
+fn subfield(value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() {
+        "X".to_string()
+    } else {
+        value.replace(' ', "_")
+    }
+}
+
+// The shortest number of at most 8 characters that is not inside (min) or below (max) the
+// value, so the written range still covers the data
+fn physical_field(value: f64, round_up: bool) -> String {
+    for decimals in (0..=6).rev() {
+        let scale = 10f64.powi(decimals);
+        let scaled = value * scale;
+        let rounded = if round_up { scaled.ceil() } else { scaled.floor() } / scale;
+        let text = format!("{:.*}", decimals as usize, rounded);
+        if text.len() <= 8 {
+            return text;
+        }
+    }
+    if round_up { "99999999" } else { "-9999999" }.to_string()
+}
+
+// Mean sample rate over the device's samples, rounded to whole samples per second
+pub(crate) fn mean_rate_hz(samples: &[ReplaySample]) -> usize {
+    let span_ms = match (samples.first(), samples.last()) {
+        (Some(first), Some(last)) => last.timestamp_ms - first.timestamp_ms,
+        _ => 0,
+    };
+    if span_ms == 0 {
+        return 1;
+    }
+    (((samples.len() - 1) as f64 * 1000.0 / span_ms as f64).round() as usize).max(1)
+}
+
+// Values at `count` evenly spaced times `1 / rate_hz` apart from `start_ms`, interpolated
+// linearly between samples and held at the first and last sample beyond them
+pub(crate) fn resample(samples: &[ReplaySample], channel: usize, start_ms: u64, rate_hz: usize, count: usize) -> Vec<f64> {
+    let mut values = Vec::with_capacity(count);
+    let mut next = 0;
+    for k in 0..count {
+        let t = start_ms as f64 + k as f64 * 1000.0 / rate_hz as f64;
+        while next < samples.len() && (samples[next].timestamp_ms as f64) <= t {
+            next += 1;
+        }
+        let value = match (next.checked_sub(1).map(|i| &samples[i]), samples.get(next)) {
+            (Some(before), Some(after)) if after.timestamp_ms > before.timestamp_ms => {
+                let fraction = (t - before.timestamp_ms as f64) / (after.timestamp_ms - before.timestamp_ms) as f64;
+                before.values[channel] as f64 + fraction * (after.values[channel] - before.values[channel]) as f64
+            }
+            (Some(before), _) => before.values[channel] as f64,
+            (None, Some(after)) => after.values[channel] as f64,
+            (None, None) => 0.0,
+        };
+        values.push(value);
+    }
+    values
+}
+
+// "+<onset>" in seconds with the trailing zeros of the fraction dropped
+fn tal_onset(seconds: f64) -> String {
+    let text = format!("{:.3}", seconds);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    format!("+{}", text)
+}
+
+pub fn encode(session: &EdfSession, format: EdfFormat) -> Result<Vec<u8>, String> {
+    let devices: Vec<&EdfDevice> = session.devices.iter().filter(|d| !d.samples.is_empty()).collect();
+    if devices.is_empty() {
+        return Err("No samples to export".to_string());
+    }
+    let first_ms = devices.iter().map(|d| d.samples[0].timestamp_ms).min().unwrap_or(0);
+    let last_ms = devices.iter().map(|d| d.samples[d.samples.len() - 1].timestamp_ms).max().unwrap_or(0);
+    // Records start on a whole second so the header's start time is exact
+    let start_ms = first_ms - first_ms % 1000;
+    let records = ((last_ms - start_ms) / (RECORD_DURATION_S * 1000) + 1) as usize;
+
+    let mut signals = Vec::new();
+    for device in &devices {
+        let rate_hz = mean_rate_hz(&device.samples);
+        let samples_per_record = rate_hz * RECORD_DURATION_S as usize;
+        for (channel, (name, unit)) in CHANNELS.iter().enumerate() {
+            let values = resample(&device.samples, channel, start_ms, rate_hz, records * samples_per_record);
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let mut max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            // The physical range must not be empty
+            if max <= min {
+                max = min + 1.0;
+            }
+            let physical_min = physical_field(min, false);
+            let physical_max = physical_field(max, true);
+            let physical = (physical_min.parse().unwrap_or(min), physical_max.parse().unwrap_or(max));
+            signals.push(Signal {
+                label: format!("{} {}", device.label, name),
+                transducer: format!("{} {}", if *unit == "g" { "Accelerometer" } else { "Resistive sensor" }, device.id),
+                dimension: if *unit == "g" { "g" } else { "Ohm" },
+                physical_fields: (physical_min, physical_max),
+                physical,
+                samples_per_record,
+                values,
+            });
+        }
+    }
+
+    // Time-keeping TAL of each record, then the annotations whose onset falls in it
+    let mut tals: Vec<Vec<u8>> = (0..records)
+        .map(|r| format!("{}\x14\x14\0", tal_onset((r as u64 * RECORD_DURATION_S) as f64)).into_bytes())
+        .collect();
+    for annotation in &session.annotations {
+        let onset_ms = annotation.onset_ms.saturating_sub(start_ms);
+        let record = ((onset_ms / (RECORD_DURATION_S * 1000)) as usize).min(records - 1);
+        let mut tal = tal_onset(onset_ms as f64 / 1000.0);
+        if let Some(duration_ms) = annotation.duration_ms {
+            tal.push('\x15');
+            tal.push_str(&tal_onset(duration_ms as f64 / 1000.0)[1..]);
+        }
+        let text: String = annotation.text.chars().map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '_' }).collect();
+        tal.push('\x14');
+        tal.push_str(&text);
+        tal.push_str("\x14\0");
+        tals[record].extend_from_slice(tal.as_bytes());
+    }
+    let sample_bytes = format.sample_bytes();
+    let annotation_bytes = tals.iter().map(Vec::len).max().unwrap_or(0).max(TIMEKEEPING_TAL_BYTES);
+    let annotation_samples = annotation_bytes.div_ceil(sample_bytes);
+
+    let signal_count = signals.len() + 1;
+    let header_bytes = HEADER_BYTES + signal_count * SIGNAL_HEADER_BYTES;
+    let record_bytes = (signals.iter().map(|s| s.samples_per_record).sum::<usize>() + annotation_samples) * sample_bytes;
+    let mut bytes = Vec::with_capacity(header_bytes + records * record_bytes);
+
+    let start = DateTime::from_timestamp_millis(start_ms as i64)
+        .ok_or("Session start time is out of range")?
+        .with_timezone(&Local);
+    match format {
+        EdfFormat::Edf => push_field(&mut bytes, "0", 8),
+        EdfFormat::Bdf => {
+            bytes.push(0xFF);
+            push_field(&mut bytes, "BIOSEMI", 7);
+        }
+    }
+    push_field(&mut bytes, &format!("{} X X X", subfield(&session.subject_id)), 80);
+    push_field(&mut bytes, &format!("Startdate {} {} X GaitMonitor",
+        start.format("%d-%b-%Y").to_string().to_uppercase(), subfield(&session.session_name)), 80);
+    push_field(&mut bytes, &start.format("%d.%m.%y").to_string(), 8);
+    push_field(&mut bytes, &start.format("%H.%M.%S").to_string(), 8);
+    push_field(&mut bytes, &header_bytes.to_string(), 8);
+    push_field(&mut bytes, if format == EdfFormat::Edf { "EDF+C" } else { "BDF+C" }, 44);
+    push_field(&mut bytes, &records.to_string(), 8);
+    push_field(&mut bytes, &RECORD_DURATION_S.to_string(), 8);
+    push_field(&mut bytes, &signal_count.to_string(), 4);
+
+    let (digital_min, digital_max) = format.digital_range();
+    let annotations_label = format.annotations_label();
+    // Signal header fields are stored field by field across all signals
+    for s in &signals {
+        push_field(&mut bytes, &s.label, 16);
+    }
+    push_field(&mut bytes, annotations_label, 16);
+    for s in &signals {
+        push_field(&mut bytes, &s.transducer, 80);
+    }
+    push_field(&mut bytes, "", 80);
+    for s in &signals {
+        push_field(&mut bytes, s.dimension, 8);
+    }
+    push_field(&mut bytes, "", 8);
+    for s in &signals {
+        push_field(&mut bytes, &s.physical_fields.0, 8);
+    }
+    push_field(&mut bytes, "-1", 8);
+    for s in &signals {
+        push_field(&mut bytes, &s.physical_fields.1, 8);
+    }
+    push_field(&mut bytes, "1", 8);
+    for _ in 0..signal_count {
+        push_field(&mut bytes, &digital_min.to_string(), 8);
+    }
+    for _ in 0..signal_count {
+        push_field(&mut bytes, &digital_max.to_string(), 8);
+    }
+    for _ in 0..signal_count {
+        push_field(&mut bytes, "", 80);
+    }
+    for s in &signals {
+        push_field(&mut bytes, &s.samples_per_record.to_string(), 8);
+    }
+    push_field(&mut bytes, &annotation_samples.to_string(), 8);
+    for _ in 0..signal_count {
+        push_field(&mut bytes, "", 32);
+    }
+    debug_assert_eq!(bytes.len(), header_bytes);
+
+    let digital_span = (digital_max - digital_min) as f64;
+    for (record, tal) in tals.iter().enumerate() {
+        for s in &signals {
+            let (physical_min, physical_max) = s.physical;
+            let scale = digital_span / (physical_max - physical_min);
+            let values = &s.values[record * s.samples_per_record..(record + 1) * s.samples_per_record];
+            for value in values {
+                let digital = (((value - physical_min) * scale).round() as i64 + digital_min as i64)
+                    .clamp(digital_min as i64, digital_max as i64) as i32;
+                bytes.extend_from_slice(&digital.to_le_bytes()[..sample_bytes]);
+            }
+        }
+        bytes.extend_from_slice(tal);
+        bytes.extend(std::iter::repeat(0u8).take(annotation_samples * sample_bytes - tal.len()));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A minimal reader, independent of the writer, for what the export must contain
+    struct ReadSignal {
+        label: String,
+        dimension: String,
+        physical: (f64, f64),
+        digital: (i64, i64),
+        samples_per_record: usize,
+        values: Vec<f64>,
+    }
+
+    struct ReadFile {
+        patient: String,
+        recording: String,
+        reserved: String,
+        records: usize,
+        signals: Vec<ReadSignal>,
+        annotations: Vec<(f64, Option<f64>, String)>,
+    }
+
+    fn text(bytes: &[u8]) -> String {
+        String::from_utf8_lossy(bytes).trim_end().to_string()
+    }
+
+    fn read(bytes: &[u8], sample_bytes: usize) -> ReadFile {
+        let field = |at: usize, len: usize| text(&bytes[at..at + len]);
+        let header_bytes: usize = field(184, 8).parse().unwrap();
+        let records: usize = field(236, 8).parse().unwrap();
+        let ns: usize = field(252, 4).parse().unwrap();
+        assert_eq!(header_bytes, 256 + ns * 256);
+
+        // Offsets of each signal header field, which are stored field by field
+        let mut at = 256;
+        let mut column = |width: usize| {
+            let values: Vec<String> = (0..ns).map(|i| field(at + i * width, width)).collect();
+            at += ns * width;
+            values
+        };
+        let labels = column(16);
+        let _transducers = column(80);
+        let dimensions = column(8);
+        let physical_min = column(8);
+        let physical_max = column(8);
+        let digital_min = column(8);
+        let digital_max = column(8);
+        let _prefiltering = column(80);
+        let samples = column(8);
+        let mut signals: Vec<ReadSignal> = (0..ns)
+            .map(|i| ReadSignal {
+                label: labels[i].clone(),
+                dimension: dimensions[i].clone(),
+                physical: (physical_min[i].parse().unwrap(), physical_max[i].parse().unwrap()),
+                digital: (digital_min[i].parse().unwrap(), digital_max[i].parse().unwrap()),
+                samples_per_record: samples[i].parse().unwrap(),
+                values: Vec::new(),
+            })
+            .collect();
+
+        let record_bytes: usize = signals.iter().map(|s| s.samples_per_record * sample_bytes).sum();
+        assert_eq!(bytes.len(), header_bytes + records * record_bytes);
+        let mut annotations = Vec::new();
+        for record in 0..records {
+            let mut at = header_bytes + record * record_bytes;
+            for signal in signals.iter_mut() {
+                let len = signal.samples_per_record * sample_bytes;
+                let data = &bytes[at..at + len];
+                at += len;
+                if signal.label.ends_with("Annotations") {
+                    let tals: Vec<&[u8]> = data.split(|&b| b == 0).filter(|t| !t.is_empty()).collect();
+                    // Time-keeping TAL first
+                    let keeping = String::from_utf8_lossy(tals[0]);
+                    assert_eq!(keeping, format!("+{}\x14\x14", record));
+                    for tal in &tals[1..] {
+                        let tal = String::from_utf8_lossy(tal);
+                        let parts: Vec<&str> = tal.split('\x14').collect();
+                        let mut timing = parts[0].split('\x15');
+                        let onset = timing.next().unwrap().parse().unwrap();
+                        let duration = timing.next().map(|d| d.parse().unwrap());
+                        annotations.push((onset, duration, parts[1].to_string()));
+                    }
+                    continue;
+                }
+                let (pmin, pmax) = signal.physical;
+                let (dmin, dmax) = signal.digital;
+                for sample in data.chunks_exact(sample_bytes) {
+                    let mut raw = [0u8; 4];
+                    raw[..sample_bytes].copy_from_slice(sample);
+                    // Sign-extend from the sample width
+                    let shift = 32 - 8 * sample_bytes as u32;
+                    let digital = (i32::from_le_bytes(raw) << shift) >> shift;
+                    signal.values.push(pmin + (digital as i64 - dmin) as f64 * (pmax - pmin) / (dmax - dmin) as f64);
+                }
+            }
+        }
+
+        ReadFile {
+            patient: field(8, 80),
+            recording: field(88, 80),
+            reserved: field(192, 44),
+            records,
+            signals,
+            annotations,
+        }
+    }
+
+    // Two devices at 100 and 50 Hz with samples on the export grid, so resampling keeps them
+    fn session() -> EdfSession {
+        let start_ms = 1_700_000_000_000;
+        let device = |id: &str, label: &str, period_ms: u64, count: u64, scale: f32| EdfDevice {
+            id: id.to_string(),
+            label: label.to_string(),
+            samples: (0..count)
+                .map(|i| {
+                    let t = i as f32 / count as f32;
+                    ReplaySample {
+                        timestamp_ms: start_ms + i * period_ms,
+                        values: [scale * t, 500.0 + t, -2.5, (t * 6.0).sin(), -1.0 + t, 9.81],
+                    }
+                })
+                .collect(),
+        };
+        EdfSession {
+            subject_id: "Subject 7".to_string(),
+            session_name: "Walk test".to_string(),
+            devices: vec![
+                device("02:5A:17:00:B0:01", "Left foot", 10, 250, 1200.0),
+                device("02:5A:17:00:B0:02", "B0:02", 20, 125, 80.0),
+            ],
+            annotations: vec![
+                EdfAnnotation { onset_ms: start_ms + 1250, duration_ms: Some(300), text: "Link gap Left foot".to_string() },
+                EdfAnnotation { onset_ms: start_ms + 2000, duration_ms: None, text: "Recording stopped".to_string() },
+            ],
+        }
+    }
+
+    fn round_trip(format: EdfFormat) {
+        let session = session();
+        let bytes = encode(&session, format).unwrap();
+        let sample_bytes = format.sample_bytes();
+        let file = read(&bytes, sample_bytes);
+
+        assert_eq!(file.patient, "Subject_7 X X X");
+        assert!(file.recording.starts_with("Startdate "));
+        assert!(file.recording.ends_with(" Walk_test X GaitMonitor"));
+        assert_eq!(file.reserved, if format == EdfFormat::Edf { "EDF+C" } else { "BDF+C" });
+        assert_eq!(file.records, 3);
+        assert_eq!(file.signals.len(), 13);
+        assert_eq!(file.signals[0].label, "Left foot r1");
+        assert_eq!(file.signals[0].dimension, "Ohm");
+        assert_eq!(file.signals[5].dimension, "g");
+        assert_eq!(file.signals[6].label, "B0:02 r1");
+        assert_eq!(file.signals[0].samples_per_record, 100);
+        assert_eq!(file.signals[6].samples_per_record, 50);
+
+        for (d, device) in session.devices.iter().enumerate() {
+            for channel in 0..6 {
+                let signal = &file.signals[d * 6 + channel];
+                let (pmin, pmax) = signal.physical;
+                let (dmin, dmax) = signal.digital;
+                let resolution = (pmax - pmin) / (dmax - dmin) as f64;
+                for (i, sample) in device.samples.iter().enumerate() {
+                    let expected = sample.values[channel] as f64;
+                    assert!(pmin <= expected && expected <= pmax, "{} outside {:?}", expected, signal.physical);
+                    let read = signal.values[i];
+                    assert!((read - expected).abs() <= resolution, "{}: {} vs {} (resolution {})", signal.label, read, expected, resolution);
+                }
+                // Held at the last sample to the end of the last record
+                let last = device.samples[device.samples.len() - 1].values[channel] as f64;
+                assert!((signal.values[signal.values.len() - 1] - last).abs() <= resolution);
+            }
+        }
+
+        assert_eq!(file.annotations, vec![
+            (1.25, Some(0.3), "Link gap Left foot".to_string()),
+            (2.0, None, "Recording stopped".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn edf_round_trip() {
+        round_trip(EdfFormat::Edf);
+    }
+
+    #[test]
+    fn bdf_round_trip() {
+        round_trip(EdfFormat::Bdf);
+    }
+
+    #[test]
+    fn physical_fields_cover_the_value() {
+        assert_eq!(physical_field(1.23456789, false), "1.234567");
+        assert_eq!(physical_field(1.23456789, true), "1.234568");
+        assert_eq!(physical_field(-1234.56789, false), "-1234.57");
+        assert_eq!(physical_field(123456.789, true), "123456.8");
+        assert_eq!(physical_field(-0.5, false), "-0.50000");
+    }
+}
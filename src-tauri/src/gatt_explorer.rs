@@ -0,0 +1,150 @@
+// GATT explorer for firmware bring-up
+//
+// Describes a connected device's services and characteristics as a tree and lets any
+// characteristic be read, written or watched for notifications, with values as hex.
+// btleplug 0.10 does not expose descriptors, so the tree stops at characteristics.
+
+use std::collections::BTreeSet;
+
+use btleplug::api::{CharPropFlags, Characteristic, Service};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::advertisement::{service_name, to_hex};
+use crate::transport::{
+    SensorDevice, BATTERY_LEVEL_UUID, FIRMWARE_REVISION_UUID, GAIT_CHARACTERISTIC_UUID,
+    GAIT_CONTROL_CHARACTERISTIC_UUID, GAIT_SERVICE_UUID, HARDWARE_REVISION_UUID, MANUFACTURER_NAME_UUID,
+    MODEL_NUMBER_UUID, SERIAL_NUMBER_UUID,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GattService {
+    pub uuid: String,
+    pub name: Option<&'static str>,
+    pub primary: bool,
+    pub characteristics: Vec<GattCharacteristic>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GattCharacteristic {
+    pub uuid: String,
+    pub name: Option<&'static str>,
+    // "read", "write", "write_without_response", "notify", "indicate", ...
+    pub properties: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GattValue {
+    pub hex: String,
+    // The value as text when it is printable UTF-8
+    pub text: Option<String>,
+}
+
+impl GattValue {
+    pub fn new(value: &[u8]) -> Self {
+        let text = std::str::from_utf8(value)
+            .ok()
+            .map(|text| text.trim_end_matches('\0'))
+            .filter(|text| !text.is_empty() && !text.chars().any(char::is_control))
+            .map(str::to_string);
+        Self { hex: to_hex(value), text }
+    }
+}
+
+fn gatt_service_name(uuid: &Uuid) -> Option<&'static str> {
+    match *uuid {
+        GAIT_SERVICE_UUID => Some("Gait"),
+        _ => service_name(uuid),
+    }
+}
+
+fn characteristic_name(uuid: &Uuid) -> Option<&'static str> {
+    Some(match *uuid {
+        GAIT_CHARACTERISTIC_UUID => "Gait Data",
+        GAIT_CONTROL_CHARACTERISTIC_UUID => "Gait Control",
+        BATTERY_LEVEL_UUID => "Battery Level",
+        MANUFACTURER_NAME_UUID => "Manufacturer Name String",
+        MODEL_NUMBER_UUID => "Model Number String",
+        SERIAL_NUMBER_UUID => "Serial Number String",
+        FIRMWARE_REVISION_UUID => "Firmware Revision String",
+        HARDWARE_REVISION_UUID => "Hardware Revision String",
+        _ => return None,
+    })
+}
+
+pub fn property_names(properties: CharPropFlags) -> Vec<&'static str> {
+    [
+        (CharPropFlags::BROADCAST, "broadcast"),
+        (CharPropFlags::READ, "read"),
+        (CharPropFlags::WRITE_WITHOUT_RESPONSE, "write_without_response"),
+        (CharPropFlags::WRITE, "write"),
+        (CharPropFlags::NOTIFY, "notify"),
+        (CharPropFlags::INDICATE, "indicate"),
+        (CharPropFlags::AUTHENTICATED_SIGNED_WRITES, "authenticated_signed_writes"),
+        (CharPropFlags::EXTENDED_PROPERTIES, "extended_properties"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| properties.contains(*flag))
+    .map(|(_, name)| name)
+    .collect()
+}
+
+pub fn service_tree(services: &BTreeSet<Service>) -> Vec<GattService> {
+    services
+        .iter()
+        .map(|service| GattService {
+            uuid: service.uuid.to_string(),
+            name: gatt_service_name(&service.uuid),
+            primary: service.primary,
+            characteristics: service
+                .characteristics
+                .iter()
+                .map(|characteristic| GattCharacteristic {
+                    uuid: characteristic.uuid.to_string(),
+                    name: characteristic_name(&characteristic.uuid),
+                    properties: property_names(characteristic.properties),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+// Looks a characteristic up by service and UUID, discovering services first if needed
+pub async fn find_gatt_characteristic(
+    device: &SensorDevice,
+    service_uuid: Uuid,
+    characteristic_uuid: Uuid,
+) -> Result<Characteristic, String> {
+    if device.services().is_empty() {
+        device.discover_services().await?;
+    }
+    device
+        .services()
+        .into_iter()
+        .filter(|service| service.uuid == service_uuid)
+        .flat_map(|service| service.characteristics)
+        .find(|characteristic| characteristic.uuid == characteristic_uuid)
+        .ok_or_else(|| format!("Characteristic {} not found in service {}", characteristic_uuid, service_uuid))
+}
+
+// Accepts "0a1b2c", "0a 1b 2c", "0a:1b:2c" and an optional 0x prefix
+pub fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    let trimmed = value.trim();
+    let digits: String = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed)
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':' && *c != '-')
+        .collect();
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex value: {}", value));
+    }
+    if digits.len() % 2 != 0 {
+        return Err(format!("Hex value has an odd number of digits: {}", value));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
@@ -0,0 +1,172 @@
+// Known-device registry
+//
+// Device ids are transport addresses (the MAC address for BLE), which don't tell an
+// operator which sensor is strapped where. Sensors registered here carry an alias and
+// body location that follow them into scan results and saved sessions. The registry is
+// persisted as JSON in the app data directory.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const KNOWN_DEVICES_FILE: &str = "known_devices.json";
+
+// Where the sensor is worn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceRole {
+    Left,
+    Right,
+    Pelvis,
+}
+
+impl DeviceRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeviceRole::Left => "left",
+            DeviceRole::Right => "right",
+            DeviceRole::Pelvis => "pelvis",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownDevice {
+    // Device id as reported by the transport
+    pub address: String,
+    // Operator-facing name, e.g. "Left foot #3"
+    pub alias: String,
+    #[serde(default)]
+    pub role: Option<DeviceRole>,
+    // Profile used instead of the one matched from the advertisement
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    #[serde(default)]
+    pub calibration_id: Option<String>,
+    #[serde(default)]
+    pub notes: String,
+    // Epoch milliseconds the device was last advertising or connected
+    #[serde(default)]
+    pub last_seen: Option<u64>,
+}
+
+impl KnownDevice {
+    fn validate(&self) -> Result<(), String> {
+        if self.address.trim().is_empty() {
+            return Err("Device address cannot be empty".to_string());
+        }
+        if self.alias.trim().is_empty() {
+            return Err("Device alias cannot be empty".to_string());
+        }
+        // Aliases end up in CSV headers and rows
+        if self.alias.chars().any(char::is_control) {
+            return Err("Device alias cannot contain line breaks or control characters".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub struct KnownDeviceRegistry {
+    file_path: PathBuf,
+    devices: Vec<KnownDevice>,
+}
+
+impl KnownDeviceRegistry {
+    // A missing or unreadable file starts an empty registry
+    pub fn load(app_data_dir: &Path) -> Self {
+        let file_path = app_data_dir.join(KNOWN_DEVICES_FILE);
+        let devices = match std::fs::read_to_string(&file_path) {
+            Ok(content) => serde_json::from_str::<Vec<KnownDevice>>(&content).unwrap_or_else(|e| {
+                println!("[KnownDevices] Failed to parse {:?}: {}", file_path, e);
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        println!("[KnownDevices] Loaded {} known device(s)", devices.len());
+        Self { file_path, devices }
+    }
+
+    pub fn list(&self) -> Vec<KnownDevice> {
+        self.devices.clone()
+    }
+
+    // Addresses compare case-insensitively, as platforms differ in how they print them
+    pub fn get(&self, address: &str) -> Option<&KnownDevice> {
+        self.devices.iter().find(|d| d.address.eq_ignore_ascii_case(address))
+    }
+
+    pub async fn create(&mut self, mut device: KnownDevice) -> Result<KnownDevice, String> {
+        device.address = device.address.trim().to_string();
+        device.validate()?;
+        if self.get(&device.address).is_some() {
+            return Err(format!("Device {} is already registered", device.address));
+        }
+        let mut devices = self.devices.clone();
+        devices.push(device.clone());
+        self.persist(devices).await?;
+        Ok(device)
+    }
+
+    // Keeps the recorded last-seen time unless the update carries one
+    pub async fn update(&mut self, mut device: KnownDevice) -> Result<KnownDevice, String> {
+        device.validate()?;
+        let mut devices = self.devices.clone();
+        let existing = devices
+            .iter_mut()
+            .find(|d| d.address.eq_ignore_ascii_case(device.address.trim()))
+            .ok_or_else(|| format!("Device not registered: {}", device.address))?;
+        device.address = existing.address.clone();
+        device.last_seen = device.last_seen.or(existing.last_seen);
+        *existing = device.clone();
+        self.persist(devices).await?;
+        Ok(device)
+    }
+
+    pub async fn delete(&mut self, address: &str) -> Result<(), String> {
+        let mut devices = self.devices.clone();
+        devices.retain(|d| !d.address.eq_ignore_ascii_case(address));
+        if devices.len() == self.devices.len() {
+            return Err(format!("Device not registered: {}", address));
+        }
+        self.persist(devices).await
+    }
+
+    // Updates the last-seen time in memory; returns false for unregistered devices.
+    // Advertisements arrive too often to write the file each time, so link events write
+    // it through persist_seen.
+    pub fn mark_seen(&mut self, address: &str, now_ms: u64) -> bool {
+        Self::set_last_seen(&mut self.devices, address, now_ms)
+    }
+
+    // Updates the last-seen time and writes the registry, with the times marked since
+    // the last write; returns false for unregistered devices
+    pub async fn persist_seen(&mut self, address: &str, now_ms: u64) -> Result<bool, String> {
+        let mut devices = self.devices.clone();
+        if !Self::set_last_seen(&mut devices, address, now_ms) {
+            return Ok(false);
+        }
+        self.persist(devices).await?;
+        Ok(true)
+    }
+
+    fn set_last_seen(devices: &mut [KnownDevice], address: &str, now_ms: u64) -> bool {
+        match devices.iter_mut().find(|d| d.address.eq_ignore_ascii_case(address)) {
+            Some(device) => {
+                device.last_seen = Some(now_ms);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Writes `devices` and only then makes them current, so a failed write leaves the
+    // registry as it was on disk
+    async fn persist(&mut self, devices: Vec<KnownDevice>) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&devices)
+            .map_err(|e| format!("Failed to serialize known devices: {}", e))?;
+        tokio::fs::write(&self.file_path, content).await
+            .map_err(|e| format!("Failed to write known devices: {}", e))?;
+        self.devices = devices;
+        Ok(())
+    }
+}
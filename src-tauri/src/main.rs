@@ -4,14 +4,14 @@
 )]
 
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use async_std::sync::Mutex;
-use btleplug::platform::{Peripheral, Manager};
-use uuid::Uuid;
+use btleplug::api::{CharPropFlags, PeripheralProperties, WriteType};
 use futures::stream::StreamExt;
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
+use tauri::ipc::Channel;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use sha2::{Sha256, Digest};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
@@ -20,16 +20,57 @@ use governor::{Quota, RateLimiter, DefaultDirectRateLimiter};
 use nonzero_ext::*;
 use tracing::{info, warn, error};
 
+mod advertisement;
+mod alignment;
+mod c3d_export;
+mod clock_sync;
+mod device_control;
+mod device_info;
+mod device_profiles;
+mod edf_export;
+mod gatt_explorer;
+mod known_devices;
+mod packet_loss;
+mod recording;
+mod sample_stream;
+mod scan_filter;
+mod session_content;
+mod session_file;
+mod stream_benchmark;
+mod stream_diagnostics;
+mod stream_handle;
+mod transport;
+
+use advertisement::{AdvertisedValue, ManufacturerDataRecord, ServiceDataRecord};
+use alignment::DeviceAlignment;
+use c3d_export::{C3dDevice, C3dSession};
+use clock_sync::{ClockModel, ClockSyncStats};
+use device_control::{ControlCommand, ControlStatus};
+use device_info::{DeviceIdentity, DeviceInfo};
+use device_profiles::{DeviceProfile, ProfileRegistry};
+use edf_export::{EdfAnnotation, EdfDevice, EdfFormat, EdfSession};
+use gatt_explorer::{GattService, GattValue};
+use known_devices::{DeviceRole, KnownDevice, KnownDeviceRegistry};
+use packet_loss::{PacketLossStats, SequenceEvent, SequenceTracker};
+use recording::{RecordingStatus, WriterTotals};
+use sample_stream::{BatchSample, RecordingTap, SampleSink, SampleStream, StreamSettings};
+use scan_filter::{CompiledScanFilter, DeviceScanFilter, ScanFilterStore};
+use session_content::SessionContent;
+use session_file::{DeviceDescriptor, SampleColumns, SampleTiming, SessionEncoder, SessionFile, SessionHeader};
+use stream_benchmark::StreamBenchmarkReport;
+use stream_diagnostics::{DecodeErrorStats, StreamDiagnostics, StreamDiagnosticsSnapshot};
+use stream_handle::{StreamCarryover, StreamHandle, StreamState, StreamStats};
+use sample_rate_calculator::SampleRateCalculator;
+use transport::replay::{ReplayDeviceInfo, ReplayPeripheral};
+use transport::{AdapterInfo, SensorDevice, SensorPeripheral, SensorTransport, TransportEvent, TransportKind};
+
 // Sample rate calculation module
 mod sample_rate_calculator {
-    use std::collections::{HashMap, VecDeque};
+    use std::collections::VecDeque;
     use std::time::{Duration, Instant};
 
+    // Rolling rate of one stream, owned by its consumer
     pub struct SampleRateCalculator {
-        device_rates: HashMap<String, DeviceRateCalculator>,
-    }
-
-    struct DeviceRateCalculator {
         timestamps: VecDeque<Instant>,
         window_duration: Duration,
         last_rate: f64,
@@ -39,31 +80,6 @@ mod sample_rate_calculator {
 
     impl SampleRateCalculator {
         pub fn new() -> Self {
-            Self {
-                device_rates: HashMap::new(),
-            }
-        }
-
-        pub fn record_sample(&mut self, device_id: &str) -> Option<f64> {
-            let now = Instant::now();
-            
-            let device_calculator = self.device_rates
-                .entry(device_id.to_string())
-                .or_insert_with(|| DeviceRateCalculator::new());
-            
-            device_calculator.record_sample(now)
-        }
-
-        pub fn get_current_rate(&self, device_id: &str) -> Option<f64> {
-            self.device_rates
-                .get(device_id)
-                .map(|calc| calc.last_rate)
-                .filter(|&rate| rate > 0.0)
-        }
-    }
-
-    impl DeviceRateCalculator {
-        fn new() -> Self {
             Self {
                 timestamps: VecDeque::new(),
                 window_duration: Duration::from_secs(5), // 5-second rolling window
@@ -73,7 +89,7 @@ mod sample_rate_calculator {
             }
         }
 
-        fn record_sample(&mut self, timestamp: Instant) -> Option<f64> {
+        pub fn record_sample(&mut self, timestamp: Instant) -> Option<f64> {
             self.timestamps.push_back(timestamp);
             
             let cutoff = timestamp - self.window_duration;
@@ -609,36 +625,211 @@ async fn validate_csrf_token(
 
 // Global state for devices and connections - using different wrapper structs to avoid type conflicts
 #[derive(Clone)]
-pub struct ConnectedDevicesState(Arc<Mutex<HashMap<String, Peripheral>>>);
+pub struct ConnectedDevicesState(Arc<Mutex<HashMap<String, SensorDevice>>>);
+
+#[derive(Clone)]
+pub struct DiscoveredDevicesState(Arc<Mutex<HashMap<String, SensorDevice>>>);
 
+// Continuous discovery session; every device seen since startup is kept with its last advertisement
 #[derive(Clone)]
-pub struct DiscoveredDevicesState(Arc<Mutex<HashMap<String, Peripheral>>>);
+pub struct DiscoveryState(Arc<Mutex<Discovery>>);
+
+#[derive(Default)]
+pub struct Discovery {
+  scanning: bool,
+  filter: CompiledScanFilter,
+  // Bumped on every start so a sweeper left over from an earlier session exits
+  generation: u64,
+  devices: HashMap<String, SeenDevice>,
+}
+
+struct SeenDevice {
+  info: BluetoothDeviceInfo,
+  // Advertised name and services, to check the device against a new scan filter
+  local_name: Option<String>,
+  advertised_services: Vec<uuid::Uuid>,
+  last_emit: Instant,
+  // Advertisement changed since the last device-updated event
+  pending_update: bool,
+  // device-lost was emitted; the next advertisement reports it as discovered again
+  lost: bool,
+}
+
+// Saved default scan filter
+#[derive(Clone)]
+pub struct ScanFilterState(Arc<Mutex<ScanFilterStore>>);
+
+// Minimum spacing of device-updated events per device (RSSI changes with every advertisement)
+const DEVICE_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+const DEVICE_LOST_TIMEOUT_MS: u64 = 10_000;
+const DISCOVERY_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+// Sensor backend selected at startup (real BLE adapter or simulated devices)
+#[derive(Clone)]
+pub struct TransportState(Arc<dyn SensorTransport>);
 
+// Virtual devices replaying saved sessions, listed alongside the transport's peripherals
 #[derive(Clone)]
-pub struct BluetoothManagerState(Arc<Mutex<Option<Manager>>>);
+pub struct ReplayDevicesState(Arc<Mutex<HashMap<String, Arc<ReplayPeripheral>>>>);
 
 // New state to track which devices are actively streaming data
 #[derive(Clone)]
 pub struct ActiveNotificationsState(Arc<Mutex<HashMap<String, bool>>>);
 
-// Global state for sample rate calculation
+// Handle of each device's latest stream; replaced on every start so a stream restarted
+// after a reconnect never runs alongside the old one. Kept after the stream stops so its
+// diagnostics stay available.
 #[derive(Clone)]
-pub struct SampleRateState(Arc<Mutex<sample_rate_calculator::SampleRateCalculator>>);
+pub struct StreamHandlesState(Arc<Mutex<HashMap<String, Arc<StreamHandle>>>>);
 
-impl SampleRateState {
-    fn new() -> Self {
-        Self(Arc::new(Mutex::new(sample_rate_calculator::SampleRateCalculator::new())))
-    }
+// Devices whose link dropped unexpectedly and are being reconnected by the supervisor
+#[derive(Clone)]
+pub struct ReconnectingDevicesState(Arc<Mutex<HashSet<String>>>);
+
+// Stream interruptions caused by link drops, recorded into the saved session
+#[derive(Clone)]
+pub struct StreamGapsState(Arc<Mutex<Vec<StreamGap>>>);
+
+#[derive(Clone, Serialize, serde::Deserialize)]
+struct StreamGap {
+  device_id: String,
+  // Epoch milliseconds from the link drop until the device was streaming again (or given up)
+  start_ms: u64,
+  end_ms: u64,
+  duration_ms: u64,
+  attempts: u32,
+  resumed: bool,
 }
 
-// Duplicate detection state - stores last packet data for each device
+// Payload of the device-reconnecting event, emitted before each attempt
+#[derive(Clone, Serialize)]
+struct ReconnectingEvent {
+  device_id: String,
+  attempt: u32,
+  max_attempts: u32,
+  delay_ms: u64,
+}
+
+// Payload of the device-reconnected and device-reconnect-failed events
+#[derive(Clone, Serialize)]
+struct ReconnectResultEvent {
+  device_id: String,
+  gap: StreamGap,
+}
+
+// Battery and Device Information readout of each connected device
 #[derive(Clone)]
-pub struct DuplicateDetectionState(Arc<Mutex<HashMap<String, (f32, f32, f32, f32, f32, f32)>>>);
+pub struct DeviceInfoState(Arc<Mutex<HashMap<String, DeviceInfo>>>);
 
-impl DuplicateDetectionState {
-    fn new() -> Self {
-        Self(Arc::new(Mutex::new(HashMap::new())))
+// Stop handle of each device's battery notification listener; dropping the sender ends it
+#[derive(Clone)]
+pub struct BatteryListenersState(Arc<Mutex<HashMap<String, async_std::channel::Sender<()>>>>);
+
+// Temporary GATT explorer subscriptions by device and characteristic, with the id of the
+// subscription that owns the entry; removing the entry stops its listener
+#[derive(Clone)]
+pub struct GattSubscriptionsState(Arc<Mutex<HashMap<(String, uuid::Uuid), (u64, async_std::channel::Sender<()>)>>>);
+
+static GATT_SUBSCRIPTION_IDS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+const GATT_SUBSCRIPTION_DEFAULT_MS: u64 = 30_000;
+const GATT_SUBSCRIPTION_MAX_MS: u64 = 300_000;
+
+// Payload of the gatt-notification event
+#[derive(Clone, Serialize)]
+struct GattNotificationEvent {
+  device_id: String,
+  characteristic_uuid: String,
+  hex: String,
+  timestamp: u64,
+}
+
+// Payload of the gatt-subscription-ended event
+#[derive(Clone, Serialize)]
+struct GattSubscriptionEndedEvent {
+  device_id: String,
+  characteristic_uuid: String,
+}
+
+// Payload of the device-info-updated event
+#[derive(Clone, Serialize)]
+struct DeviceInfoEvent {
+  device_id: String,
+  info: DeviceInfo,
+}
+
+// Persisted device profile registry
+#[derive(Clone)]
+pub struct DeviceProfilesState(Arc<Mutex<ProfileRegistry>>);
+
+// Persisted registry of known sensors (alias, role, assigned profile)
+#[derive(Clone)]
+pub struct KnownDevicesState(Arc<Mutex<KnownDeviceRegistry>>);
+
+// Profile resolved for each connected device
+#[derive(Clone)]
+pub struct DeviceProfileAssignmentsState(Arc<Mutex<HashMap<String, DeviceProfile>>>);
+
+// Payload of the gait-decode-error event
+#[derive(Clone, Serialize)]
+struct DecodeErrorEvent {
+  device_id: String,
+  error: String,
+  total_errors: u64,
+}
+
+// Batched sample delivery to the frontend and its settings
+#[derive(Clone)]
+pub struct SampleStreamState(Arc<Mutex<SampleStream>>);
+
+// How often the stream-diagnostics event is emitted while a device streams
+const STREAM_DIAGNOSTICS_INTERVAL: Duration = Duration::from_secs(1);
+// How often a stream's consumer publishes its diagnostics to the stream handle
+const DIAGNOSTICS_PUBLISH_INTERVAL: Duration = Duration::from_millis(250);
+// How often the watchdog checks a stream for stalls
+const STREAM_WATCHDOG_INTERVAL: Duration = Duration::from_millis(250);
+
+// Payload of the stream-state-changed event
+#[derive(Clone, Serialize)]
+struct StreamStateEvent {
+  device_id: String,
+  state: StreamState,
+  // Why the stream stalled or failed to start
+  reason: Option<String>,
+  // Epoch milliseconds of the change
+  at: u64,
+}
+
+// Recording-level time origin shared by every streaming device, so samples from
+// different devices are stamped on the same axis
+#[derive(Clone, Copy)]
+struct TimeBase {
+  instant: Instant,
+  epoch_us: u64,
+}
+
+impl TimeBase {
+  fn now() -> Self {
+    Self {
+      instant: Instant::now(),
+      epoch_us: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64,
     }
+  }
+}
+
+// Set when the first device starts streaming, cleared when the last one stops outside a
+// recording (or when the recording stops after it)
+#[derive(Clone)]
+pub struct TimeBaseState(Arc<Mutex<Option<TimeBase>>>);
+
+// Payload of the gait-packet-loss event, emitted for each gap in the sequence counter
+#[derive(Clone, Serialize)]
+struct PacketLossEvent {
+  device_id: String,
+  expected_sequence: u64,
+  received_sequence: u64,
+  lost: u64,
+  total_lost: u64,
+  loss_percent: f64,
 }
 
 // Cross-platform path configuration state
@@ -653,16 +844,44 @@ impl PathConfigState {
 }
 
 // A simple serializable struct to send back to JS
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct BluetoothDeviceInfo {
   id: String,
   name: String,
   rssi: Option<i16>,
+  // Advertised TX power level (dBm)
+  tx_power: Option<i16>,
   connectable: bool,
   address_type: String,
   services: Vec<String>,
-  manufacturer_data: Vec<String>,
-  service_data: Vec<String>,
+  manufacturer_data: Vec<ManufacturerDataRecord>,
+  service_data: Vec<ServiceDataRecord>,
+  // Values decoded by the matching device profile's advertisement decoders
+  advertised: BTreeMap<String, AdvertisedValue>,
+  // Epoch milliseconds of the most recent advertisement
+  last_seen: u64,
+  // Set for devices in the known-device registry
+  known: bool,
+  alias: Option<String>,
+  role: Option<DeviceRole>,
+}
+
+impl BluetoothDeviceInfo {
+  fn apply_known(&mut self, known: Option<&KnownDevice>) {
+    self.known = known.is_some();
+    self.alias = known.map(|d| d.alias.clone());
+    self.role = known.and_then(|d| d.role);
+  }
+
+  // Same advertised content, ignoring when it was seen
+  fn same_advertisement(&self, other: &Self) -> bool {
+    self.name == other.name
+      && self.rssi == other.rssi
+      && self.tx_power == other.tx_power
+      && self.services == other.services
+      && self.manufacturer_data == other.manufacturer_data
+      && self.service_data == other.service_data
+  }
 }
 
 // Add BLE data streaming functionality
@@ -677,6 +896,20 @@ struct GaitData {
   z: f32,
   // Millisecond timestamp retained for backward compatibility
   timestamp: u64,
+  // Device clock in milliseconds when the profile's packet layout has a timestamp field
+  #[serde(default)]
+  device_timestamp_ms: Option<f64>,
+}
+
+// Samples decoded from one notification
+struct GaitPacket {
+  // Packet counter from the header
+  sequence: Option<u64>,
+  samples: Vec<GaitData>,
+  // Per sample counters, for layouts whose sequence field is in the sample block
+  sample_sequences: Vec<Option<u64>>,
+  // Per sample, how long before the notification arrived it was taken
+  arrival_offsets_us: Vec<u64>,
 }
 
 // Enhanced GaitData to include sample rate for frontend
@@ -696,6 +929,11 @@ struct GaitDataWithRate {
   // Monotonic relative seconds since the start of the notification stream for this device
   monotonic_s: f64,
   sample_rate: Option<f64>,
+  device_timestamp_ms: Option<f64>,
+  // Device timestamp mapped onto the host clock (epoch microseconds), free of BLE batching jitter
+  reconstructed_timestamp_us: Option<u64>,
+  // Seconds since the shared recording time base; comparable across devices
+  session_time_s: f64,
 }
 
 // Rate limiting structure
@@ -743,14 +981,323 @@ impl RateLimitingState {
   }
 }
 
+async fn device_properties(p: &SensorDevice) -> Option<PeripheralProperties> {
+  match p.properties().await {
+    Ok(Some(props)) => Some(props),
+    Ok(None) => {
+      println!("Device found but no properties available");
+      None
+    }
+    Err(e) => {
+      println!("Error getting device properties: {}", e);
+      None
+    }
+  }
+}
+
+// Snapshot of a peripheral's advertisement for the frontend
+fn device_info(id: String, props: PeripheralProperties, profile: &DeviceProfile) -> BluetoothDeviceInfo {
+  let name = props
+    .local_name
+    .clone()
+    .unwrap_or_else(|| "(unknown)".into());
+  // Note: connectable property is not directly available in btleplug 0.10
+  // We'll assume devices found during scan are potentially connectable
+  let connectable = true;
+  let address_type = format!("{:?}", props.address_type);
+
+  // Get service UUIDs
+  let services: Vec<String> = props.services
+    .iter()
+    .map(|uuid| uuid.to_string())
+    .collect();
+
+  BluetoothDeviceInfo {
+    id,
+    name,
+    rssi: props.rssi,
+    tx_power: props.tx_power_level,
+    connectable,
+    address_type,
+    services,
+    manufacturer_data: advertisement::manufacturer_records(&props),
+    service_data: advertisement::service_data_records(&props),
+    advertised: advertisement::decode_advertisement(&profile.advertisement, &props),
+    last_seen: epoch_ms(),
+    known: false,
+    alias: None,
+    role: None,
+  }
+}
+
+// Records an advertisement received while discovery is running, if it passes the active
+// scan filter
+async fn record_advertisement(app_handle: &tauri::AppHandle, device: SensorDevice) {
+  let filter = {
+    let discovery_state = app_handle.state::<DiscoveryState>();
+    let discovery = discovery_state.0.lock().await;
+    if !discovery.scanning {
+      return;
+    }
+    discovery.filter.clone()
+  };
+  let Some(props) = device_properties(&device).await else {
+    return;
+  };
+
+  let mut advertised_services = props.services.clone();
+  advertised_services.extend(props.service_data.keys().copied());
+  // Connectability isn't reported by btleplug 0.10 (see device_info)
+  if !filter.matches(props.local_name.as_deref(), props.rssi, &advertised_services, true) {
+    return;
+  }
+  record_seen_device(app_handle, device, props).await;
+}
+
+// New devices and devices that were reported lost emit device-discovered; changes to known
+// devices emit device-updated at most once per DEVICE_UPDATE_INTERVAL
+async fn record_seen_device(app_handle: &tauri::AppHandle, device: SensorDevice, props: PeripheralProperties) {
+  let profile = app_handle.state::<DeviceProfilesState>().0.lock().await
+    .resolve(props.local_name.as_deref(), &props.services);
+  let local_name = props.local_name.clone();
+  let mut advertised_services = props.services.clone();
+  advertised_services.extend(props.service_data.keys().copied());
+  let mut info = device_info(device.id(), props, &profile);
+  {
+    let known_devices = app_handle.state::<KnownDevicesState>();
+    let mut registry = known_devices.0.lock().await;
+    registry.mark_seen(&info.id, info.last_seen);
+    info.apply_known(registry.get(&info.id));
+  }
+
+  // Store the peripheral for later connection
+  app_handle.state::<DiscoveredDevicesState>().0.lock().await.insert(info.id.clone(), device);
+
+  let discovery_state = app_handle.state::<DiscoveryState>();
+  let mut discovery = discovery_state.0.lock().await;
+  if !discovery.scanning {
+    return;
+  }
+  match discovery.devices.get_mut(&info.id) {
+    Some(seen) if !seen.lost => {
+      seen.pending_update |= !seen.info.same_advertisement(&info);
+      seen.info = info;
+      seen.local_name = local_name;
+      seen.advertised_services = advertised_services;
+      if seen.pending_update && seen.last_emit.elapsed() >= DEVICE_UPDATE_INTERVAL {
+        seen.pending_update = false;
+        seen.last_emit = Instant::now();
+        let _ = app_handle.emit("device-updated", &seen.info);
+      }
+    }
+    _ => {
+      println!("Found device: {} - {} (RSSI: {:?}, Connectable: {})",
+               info.id, info.name, info.rssi, info.connectable);
+      let _ = app_handle.emit("device-discovered", &info);
+      discovery.devices.insert(info.id.clone(), SeenDevice {
+        info,
+        local_name,
+        advertised_services,
+        last_emit: Instant::now(),
+        pending_update: false,
+        lost: false,
+      });
+    }
+  }
+}
+
+// Connected devices don't advertise; a link event still counts as contact, so the
+// lost timeout after a disconnect starts from the disconnect rather than the last scan
+async fn touch_discovered_device(app_handle: &tauri::AppHandle, device_id: &str) {
+  let now = epoch_ms();
+  {
+    let discovery_state = app_handle.state::<DiscoveryState>();
+    let mut discovery = discovery_state.0.lock().await;
+    if let Some(seen) = discovery.devices.get_mut(device_id) {
+      seen.info.last_seen = now;
+    }
+  }
+
+  // Link events are rare enough to persist the known device's last-seen time right away
+  let known_devices = app_handle.state::<KnownDevicesState>();
+  let mut registry = known_devices.0.lock().await;
+  if let Err(e) = registry.persist_seen(device_id, now).await {
+    println!("[KnownDevices] {}", e);
+  }
+}
+
+// Re-labels listed devices after a registry change
+async fn refresh_known_devices(app_handle: &tauri::AppHandle) {
+  let registry = app_handle.state::<KnownDevicesState>();
+  let registry = registry.0.lock().await;
+  let discovery_state = app_handle.state::<DiscoveryState>();
+  let mut discovery = discovery_state.0.lock().await;
+  for seen in discovery.devices.values_mut() {
+    let before = (seen.info.known, seen.info.alias.clone(), seen.info.role);
+    seen.info.apply_known(registry.get(&seen.info.id));
+    if before != (seen.info.known, seen.info.alias.clone(), seen.info.role) && !seen.lost {
+      let _ = app_handle.emit("device-updated", &seen.info);
+    }
+  }
+}
+
+// Reports devices that stopped advertising while discovery runs. Connected devices stop
+// advertising by design and replay devices never advertise, so both are left alone.
+async fn sweep_lost_devices(app_handle: tauri::AppHandle, generation: u64) {
+  loop {
+    async_std::task::sleep(DISCOVERY_SWEEP_INTERVAL).await;
+
+    let mut exempt: HashSet<String> = app_handle.state::<ConnectedDevicesState>().0.lock().await
+      .keys().cloned().collect();
+    exempt.extend(app_handle.state::<ReplayDevicesState>().0.lock().await.keys().cloned());
+
+    let discovery_state = app_handle.state::<DiscoveryState>();
+    let mut discovery = discovery_state.0.lock().await;
+    if !discovery.scanning || discovery.generation != generation {
+      break;
+    }
+    let now = epoch_ms();
+    for (id, seen) in discovery.devices.iter_mut() {
+      if seen.lost || exempt.contains(id) {
+        continue;
+      }
+      if now.saturating_sub(seen.info.last_seen) >= DEVICE_LOST_TIMEOUT_MS {
+        println!("Device lost: {} - {}", id, seen.info.name);
+        seen.lost = true;
+        let _ = app_handle.emit("device-lost", &seen.info);
+      }
+    }
+  }
+}
+
+// Starts a continuous scan; discovered devices are reported through device-discovered,
+// device-updated and device-lost events until stop_discovery is called. Without a filter
+// the saved default filter applies. Calling it while discovery is already running does
+// nothing unless the filter changes, which restarts the scan; devices seen before stay
+// listed if they pass the new filter.
+#[tauri::command]
+async fn start_discovery(
+  filter: Option<DeviceScanFilter>,
+  discovery: tauri::State<'_, DiscoveryState>,
+  scan_filters: tauri::State<'_, ScanFilterState>,
+  transport: tauri::State<'_, TransportState>,
+  replay_devices: tauri::State<'_, ReplayDevicesState>,
+  app_handle: tauri::AppHandle
+) -> Result<(), String> {
+  let filter = match filter {
+    Some(filter) => filter,
+    None => scan_filters.0.lock().await.default_filter().clone(),
+  };
+  let compiled = filter.compile()?;
+
+  let (generation, restarting) = {
+    let mut discovery = discovery.0.lock().await;
+    let filter_changed = discovery.filter.filter() != &filter;
+    if discovery.scanning && !filter_changed {
+      return Ok(());
+    }
+    if filter_changed {
+      discovery.devices.retain(|_, seen| {
+        compiled.matches(seen.local_name.as_deref(), seen.info.rssi, &seen.advertised_services, seen.info.connectable)
+      });
+      discovery.filter = compiled;
+    }
+    let restarting = discovery.scanning;
+    if !restarting {
+      discovery.scanning = true;
+      discovery.generation += 1;
+    }
+    (discovery.generation, restarting)
+  };
+
+  if restarting {
+    println!("Restarting Bluetooth discovery with a new scan filter");
+    let _ = transport.0.stop_scan().await;
+  } else {
+    println!("Starting Bluetooth discovery ({:?} transport)...", transport.0.kind());
+  }
+  if let Err(e) = transport.0.start_scan(filter.adapter_filter()).await {
+    println!("Failed to start scan: {}", e);
+    discovery.0.lock().await.scanning = false;
+    return Err(e);
+  }
+
+  // Loaded replay devices are listed alongside the transport's peripherals, whatever the filter
+  let replays: Vec<SensorDevice> = replay_devices.0.lock().await
+    .values()
+    .map(|r| r.clone() as SensorDevice)
+    .collect();
+  for device in replays {
+    if let Some(props) = device_properties(&device).await {
+      record_seen_device(&app_handle, device, props).await;
+    }
+  }
+
+  if !restarting {
+    tauri::async_runtime::spawn(sweep_lost_devices(app_handle.clone(), generation));
+  }
+  Ok(())
+}
+
+#[tauri::command]
+async fn stop_discovery(
+  discovery: tauri::State<'_, DiscoveryState>,
+  transport: tauri::State<'_, TransportState>
+) -> Result<(), String> {
+  {
+    let mut discovery = discovery.0.lock().await;
+    if !discovery.scanning {
+      return Ok(());
+    }
+    discovery.scanning = false;
+  }
+  println!("Stopping Bluetooth discovery");
+  transport.0.stop_scan().await
+}
+
+// Every device seen since startup, most recently seen first
+#[tauri::command]
+async fn get_discovered_devices(
+  discovery: tauri::State<'_, DiscoveryState>
+) -> Result<Vec<BluetoothDeviceInfo>, String> {
+  let discovery = discovery.0.lock().await;
+  let mut devices: Vec<BluetoothDeviceInfo> = discovery.devices.values().map(|seen| seen.info.clone()).collect();
+  devices.sort_by_key(|device| std::cmp::Reverse(device.last_seen));
+  Ok(devices)
+}
+
+#[tauri::command]
+async fn get_default_scan_filter(
+  scan_filters: tauri::State<'_, ScanFilterState>
+) -> Result<DeviceScanFilter, String> {
+  Ok(scan_filters.0.lock().await.default_filter().clone())
+}
+
+// Saved in the app data directory and used by scans started without a filter
+#[tauri::command]
+async fn set_default_scan_filter(
+  filter: DeviceScanFilter,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  scan_filters: tauri::State<'_, ScanFilterState>
+) -> Result<(), String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  scan_filters.0.lock().await.set_default_filter(filter).await
+}
+
+// One-shot scan: returns the devices seen over five seconds. A discovery the UI is running
+// is sampled under its own filter, and a scan with a different filter is refused rather
+// than replacing it; otherwise discovery runs for the duration of the scan.
 #[tauri::command]
 async fn scan_devices(
-  discovered_devices: tauri::State<'_, DiscoveredDevicesState>,
-  bt_manager: tauri::State<'_, BluetoothManagerState>,
-  rate_limiting: tauri::State<'_, RateLimitingState>
+  filter: Option<DeviceScanFilter>,
+  discovery: tauri::State<'_, DiscoveryState>,
+  transport: tauri::State<'_, TransportState>,
+  replay_devices: tauri::State<'_, ReplayDevicesState>,
+  scan_filters: tauri::State<'_, ScanFilterState>,
+  rate_limiting: tauri::State<'_, RateLimitingState>,
+  app_handle: tauri::AppHandle
 ) -> Result<Vec<BluetoothDeviceInfo>, String> {
-  use btleplug::api::{Central, Manager as _, Peripheral, ScanFilter};
-  
   // Rate limiting check for scan operations (minimum 2 seconds between scans)
   {
     let mut limiters = rate_limiting.0.lock().await;
@@ -762,134 +1309,39 @@ async fn scan_devices(
       return Err(format!("Rate limited: Please wait {} ms before scanning again", wait_time.as_millis()));
     }
   }
-  
-  println!("Starting Bluetooth scan...");
-  
-  // Create or get existing manager
-  let manager = {
-    let mut mgr_guard = bt_manager.0.lock().await;
-    if mgr_guard.is_none() {
-      let new_manager = btleplug::platform::Manager::new()
-        .await
-        .map_err(|e| {
-          println!("Failed to create manager: {}", e);
-          e.to_string()
-        })?;
-      *mgr_guard = Some(new_manager);
-    }
-    mgr_guard.as_ref().unwrap().clone()
-  };
 
-  // Clear previously discovered devices
-  {
-    let mut discovered = discovered_devices.0.lock().await;
-    discovered.clear();
+  let scan_started_ms = epoch_ms();
+  let running_filter = {
+    let discovery = discovery.0.lock().await;
+    discovery.scanning.then(|| discovery.filter.filter().clone())
+  };
+  let was_scanning = running_filter.is_some();
+  match running_filter {
+    Some(running) => {
+      if filter.is_some_and(|filter| filter != running) {
+        return Err("Discovery is running with a different scan filter; stop it or scan without a filter".to_string());
+      }
+    }
+    None => start_discovery(filter, discovery.clone(), scan_filters, transport.clone(), replay_devices, app_handle.clone()).await?,
   }
 
-  // Grab all adapters (usually one)
-  let adapters = manager.adapters().await.map_err(|e| {
-    println!("Failed to get adapters: {}", e);
-    e.to_string()
-  })?;
-  
-  if adapters.is_empty() {
-    return Err("No Bluetooth adapters found".to_string());
+  println!("Scan started, waiting for devices...");
+  async_std::task::sleep(std::time::Duration::from_secs(5)).await;
+
+  // Peripherals the platform reports without a fresh advertisement event
+  for device in transport.0.peripherals().await? {
+    record_advertisement(&app_handle, device).await;
   }
-  
-  println!("Found {} Bluetooth adapter(s)", adapters.len());
-  let mut devices = Vec::new();
 
-  for adapter in adapters {
-    println!("Using adapter: {:?}", adapter.adapter_info().await);
-    
-    // Stop any previous scan
-    let _ = adapter.stop_scan().await;
-    
-    // Start scan with filter
-    adapter
-      .start_scan(ScanFilter::default())
-      .await
-      .map_err(|e| {
-        println!("Failed to start scan: {}", e);
-        e.to_string()
-      })?;
-    
-    println!("Scan started, waiting for devices...");
-    // Wait longer for devices to be discovered
-    async_std::task::sleep(std::time::Duration::from_secs(5)).await;
-
-    // Collect all peripherals seen so far
-    let peripherals = adapter.peripherals().await.map_err(|e| {
-      println!("Failed to get peripherals: {}", e);
-      e.to_string()
-    })?;
-    
-    println!("Found {} peripheral(s)", peripherals.len());
-    
-    // Store discovered peripherals for later connection
-    let mut discovered = discovered_devices.0.lock().await;
-    
-    for p in peripherals {
-      match p.properties().await {
-        Ok(Some(props)) => {
-          let id = props.address.to_string();
-          let name = props
-            .local_name
-            .unwrap_or_else(|| "(unknown)".into());
-          
-          // Store the peripheral for later use
-          discovered.insert(id.clone(), p.clone());
-          
-          // Extract additional information
-          let rssi = props.rssi;
-          // Note: connectable property is not directly available in btleplug 0.10
-          // We'll assume devices found during scan are potentially connectable
-          let connectable = true;
-          let address_type = format!("{:?}", props.address_type);
-          
-          // Get service UUIDs
-          let services: Vec<String> = props.services
-            .iter()
-            .map(|uuid| uuid.to_string())
-            .collect();
-          
-          // Get manufacturer data
-          let manufacturer_data: Vec<String> = props.manufacturer_data
-            .iter()
-            .map(|(company_id, data)| format!("Company: {}, Data: {:?}", company_id, data))
-            .collect();
-          
-          // Get service data
-          let service_data: Vec<String> = props.service_data
-            .iter()
-            .map(|(uuid, data)| format!("Service: {}, Data: {:?}", uuid, data))
-            .collect();
-          
-          println!("Found device: {} - {} (RSSI: {:?}, Connectable: {})", 
-                   id, name, rssi, connectable);
-          
-          devices.push(BluetoothDeviceInfo { 
-            id, 
-            name, 
-            rssi,
-            connectable,
-            address_type,
-            services,
-            manufacturer_data,
-            service_data,
-          });
-        }
-        Ok(None) => {
-          println!("Device found but no properties available");
-        }
-        Err(e) => {
-          println!("Error getting device properties: {}", e);
-        }
-      }
-    }
-    
-    // Stop the scan
-    let _ = adapter.stop_scan().await;
+  let mut devices: Vec<BluetoothDeviceInfo> = discovery.0.lock().await.devices
+    .values()
+    .filter(|seen| seen.info.last_seen >= scan_started_ms)
+    .map(|seen| seen.info.clone())
+    .collect();
+  devices.sort_by(|a, b| a.id.cmp(&b.id));
+
+  if !was_scanning {
+    let _ = stop_discovery(discovery, transport).await;
   }
 
   println!("Scan completed, found {} devices", devices.len());
@@ -897,14 +1349,65 @@ async fn scan_devices(
 }
 
 #[tauri::command]
+async fn list_bluetooth_adapters(transport: tauri::State<'_, TransportState>) -> Result<Vec<AdapterInfo>, String> {
+  transport.0.adapters().await
+}
+
+// Persisted across restarts; scanning and connections then use only this adapter
+// (None goes back to using every adapter)
+#[tauri::command]
+async fn select_bluetooth_adapter(
+  adapter_id: Option<String>,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  transport: tauri::State<'_, TransportState>,
+  discovery: tauri::State<'_, DiscoveryState>,
+  path_config: tauri::State<'_, PathConfigState>
+) -> Result<(), String> {
+  validate_csrf!(csrf_state, &csrf_token);
+
+  if let Some(id) = &adapter_id {
+    let adapters = transport.0.adapters().await?;
+    if !adapters.iter().any(|adapter| adapter.id == *id && adapter.available) {
+      return Err(format!("Bluetooth adapter not found: {}", id));
+    }
+  }
+  transport.0.select_adapter(adapter_id.clone()).await?;
+  let app_data_dir = path_config.0.lock().await.app_data_dir.clone();
+  transport::save_adapter_preference(&app_data_dir, adapter_id.clone()).await?;
+  println!("Selected Bluetooth adapter: {}", adapter_id.as_deref().unwrap_or("(all adapters)"));
+
+  // Devices found through the previous adapter can't be connected through the new one;
+  // a running discovery continues on the new adapter
+  let restart_filter = {
+    let mut discovery = discovery.0.lock().await;
+    discovery.devices.clear();
+    discovery.scanning.then(|| discovery.filter.filter().adapter_filter())
+  };
+  if let Some(filter) = restart_filter {
+    let _ = transport.0.stop_scan().await;
+    transport.0.start_scan(filter).await?;
+  }
+  Ok(())
+}
+
+#[tauri::command]
+async fn get_transport_kind(transport: tauri::State<'_, TransportState>) -> Result<TransportKind, String> {
+  Ok(transport.0.kind())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn connect_device(
   device_id: String, 
   connected_devices: tauri::State<'_, ConnectedDevicesState>,
   discovered_devices: tauri::State<'_, DiscoveredDevicesState>,
-  rate_limiting: tauri::State<'_, RateLimitingState>
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>,
+  rate_limiting: tauri::State<'_, RateLimitingState>,
+  known_devices: tauri::State<'_, KnownDevicesState>,
+  app_handle: tauri::AppHandle
 ) -> Result<String, String> {
-  use btleplug::api::Peripheral;
-  
   // Rate limiting check for connect operations (minimum 1 second between connections)
   {
     let mut limiters = rate_limiting.0.lock().await;
@@ -938,11 +1441,28 @@ async fn connect_device(
     }
   };
 
+  // Registered devices use their assigned profile; others are matched from the advertisement
+  let assigned_profile_id = known_devices.0.lock().await.get(&device_id).and_then(|d| d.profile_id.clone());
+  let assigned_profile = match &assigned_profile_id {
+    Some(profile_id) => profiles.0.lock().await.get(profile_id),
+    None => None,
+  };
+  if let (Some(profile_id), None) = (&assigned_profile_id, &assigned_profile) {
+    println!("Assigned profile '{}' of device {} no longer exists, matching from advertisement", profile_id, device_id);
+  }
+  let profile = match assigned_profile {
+    Some(profile) => profile,
+    None => resolve_device_profile(&peripheral, &profiles).await,
+  };
+  println!("Using device profile '{}' for device: {}", profile.id, device_id);
+  profile_assignments.0.lock().await.insert(device_id.clone(), profile);
+
   // Check if already connected to this peripheral
   if peripheral.is_connected().await.unwrap_or(false) {
     println!("Device is already connected at peripheral level");
     let mut connected = connected_devices.0.lock().await;
-    connected.insert(device_id.clone(), peripheral);
+    connected.insert(device_id.clone(), peripheral.clone());
+    tauri::async_runtime::spawn(refresh_device_info(app_handle, device_id.clone(), peripheral));
     return Ok(format!("Device was already connected: {}", device_id));
   }
   
@@ -983,7 +1503,8 @@ async fn connect_device(
         if connection_verified {
           // Store the connected device
           let mut connected = connected_devices.0.lock().await;
-          connected.insert(device_id.clone(), peripheral);
+          connected.insert(device_id.clone(), peripheral.clone());
+          tauri::async_runtime::spawn(refresh_device_info(app_handle, device_id.clone(), peripheral));
           
           println!("Successfully connected and verified connection to device: {}", device_id);
           return Ok(format!("Successfully connected to device: {} ({})", device_name, device_id));
@@ -1012,29 +1533,206 @@ async fn connect_device(
   Err(format!("Failed to connect to device after {} attempts", MAX_ATTEMPTS))
 }
 
-#[tauri::command]
-async fn disconnect_device(device_id: String, connected_devices: tauri::State<'_, ConnectedDevicesState>) -> Result<String, String> {
-  use btleplug::api::Peripheral;
-  
-  println!("Attempting to disconnect from device: {}", device_id);
-  
-  let mut connected = connected_devices.0.lock().await;
-  
-  if let Some(peripheral) = connected.remove(&device_id) {
-    match peripheral.disconnect().await {
-      Ok(_) => {
-        println!("Successfully disconnected from device: {}", device_id);
-        Ok(format!("Disconnected from device: {}", device_id))
-      }
-      Err(e) => {
-        println!("Failed to disconnect from device: {}", e);
-        // Re-insert the device since disconnect failed
-        connected.insert(device_id.clone(), peripheral);
-        Err(format!("Failed to disconnect: {}", e))
-      }
-    }
-  } else {
-    Err(format!("Device not connected: {}", device_id))
+// Reads the Battery and Device Information services of a freshly connected device and
+// keeps the battery level current through notifications. Runs in the background so a
+// slow or missing readout never delays the connection itself.
+async fn refresh_device_info(app_handle: tauri::AppHandle, device_id: String, peripheral: SensorDevice) {
+  if let Err(e) = peripheral.discover_services().await {
+    println!("[DeviceInfo] Service discovery failed for {}: {}", device_id, e);
+    return;
+  }
+  let info = device_info::read_device_info(&peripheral, epoch_ms()).await;
+  println!("[DeviceInfo] {}: battery {:?}%, serial {:?}, firmware {:?}",
+    device_id, info.battery_level, info.serial_number, info.firmware_revision);
+  app_handle.state::<DeviceInfoState>().0.lock().await.insert(device_id.clone(), info.clone());
+  let _ = app_handle.emit("device-info-updated", &DeviceInfoEvent { device_id: device_id.clone(), info });
+
+  let Some(characteristic) = device_info::battery_notify_characteristic(&peripheral) else {
+    return;
+  };
+  let notifications = match peripheral.notifications().await {
+    Ok(stream) => stream,
+    Err(e) => {
+      println!("[DeviceInfo] Battery notifications unavailable for {}: {}", device_id, e);
+      return;
+    }
+  };
+  if let Err(e) = peripheral.subscribe(&characteristic).await {
+    println!("[DeviceInfo] Failed to subscribe to battery level of {}: {}", device_id, e);
+    return;
+  }
+
+  // Replacing the sender stops a listener left over from an earlier connection
+  let (stop_tx, stop_rx) = async_std::channel::bounded::<()>(1);
+  app_handle.state::<BatteryListenersState>().0.lock().await.insert(device_id.clone(), stop_tx);
+
+  tauri::async_runtime::spawn(async move {
+    let stopped = async move {
+      let _ = stop_rx.recv().await;
+    };
+    let mut notifications = notifications.take_until(Box::pin(stopped));
+    while let Some(notification) = notifications.next().await {
+      if notification.uuid != transport::BATTERY_LEVEL_UUID {
+        continue;
+      }
+      let Some(level) = device_info::parse_battery_level(&notification.value) else {
+        continue;
+      };
+      let info = {
+        let device_info_state = app_handle.state::<DeviceInfoState>();
+        let mut infos = device_info_state.0.lock().await;
+        let info = infos.entry(device_id.clone()).or_default();
+        info.battery_level = Some(level);
+        info.battery_updated_at = Some(epoch_ms());
+        info.clone()
+      };
+      let _ = app_handle.emit("device-info-updated", &DeviceInfoEvent { device_id: device_id.clone(), info });
+    }
+    println!("[DeviceInfo] Battery listener for {} stopped", device_id);
+  });
+}
+
+#[tauri::command]
+async fn get_device_info(
+  device_id: String,
+  device_info_state: tauri::State<'_, DeviceInfoState>
+) -> Result<DeviceInfo, String> {
+  device_info_state.0.lock().await.get(&device_id).cloned()
+    .ok_or_else(|| format!("No device information for {}", device_id))
+}
+
+// Sends a command over the control characteristic of the device's profile
+async fn control_device(
+  device_id: &str,
+  command: ControlCommand,
+  connected_devices: &ConnectedDevicesState,
+  profiles: &DeviceProfilesState,
+  profile_assignments: &DeviceProfileAssignmentsState,
+) -> Result<ControlStatus, String> {
+  let peripheral = connected_devices.0.lock().await.get(device_id).cloned()
+    .ok_or_else(|| format!("Device not connected: {}", device_id))?;
+  let profile = device_profile_for(device_id, &peripheral, profiles, profile_assignments).await;
+  let spec = profile.control
+    .ok_or_else(|| format!("Device profile '{}' has no control characteristic", profile.id))?;
+
+  println!("[Control] Sending {:?} to {}", command, device_id);
+  let status = device_control::send_control_command(&peripheral, &spec, command).await
+    .map_err(|e| format!("{:?} failed for {}: {}", command, device_id, e))?;
+  println!("[Control] {} acknowledged {:?}: sampling {}, {} Hz", device_id, command, status.sampling, status.sample_rate_hz);
+  Ok(status)
+}
+
+#[tauri::command]
+async fn set_device_sample_rate(
+  device_id: String,
+  sample_rate_hz: u16,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<ControlStatus, String> {
+  control_device(&device_id, ControlCommand::SetSampleRate(sample_rate_hz), &connected_devices, &profiles, &profile_assignments).await
+}
+
+#[tauri::command]
+async fn device_start_sampling(
+  device_id: String,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<ControlStatus, String> {
+  control_device(&device_id, ControlCommand::StartSampling, &connected_devices, &profiles, &profile_assignments).await
+}
+
+#[tauri::command]
+async fn device_stop_sampling(
+  device_id: String,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<ControlStatus, String> {
+  control_device(&device_id, ControlCommand::StopSampling, &connected_devices, &profiles, &profile_assignments).await
+}
+
+// Blinks the device's LED so the operator can tell which sensor is which
+#[tauri::command]
+async fn device_identify(
+  device_id: String,
+  duration_s: Option<u8>,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<ControlStatus, String> {
+  let duration_s = duration_s.unwrap_or(device_control::DEFAULT_IDENTIFY_SECONDS);
+  control_device(&device_id, ControlCommand::Identify(duration_s), &connected_devices, &profiles, &profile_assignments).await
+}
+
+// Tares the resistive channels at their current reading
+#[tauri::command]
+async fn device_zero(
+  device_id: String,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<ControlStatus, String> {
+  control_device(&device_id, ControlCommand::Zero, &connected_devices, &profiles, &profile_assignments).await
+}
+
+async fn resolve_device_profile(peripheral: &SensorDevice, profiles: &DeviceProfilesState) -> DeviceProfile {
+  let (local_name, services) = match peripheral.properties().await {
+    Ok(Some(props)) => (props.local_name, props.services),
+    _ => (None, vec![]),
+  };
+  profiles.0.lock().await.resolve(local_name.as_deref(), &services)
+}
+
+// Profile assigned at connect time, resolved on demand for devices connected without one
+async fn device_profile_for(
+  device_id: &str,
+  peripheral: &SensorDevice,
+  profiles: &DeviceProfilesState,
+  profile_assignments: &DeviceProfileAssignmentsState,
+) -> DeviceProfile {
+  if let Some(profile) = profile_assignments.0.lock().await.get(device_id) {
+    return profile.clone();
+  }
+  let profile = resolve_device_profile(peripheral, profiles).await;
+  profile_assignments.0.lock().await.insert(device_id.to_string(), profile.clone());
+  profile
+}
+
+#[tauri::command]
+async fn disconnect_device(
+  device_id: String,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  battery_listeners: tauri::State<'_, BatteryListenersState>,
+  gatt_subscriptions: tauri::State<'_, GattSubscriptionsState>,
+  stream_handles: tauri::State<'_, StreamHandlesState>,
+  app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+  println!("Attempting to disconnect from device: {}", device_id);
+  if let Some(handle) = stream_handles.0.lock().await.get(&device_id) {
+    cancel_stream(Some(&app_handle), &device_id, handle);
+  }
+  battery_listeners.0.lock().await.remove(&device_id);
+  gatt_subscriptions.0.lock().await.retain(|(id, _), _| *id != device_id);
+  
+  let mut connected = connected_devices.0.lock().await;
+  
+  if let Some(peripheral) = connected.remove(&device_id) {
+    match peripheral.disconnect().await {
+      Ok(_) => {
+        println!("Successfully disconnected from device: {}", device_id);
+        Ok(format!("Disconnected from device: {}", device_id))
+      }
+      Err(e) => {
+        println!("Failed to disconnect from device: {}", e);
+        // Re-insert the device since disconnect failed
+        connected.insert(device_id.clone(), peripheral);
+        Err(format!("Failed to disconnect: {}", e))
+      }
+    }
+  } else {
+    Err(format!("Device not connected: {}", device_id))
   }
 }
 
@@ -1052,16 +1750,18 @@ async fn is_device_connected(device_id: String, connected_devices: tauri::State<
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn start_gait_notifications(
   device_id: String,
   connected_devices: tauri::State<'_, ConnectedDevicesState>,
   active_notifications: tauri::State<'_, ActiveNotificationsState>,
-  sample_rate_state: tauri::State<'_, SampleRateState>,
-  duplicate_detection_state: tauri::State<'_, DuplicateDetectionState>,
+  time_base_state: tauri::State<'_, TimeBaseState>,
+  stream_handles: tauri::State<'_, StreamHandlesState>,
+  sample_stream_state: tauri::State<'_, SampleStreamState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>,
   app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-  use btleplug::api::Peripheral;
-  
   println!("Starting gait notifications for device: {}", device_id);
   
   let peripheral = {
@@ -1077,13 +1777,72 @@ async fn start_gait_notifications(
     return Err(format!("Device {} is not connected", device_id));
   }
   
-  // Define the UUIDs from your Arduino code
-  let service_uuid = Uuid::parse_str("48877734-d012-40c4-81de-3ab006f71189")
-    .map_err(|e| format!("Invalid service UUID: {}", e))?;
-  let characteristic_uuid = Uuid::parse_str("8c4711b4-571b-41ba-a240-73e6884a85eb")
-    .map_err(|e| format!("Invalid characteristic UUID: {}", e))?;
+  // A stream that is already running keeps the device; otherwise this call claims it
+  let (handle, previous) = {
+    let mut handles = stream_handles.0.lock().await;
+    if handles.get(&device_id).is_some_and(|handle| handle.state().is_live()) {
+      println!("Notifications already running for device: {}", device_id);
+      return Ok(format!("Notifications already running for device: {}", device_id));
+    }
+    let handle = Arc::new(StreamHandle::new());
+    // Retire a previous stream that is still winding down
+    let previous = handles.insert(device_id.clone(), handle.clone());
+    if let Some(previous) = &previous {
+      previous.cancel();
+    }
+    (handle, previous)
+  };
+  emit_stream_state(Some(&app_handle), &device_id, StreamState::Starting, None);
+
+  // Service/characteristic UUIDs and packet layout come from the device profile
+  let profile = device_profile_for(&device_id, &peripheral, &profiles, &profile_assignments).await;
+  if let Err(e) = subscribe_gait_characteristic(&peripheral, &device_id, &profile).await {
+    handle.abandon();
+    emit_stream_state(Some(&app_handle), &device_id, StreamState::Stopped, Some(e.clone()));
+    return Err(e);
+  }
   
-  println!("Discovering services for device: {}", device_id);
+  // Mark device as actively collecting; a device that is still marked active is being
+  // resumed after a reconnect and continues the old stream's statistics, which its
+  // consumer hands over once it has exited
+  let resuming = {
+    let mut active = active_notifications.0.lock().await;
+    active.insert(device_id.clone(), true).unwrap_or(false)
+  };
+  let carryover = match previous.filter(|_| resuming) {
+    Some(previous) => previous.take_over().await.unwrap_or_default(),
+    None => StreamCarryover::default(),
+  };
+
+  let time_base = *time_base_state.0.lock().await.get_or_insert_with(TimeBase::now);
+  let (sink, stall_timeout) = {
+    let sample_stream = sample_stream_state.0.lock().await;
+    (sample_stream.sink(), Duration::from_millis(sample_stream.settings().stall_timeout_ms))
+  };
+  spawn_stream(peripheral, StreamContext {
+    device_id: device_id.clone(),
+    profile,
+    handle,
+    sink,
+    time_base,
+    stall_timeout,
+    carryover,
+    app_handle: Some(app_handle),
+  });
+  
+  Ok(format!("Started notifications for device: {}", device_id))
+}
+
+// Finds the profile's gait characteristic and subscribes to it
+async fn subscribe_gait_characteristic(
+  peripheral: &SensorDevice,
+  device_id: &str,
+  profile: &DeviceProfile,
+) -> Result<(), String> {
+  let service_uuid = profile.service_uuid;
+  let characteristic_uuid = profile.characteristic_uuid;
+  
+  println!("Discovering services for device: {} (profile '{}')", device_id, profile.id);
   
   // Discover services with retry logic
   let mut discovery_attempts = 0;
@@ -1150,221 +1909,356 @@ async fn start_gait_notifications(
     .map_err(|e| format!("Failed to subscribe to gait notifications: {}", e))?;
   
   println!("Successfully subscribed to gait notifications for device: {}", device_id);
-  
-  // Mark device as actively collecting
-  {
-    let mut active = active_notifications.0.lock().await;
-    active.insert(device_id.clone(), true);
-  }
+  Ok(())
+}
+
+// Everything a stream's tasks need besides the peripheral
+struct StreamContext {
+  device_id: String,
+  profile: DeviceProfile,
+  handle: Arc<StreamHandle>,
+  sink: SampleSink,
+  time_base: TimeBase,
+  stall_timeout: Duration,
+  // Statistics to continue from; fresh unless the stream resumes after a reconnect
+  carryover: StreamCarryover,
+  // Target of decode error, packet loss, diagnostics and legacy gait-data events; None for
+  // benchmark streams
+  app_handle: Option<tauri::AppHandle>,
+}
+
+// Spawns the producer, consumer and diagnostics reporter of a subscribed stream. Each task
+// owns its per-packet state; others see it through the stream handle.
+fn spawn_stream(peripheral: SensorDevice, ctx: StreamContext) {
+  let StreamContext { device_id, profile, handle, sink, time_base, stall_timeout, carryover, app_handle } = ctx;
+  let characteristic_uuid = profile.characteristic_uuid;
+  let sequence_modulus = profile.packet_layout.sequence_modulus();
+  let timestamp_wrap_ms = profile.packet_layout.timestamp_wrap_ms();
 
-  // Set up notification handler
-  let app_handle_clone = app_handle.clone();
-  let device_id_clone = device_id.clone();
-  let active_notifications_clone = active_notifications.inner().clone();
-  let sample_rate_state_clone = sample_rate_state.inner().clone();
-  let duplicate_detection_clone = duplicate_detection_state.inner().clone();
-  
-  // Start listening for notifications in a background task
   // Capture a monotonic baseline for relative timing (stable against system clock adjustments)
   let stream_start_instant = Instant::now();
+  let stream_offset_s = stream_start_instant.duration_since(time_base.instant).as_secs_f64();
   // Channel capacity tuned to absorb brief bursts without large latency.
-  let (tx, rx) = async_std::channel::bounded::<(u64, u64, f64, [u8;24])>(256);
+  let (tx, rx) = async_std::channel::bounded::<(u64, u64, f64, Vec<u8>)>(256);
 
   // Producer: minimal work (timestamp + copy + enqueue)
   {
-    let device_id_clone = device_id_clone.clone();
-    let active_notifications_clone_prod = active_notifications_clone.clone();
+    let handle = handle.clone();
+    let device_id = device_id.clone();
+    let app_handle = app_handle.clone();
     tauri::async_runtime::spawn(async move {
-      let mut notification_stream = peripheral.notifications().await.unwrap();
-      use std::sync::atomic::{AtomicU64, Ordering};
-      static RAW_DROPPED: AtomicU64 = AtomicU64::new(0);
-      static RAW_PRODUCED: AtomicU64 = AtomicU64::new(0);
+      let notification_stream = match peripheral.notifications().await {
+        Ok(notification_stream) => notification_stream,
+        Err(e) => {
+          println!("[Producer][{}] Failed to get notification stream: {}", device_id, e);
+          finish_stream_task(app_handle.as_ref(), &device_id, &handle);
+          return;
+        }
+      };
+      // Ends as soon as the stream is cancelled, even if the device has gone silent
+      let mut notification_stream = notification_stream.take_until(Box::pin(handle.stopped()));
       while let Some(data) = notification_stream.next().await {
-        // Quick active check (no heavy locks afterwards)
-        let is_active = {
-          let active = active_notifications_clone_prod.0.lock().await;
-          active.get(&device_id_clone).copied().unwrap_or(false)
-        };
-        if !is_active { break; }
+        if handle.is_cancelled() { break; }
 
-        if data.uuid == characteristic_uuid && data.value.len() == 24 {
+        // Length is checked by the decoder in the consumer so bad packets are counted, not lost
+        if data.uuid == characteristic_uuid {
           let capture_mono = Instant::now();
           let capture_abs = SystemTime::now();
-            let abs_us = capture_abs
-              .duration_since(UNIX_EPOCH)
-              .unwrap_or_else(|_| Duration::from_secs(0))
-              .as_micros() as u64;
-            let abs_ms = abs_us / 1000;
-            let monotonic_s = capture_mono
-              .duration_since(stream_start_instant)
-              .as_secs_f64();
-          let mut buf = [0u8;24];
-          buf.copy_from_slice(&data.value);
-          if tx.try_send((abs_ms, abs_us, monotonic_s, buf)).is_err() {
-            RAW_DROPPED.fetch_add(1, Ordering::Relaxed);
-          } else {
-            let produced = RAW_PRODUCED.fetch_add(1, Ordering::Relaxed) + 1;
-            if produced % 1000 == 0 { // occasional summary
-              let dropped = RAW_DROPPED.load(Ordering::Relaxed);
-              println!("[Producer][{}] Produced: {}, Dropped: {}", device_id_clone, produced, dropped);
-            }
-          }
+          let abs_us = capture_abs
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::from_secs(0))
+            .as_micros() as u64;
+          let abs_ms = abs_us / 1000;
+          let monotonic_s = capture_mono
+            .duration_since(stream_start_instant)
+            .as_secs_f64();
+          let enqueued = tx.try_send((abs_ms, abs_us, monotonic_s, data.value)).is_ok();
+          handle.producer.record(enqueued);
         }
       }
+      finish_stream_task(app_handle.as_ref(), &device_id, &handle);
     });
   }
 
-  // Consumer: parsing, duplicate detection, sample rate, emit
+  // Consumer: parsing, duplicate detection, sample rate, delivery
   {
-    let device_id_clone = device_id_clone.clone();
+    let handle = handle.clone();
+    let device_id = device_id.clone();
+    let app_handle = app_handle.clone();
     tauri::async_runtime::spawn(async move {
-      use std::sync::atomic::{AtomicU32, Ordering};
-      static PACKET_COUNT: AtomicU32 = AtomicU32::new(0);
-      // Burst instrumentation state (group packets with inter-arrival <5ms)
-      let mut last_packet_us: u64 = 0;
-      let mut current_burst_size: u32 = 0;
-      let mut total_bursts: u64 = 0;
-      let mut burst_size_sum: u64 = 0;
-      let mut max_burst: u32 = 0;
-      // Histogram bins: index 0=>size1, 1=>2, ... 8=>9, 9=>10+ (capped)
-      let mut burst_histogram: [u64; 10] = [0; 10];
-      while let Ok((abs_ms, abs_us, monotonic_s, raw_bytes)) = rx.recv().await {
-        // Active check again to terminate ASAP if stopped
-        let is_active = {
-          let active = active_notifications_clone.0.lock().await;
-          active.get(&device_id_clone).copied().unwrap_or(false)
-        };
-        if !is_active { break; }
-
-        let parse_start = std::time::Instant::now();
-        if let Ok(gait_data) = parse_gait_data(&raw_bytes, &device_id_clone, abs_ms) {
-          let parse_duration = parse_start.elapsed();
-
-          // ---------------- Burst grouping logic ----------------
-          if last_packet_us == 0 {
-            // First packet start a new burst
-            current_burst_size = 1;
-          } else {
-            let delta_us = abs_us.saturating_sub(last_packet_us);
-            if delta_us < 5_000 { // <5 ms => same connection event burst
-              current_burst_size += 1;
-            } else {
-              // Close previous burst
-              if current_burst_size > 0 {
-                total_bursts += 1;
-                burst_size_sum += current_burst_size as u64;
-                if current_burst_size > max_burst { max_burst = current_burst_size; }
-                let bin = if current_burst_size >= 10 { 9 } else { (current_burst_size - 1) as usize };
-                burst_histogram[bin] += 1;
+      let sink_device_id: Arc<str> = Arc::from(device_id.as_str());
+      let mut last_values: Option<[f32; 6]> = None;
+      let mut rate_calc = SampleRateCalculator::new();
+      let mut diagnostics = StreamDiagnostics::new(epoch_ms());
+      let StreamCarryover { decode_errors: mut decode_error_stats, tracker, mut clock_model } = carryover;
+      let mut tracker = tracker.or_else(|| sequence_modulus.map(SequenceTracker::new));
+      let publish_stats = |decode_error_stats: &DecodeErrorStats, tracker: &Option<SequenceTracker>, clock_model: &Option<ClockModel>| {
+        handle.publish_stats(StreamStats {
+          packet_loss: tracker.as_ref().map(|t| t.stats().clone()),
+          clock_sync: clock_model.as_ref().map(|m| m.stats().clone()),
+          decode_errors: decode_error_stats.clone(),
+        });
+      };
+      publish_stats(&decode_error_stats, &tracker, &clock_model);
+      let mut last_publish = Instant::now();
+      let mut packets = rx.take_until(Box::pin(handle.stopped()));
+      while let Some((abs_ms, abs_us, monotonic_s, raw_bytes)) = packets.next().await {
+        if handle.is_cancelled() { break; }
+        diagnostics.record_arrival(abs_us);
+
+        let parse_start = Instant::now();
+        let packet = match parse_gait_data(&profile, &raw_bytes, &device_id, abs_us, handle.sample_rate()) {
+          Ok(packet) => packet,
+          Err(e) => {
+            diagnostics.record_decode_failure();
+            let total_errors = decode_error_stats.record(&e, abs_ms);
+            // Report the first failure and then every 100th so a wrong profile doesn't flood the UI
+            if total_errors == 1 || total_errors % 100 == 0 {
+              println!("[Decode][{}] {} (total errors: {})", device_id, e, total_errors);
+              if let Some(app_handle) = &app_handle {
+                let _ = app_handle.emit("gait-decode-error", &DecodeErrorEvent {
+                  device_id: device_id.clone(),
+                  error: e,
+                  total_errors,
+                });
               }
-              // Start new burst
-              current_burst_size = 1;
             }
+            continue;
           }
-          last_packet_us = abs_us;
-          // ------------------------------------------------------
-
-          // Duplicate detection (can be removed or optimized later)
-          let current_tuple = (gait_data.r1, gait_data.r2, gait_data.r3, gait_data.x, gait_data.y, gait_data.z);
-          let is_duplicate = {
-            let mut duplicate_cache = duplicate_detection_clone.0.lock().await;
-            if let Some(last) = duplicate_cache.get(&device_id_clone) {
-              if *last == current_tuple { true } else { duplicate_cache.insert(device_id_clone.clone(), current_tuple); false }
-            } else {
-              duplicate_cache.insert(device_id_clone.clone(), current_tuple);
-              false
+        };
+        let parse_duration = parse_start.elapsed();
+        diagnostics.record_decoded(parse_duration.as_micros() as u64);
+
+        // Over-the-air loss from the device's sequence counter; a counter in the header drops
+        // a repeated packet here, one in the sample block drops repeated samples below
+        if let (Some(sequence), Some(tracker)) = (packet.sequence, tracker.as_mut()) {
+          if !track_sequence(tracker, sequence, &device_id, app_handle.as_ref()) {
+            diagnostics.record_duplicate_packet();
+            continue;
+          }
+        }
+
+        // Fit the clock model on the newest sample in the packet (closest to its send time)
+        // and map every sample's device time onto the host clock
+        let reconstructed: Vec<Option<u64>> = match packet.samples.last().and_then(|s| s.device_timestamp_ms) {
+          Some(device_ms) => {
+            let model = clock_model.get_or_insert_with(|| ClockModel::new(timestamp_wrap_ms));
+            model.record(device_ms, abs_us as f64 / 1000.0);
+            packet.samples.iter()
+              .map(|s| s.device_timestamp_ms.and_then(|t| model.to_host_ms(t)).map(|ms| (ms * 1000.0) as u64))
+              .collect()
+          }
+          None => vec![None; packet.samples.len()],
+        };
+
+        // Packets may carry several sample blocks; each is processed as its own sample, at the
+        // time parse_gait_data placed it before the packet's arrival
+        let arrived = stream_start_instant + Duration::from_secs_f64(monotonic_s);
+        let samples = packet.samples.into_iter()
+          .zip(packet.arrival_offsets_us)
+          .zip(packet.sample_sequences)
+          .zip(reconstructed);
+        for (((gait_data, arrival_offset_us), sample_sequence), reconstructed_timestamp_us) in samples {
+          if let (Some(sequence), Some(tracker)) = (sample_sequence, tracker.as_mut()) {
+            if !track_sequence(tracker, sequence, &device_id, app_handle.as_ref()) {
+              diagnostics.record_duplicate_packet();
+              continue;
             }
-          };
-          if is_duplicate { continue; }
+          }
 
-          let sample_rate = {
-            let mut rate_calc = sample_rate_state_clone.0.lock().await;
-            rate_calc.record_sample(&device_id_clone)
-          };
+          // Repeated values are dropped
+          let values = [gait_data.r1, gait_data.r2, gait_data.r3, gait_data.x, gait_data.y, gait_data.z];
+          if last_values == Some(values) {
+            diagnostics.record_duplicate_sample();
+            continue;
+          }
+          last_values = Some(values);
+
+          let arrival_offset = Duration::from_micros(arrival_offset_us);
+          let timestamp_us = abs_us.saturating_sub(arrival_offset_us);
+          let monotonic_s = monotonic_s - arrival_offset.as_secs_f64();
+          let sample_rate = rate_calc.record_sample(arrived.checked_sub(arrival_offset).unwrap_or(arrived));
+          handle.set_sample_rate(sample_rate);
+          if handle.record_sample() {
+            emit_stream_state(app_handle.as_ref(), &device_id, StreamState::Streaming, None);
+          }
 
-          let gait_data_with_rate = GaitDataWithRate {
-            device_id: gait_data.device_id,
-            r1: gait_data.r1,
-            r2: gait_data.r2,
-            r3: gait_data.r3,
-            x: gait_data.x,
-            y: gait_data.y,
-            z: gait_data.z,
-            timestamp: gait_data.timestamp,
-            timestamp_us: abs_us,
-            monotonic_s,
-            sample_rate,
+          // Prefer the device clock when available, otherwise host arrival time
+          let session_time_s = match reconstructed_timestamp_us {
+            Some(us) => (us as f64 - time_base.epoch_us as f64) / 1_000_000.0,
+            None => monotonic_s + stream_offset_s,
           };
 
-          let emit_start = std::time::Instant::now();
-          let _ = app_handle_clone.emit("gait-data", &gait_data_with_rate);
-            let emit_duration = emit_start.elapsed();
+          let emit_start = Instant::now();
+          // Queued even in legacy mode: the flusher also feeds the recording
+          sink.push(&sink_device_id, sample_rate, BatchSample {
+            timestamp_us,
+            monotonic_s,
+            session_time_s,
+            device_timestamp_ms: gait_data.device_timestamp_ms,
+            reconstructed_timestamp_us,
+            values,
+          });
+          if let Some(app_handle) = app_handle.as_ref().filter(|_| sink.legacy_events()) {
+            let _ = app_handle.emit("gait-data", &GaitDataWithRate {
+              device_id: gait_data.device_id,
+              r1: gait_data.r1,
+              r2: gait_data.r2,
+              r3: gait_data.r3,
+              x: gait_data.x,
+              y: gait_data.y,
+              z: gait_data.z,
+              timestamp: gait_data.timestamp,
+              timestamp_us,
+              monotonic_s,
+              sample_rate,
+              device_timestamp_ms: gait_data.device_timestamp_ms,
+              reconstructed_timestamp_us,
+              session_time_s,
+            });
+          }
+          let emit_duration = emit_start.elapsed();
 
-          let count = PACKET_COUNT.fetch_add(1, Ordering::Relaxed);
-          if count % 100 == 0 {
+          let count = diagnostics.record_emitted(emit_duration.as_micros() as u64);
+          if count % 100 == 1 {
             let rate_info = sample_rate.map(|r| format!("{:.1} Hz", r)).unwrap_or_else(|| "calculating...".to_string());
             println!("🧵 BLE Proc [{}]: ts_ms: {}, rel_s: {:.6}, Rate: {}, Parse: {:?}, Emit: {:?}",
               count, abs_ms, monotonic_s, rate_info, parse_duration, emit_duration);
           }
-          if count % 500 == 0 {
-            // Finalize current burst temporarily (without resetting) for reporting snapshot
-            let (report_bursts, report_avg, report_max, hist_str) = if total_bursts > 0 {
-              let avg = burst_size_sum as f64 / total_bursts as f64;
-              let hist_parts: Vec<String> = burst_histogram.iter().enumerate().map(|(i, v)| {
-                if i < 9 { format!("{}:{}", i+1, v) } else { format!("10+:{}", v) }
-              }).collect();
-              (total_bursts, avg, max_burst, hist_parts.join(" "))
-            } else { (0, 0.0, 0, String::from("")) };
-            println!("[BurstStats][{}] packets:{} bursts:{} avg_size:{:.2} max:{} hist:[{}] current_open:{}", 
-              device_id_clone, count+1, report_bursts, report_avg, report_max, hist_str, current_burst_size);
-          }
         }
+
+        if last_publish.elapsed() >= DIAGNOSTICS_PUBLISH_INTERVAL {
+          last_publish = Instant::now();
+          handle.publish_diagnostics(diagnostics.snapshot(&device_id, &handle.producer, handle.sample_rate()));
+          publish_stats(&decode_error_stats, &tracker, &clock_model);
+        }
+      }
+      handle.publish_diagnostics(diagnostics.snapshot(&device_id, &handle.producer, handle.sample_rate()));
+      publish_stats(&decode_error_stats, &tracker, &clock_model);
+      handle.hand_over(StreamCarryover { decode_errors: decode_error_stats, tracker, clock_model });
+      println!("[Consumer][{}] Exiting consumer loop", device_id);
+      finish_stream_task(app_handle.as_ref(), &device_id, &handle);
+    });
+  }
+
+  // Watchdog and diagnostics reporter: flags the stream as stalled when samples stop
+  // arriving and pushes a diagnostics snapshot to the frontend while the stream runs
+  tauri::async_runtime::spawn(async move {
+    let mut last_report = Instant::now();
+    loop {
+      let stopped = Box::pin(handle.stopped());
+      let tick = Box::pin(async_std::task::sleep(STREAM_WATCHDOG_INTERVAL));
+      if let futures::future::Either::Left(_) = futures::future::select(stopped, tick).await {
+        break;
+      }
+
+      let idle = handle.idle_for();
+      if idle >= stall_timeout && handle.mark_stalled() {
+        let reason = format!("No samples for {} ms", idle.as_millis());
+        emit_stream_state(app_handle.as_ref(), &device_id, StreamState::Stalled, Some(reason));
       }
-      // Flush last burst on exit
-      if current_burst_size > 0 {
-        total_bursts += 1;
-        burst_size_sum += current_burst_size as u64;
-        if current_burst_size > max_burst { max_burst = current_burst_size; }
-        let bin = if current_burst_size >= 10 { 9 } else { (current_burst_size - 1) as usize };
-        burst_histogram[bin] += 1;
+
+      if let Some(app_handle) = &app_handle {
+        if last_report.elapsed() >= STREAM_DIAGNOSTICS_INTERVAL {
+          last_report = Instant::now();
+          if let Some(snapshot) = handle.diagnostics() {
+            let _ = app_handle.emit("stream-diagnostics", &snapshot);
+          }
+        }
       }
-      if total_bursts > 0 {
-        let avg = burst_size_sum as f64 / total_bursts as f64;
-        let hist_parts: Vec<String> = burst_histogram.iter().enumerate().map(|(i, v)| {
-          if i < 9 { format!("{}:{}", i+1, v) } else { format!("10+:{}", v) }
-        }).collect();
-        println!("[BurstStatsFinal][{}] bursts:{} avg_size:{:.2} max:{} hist:[{}]", device_id_clone, total_bursts, avg, max_burst, hist_parts.join(" "));
+    }
+  });
+}
+
+// Records a device sequence number and reports gaps; false for a number already received
+fn track_sequence(tracker: &mut SequenceTracker, sequence: u64, device_id: &str, app_handle: Option<&tauri::AppHandle>) -> bool {
+  match tracker.record(sequence) {
+    SequenceEvent::Duplicate => return false,
+    SequenceEvent::Gap { expected, received, lost } => {
+      let stats = tracker.stats();
+      println!("[PacketLoss][{}] expected seq {} got {} ({} lost, total {} / {:.2}%)",
+        device_id, expected, received, lost, stats.lost, stats.loss_percent);
+      if let Some(app_handle) = app_handle {
+        let _ = app_handle.emit("gait-packet-loss", &PacketLossEvent {
+          device_id: device_id.to_string(),
+          expected_sequence: expected,
+          received_sequence: received,
+          lost,
+          total_lost: stats.lost,
+          loss_percent: stats.loss_percent,
+        });
       }
-      println!("[Consumer][{}] Exiting consumer loop", device_id_clone);
+    }
+    SequenceEvent::InOrder | SequenceEvent::Reordered => {}
+  }
+  true
+}
+
+// Called by the producer and consumer when they exit; the last one reports the stream stopped
+fn finish_stream_task(app_handle: Option<&tauri::AppHandle>, device_id: &str, handle: &StreamHandle) {
+  if handle.task_finished() {
+    emit_stream_state(app_handle, device_id, StreamState::Stopped, None);
+  }
+}
+
+fn emit_stream_state(app_handle: Option<&tauri::AppHandle>, device_id: &str, state: StreamState, reason: Option<String>) {
+  match &reason {
+    Some(reason) => println!("[Stream][{}] {:?}: {}", device_id, state, reason),
+    None => println!("[Stream][{}] {:?}", device_id, state),
+  }
+  if let Some(app_handle) = app_handle {
+    let _ = app_handle.emit("stream-state-changed", &StreamStateEvent {
+      device_id: device_id.to_string(),
+      state,
+      reason,
+      at: epoch_ms(),
     });
   }
-  
-  Ok(format!("Started notifications for device: {}", device_id))
+}
+
+// Stops a device's stream if one is running
+fn cancel_stream(app_handle: Option<&tauri::AppHandle>, device_id: &str, handle: &StreamHandle) {
+  if handle.cancel() {
+    emit_stream_state(app_handle, device_id, StreamState::Stopping, None);
+  }
 }
 
 #[tauri::command]
 async fn get_sample_rate(
   device_id: String,
-  sample_rate_state: tauri::State<'_, SampleRateState>,
+  stream_handles: tauri::State<'_, StreamHandlesState>,
 ) -> Result<Option<f64>, String> {
-  let rate_calc = sample_rate_state.0.lock().await;
-  Ok(rate_calc.get_current_rate(&device_id))
+  Ok(stream_handles.0.lock().await.get(&device_id).and_then(|handle| handle.sample_rate()))
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn stop_gait_notifications(
   device_id: String,
   connected_devices: tauri::State<'_, ConnectedDevicesState>,
   active_notifications: tauri::State<'_, ActiveNotificationsState>,
+  time_base_state: tauri::State<'_, TimeBaseState>,
+  recording_state: tauri::State<'_, RecordingState>,
+  stream_handles: tauri::State<'_, StreamHandlesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>,
+  app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
-  use btleplug::api::Peripheral;
-  
   println!("Stopping gait notifications for device: {}", device_id);
   
+  if let Some(handle) = stream_handles.0.lock().await.get(&device_id) {
+    cancel_stream(Some(&app_handle), &device_id, handle);
+  }
+  
   // Mark device as inactive first (this will stop the notification loop)
   {
     let mut active = active_notifications.0.lock().await;
     active.insert(device_id.clone(), false);
+    // The next recording starts a fresh time base once no device is streaming; a running
+    // recording keeps its own until stop_recording
+    if !active.values().any(|&streaming| streaming) && recording_state.0.lock().await.is_none() {
+      *time_base_state.0.lock().await = None;
+    }
   }
 
   let peripheral = {
@@ -1384,11 +2278,10 @@ async fn stop_gait_notifications(
     return Ok(format!("Device {} was already disconnected", device_id));
   }
   
-  // Define UUIDs
-  let service_uuid = Uuid::parse_str("48877734-d012-40c4-81de-3ab006f71189")
-    .map_err(|e| format!("Invalid service UUID: {}", e))?;
-  let characteristic_uuid = Uuid::parse_str("8c4711b4-571b-41ba-a240-73e6884a85eb")
-    .map_err(|e| format!("Invalid characteristic UUID: {}", e))?;
+  // UUIDs from the device profile used when the stream was started
+  let profile = device_profile_for(&device_id, &peripheral, &profiles, &profile_assignments).await;
+  let service_uuid = profile.service_uuid;
+  let characteristic_uuid = profile.characteristic_uuid;
   
   // Find the characteristic
   let services = peripheral.services();
@@ -1430,71 +2323,185 @@ async fn is_device_collecting(
   Ok(active.get(&device_id).copied().unwrap_or(false))
 }
 
+async fn connected_device(device_id: &str, connected_devices: &ConnectedDevicesState) -> Result<SensorDevice, String> {
+  connected_devices.0.lock().await.get(device_id).cloned()
+    .ok_or_else(|| format!("Device not connected: {}", device_id))
+}
+
+// Service/characteristic tree of a connected device, for firmware bring-up
 #[tauri::command]
-async fn debug_device_services(
+async fn get_gatt_tree(
   device_id: String,
   connected_devices: tauri::State<'_, ConnectedDevicesState>,
-) -> Result<Vec<String>, String> {
-  use btleplug::api::Peripheral;
-  
-  println!("Debug: Listing services for device: {}", device_id);
-  
-  let peripheral = {
-    let connected = connected_devices.0.lock().await;
-    match connected.get(&device_id) {
-      Some(peripheral) => peripheral.clone(),
-      None => return Err(format!("Device not connected: {}", device_id)),
-    }
-  };
-  
-  // Verify connection state
+) -> Result<Vec<GattService>, String> {
+  let peripheral = connected_device(&device_id, &connected_devices).await?;
   if !peripheral.is_connected().await.unwrap_or(false) {
     return Err(format!("Device {} is not connected", device_id));
   }
-  
-  // Discover services
-  println!("Debug: Starting service discovery for device: {}", device_id);
   peripheral.discover_services().await
     .map_err(|e| format!("Failed to discover services: {}", e))?;
-  
-  // Wait for discovery to complete
-  async_std::task::sleep(std::time::Duration::from_millis(1000)).await;
-  
-  // List all services
-  let services = peripheral.services();
-  let mut service_info = Vec::new();
-  
-  println!("Debug: Found {} services on device {}:", services.len(), device_id);
-  for (i, service) in services.iter().enumerate() {
-    let service_str = format!("Service {}: {} (characteristics: {})", i, service.uuid, service.characteristics.len());
-    println!("  {}", service_str);
-    service_info.push(service_str);
-    
-    for (j, char) in service.characteristics.iter().enumerate() {
-      let char_str = format!("    Characteristic {}: {}", j, char.uuid);
-      println!("  {}", char_str);
-      service_info.push(char_str);
-    }
+
+  let tree = gatt_explorer::service_tree(&peripheral.services());
+  println!("[GATT] {} exposes {} service(s)", device_id, tree.len());
+  Ok(tree)
+}
+
+#[tauri::command]
+async fn read_characteristic(
+  device_id: String,
+  service_uuid: uuid::Uuid,
+  characteristic_uuid: uuid::Uuid,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+) -> Result<GattValue, String> {
+  let peripheral = connected_device(&device_id, &connected_devices).await?;
+  let characteristic = gatt_explorer::find_gatt_characteristic(&peripheral, service_uuid, characteristic_uuid).await?;
+  if !characteristic.properties.contains(CharPropFlags::READ) {
+    return Err(format!("Characteristic {} is not readable", characteristic_uuid));
   }
-  
-  Ok(service_info)
+  let value = peripheral.read(&characteristic).await
+    .map_err(|e| format!("Failed to read {}: {}", characteristic_uuid, e))?;
+  Ok(GattValue::new(&value))
+}
+
+// Writes a hex value; with_response defaults to whatever the characteristic supports,
+// preferring a write with response
+#[tauri::command]
+async fn write_characteristic(
+  device_id: String,
+  service_uuid: uuid::Uuid,
+  characteristic_uuid: uuid::Uuid,
+  value: String,
+  with_response: Option<bool>,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+) -> Result<(), String> {
+  let data = gatt_explorer::parse_hex(&value)?;
+  let peripheral = connected_device(&device_id, &connected_devices).await?;
+  let characteristic = gatt_explorer::find_gatt_characteristic(&peripheral, service_uuid, characteristic_uuid).await?;
+
+  let with_response = with_response.unwrap_or(characteristic.properties.contains(CharPropFlags::WRITE));
+  let (write_type, required) = if with_response {
+    (WriteType::WithResponse, CharPropFlags::WRITE)
+  } else {
+    (WriteType::WithoutResponse, CharPropFlags::WRITE_WITHOUT_RESPONSE)
+  };
+  if !characteristic.properties.contains(required) {
+    return Err(format!("Characteristic {} does not support {:?} writes", characteristic_uuid, write_type));
+  }
+
+  println!("[GATT] Writing {} to {} on {}", advertisement::to_hex(&data), characteristic_uuid, device_id);
+  peripheral.write(&characteristic, &data, write_type).await
+    .map_err(|e| format!("Failed to write {}: {}", characteristic_uuid, e))
+}
+
+// Watches any notifying characteristic for a limited time, emitting each value as a
+// gatt-notification event until the time is up or unsubscribe_characteristic is called
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn subscribe_characteristic(
+  device_id: String,
+  service_uuid: uuid::Uuid,
+  characteristic_uuid: uuid::Uuid,
+  duration_ms: Option<u64>,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  gatt_subscriptions: tauri::State<'_, GattSubscriptionsState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>,
+  app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+  let peripheral = connected_device(&device_id, &connected_devices).await?;
+  let characteristic = gatt_explorer::find_gatt_characteristic(&peripheral, service_uuid, characteristic_uuid).await?;
+  if !characteristic.properties.intersects(CharPropFlags::NOTIFY | CharPropFlags::INDICATE) {
+    return Err(format!("Characteristic {} does not notify or indicate", characteristic_uuid));
+  }
+  let duration = Duration::from_millis(duration_ms.unwrap_or(GATT_SUBSCRIPTION_DEFAULT_MS).min(GATT_SUBSCRIPTION_MAX_MS));
+
+  // The app's own data and battery subscriptions must survive the temporary one
+  let app_owned = characteristic_uuid == transport::BATTERY_LEVEL_UUID
+    || profile_assignments.0.lock().await.get(&device_id).is_some_and(|p| p.characteristic_uuid == characteristic_uuid);
+
+  let notifications = peripheral.notifications().await?;
+  peripheral.subscribe(&characteristic).await
+    .map_err(|e| format!("Failed to subscribe to {}: {}", characteristic_uuid, e))?;
+
+  // A newer subscription to the same characteristic replaces this one
+  let subscription_id = GATT_SUBSCRIPTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+  let (stop_tx, stop_rx) = async_std::channel::bounded::<()>(1);
+  let key = (device_id.clone(), characteristic_uuid);
+  gatt_subscriptions.0.lock().await.insert(key.clone(), (subscription_id, stop_tx));
+  println!("[GATT] Watching {} on {} for {:?}", characteristic_uuid, device_id, duration);
+
+  let subscriptions = gatt_subscriptions.0.clone();
+  tauri::async_runtime::spawn(async move {
+    let stopped = async move {
+      futures::future::select(Box::pin(stop_rx.recv()), Box::pin(async_std::task::sleep(duration))).await;
+    };
+    let mut notifications = notifications.take_until(Box::pin(stopped));
+    while let Some(notification) = notifications.next().await {
+      if notification.uuid != characteristic_uuid {
+        continue;
+      }
+      let _ = app_handle.emit("gatt-notification", &GattNotificationEvent {
+        device_id: device_id.clone(),
+        characteristic_uuid: characteristic_uuid.to_string(),
+        hex: advertisement::to_hex(&notification.value),
+        timestamp: epoch_ms(),
+      });
+    }
+
+    let replaced = {
+      let mut subscriptions = subscriptions.lock().await;
+      match subscriptions.get(&key) {
+        Some((id, _)) if *id == subscription_id => {
+          subscriptions.remove(&key);
+          false
+        }
+        Some(_) => true,
+        None => false,
+      }
+    };
+    if !replaced && !app_owned {
+      if let Err(e) = peripheral.unsubscribe(&characteristic).await {
+        println!("[GATT] Failed to unsubscribe from {} on {}: {}", characteristic_uuid, device_id, e);
+      }
+    }
+    println!("[GATT] Stopped watching {} on {}", characteristic_uuid, device_id);
+    let _ = app_handle.emit("gatt-subscription-ended", &GattSubscriptionEndedEvent {
+      device_id,
+      characteristic_uuid: characteristic_uuid.to_string(),
+    });
+  });
+  Ok(())
+}
+
+#[tauri::command]
+async fn unsubscribe_characteristic(
+  device_id: String,
+  characteristic_uuid: uuid::Uuid,
+  gatt_subscriptions: tauri::State<'_, GattSubscriptionsState>,
+) -> Result<(), String> {
+  gatt_subscriptions.0.lock().await.remove(&(device_id.clone(), characteristic_uuid))
+    .map(|_| ())
+    .ok_or_else(|| format!("Not watching {} on {}", characteristic_uuid, device_id))
 }
 
 #[tauri::command]
 async fn check_connection_status(
   connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  reconnecting_devices: tauri::State<'_, ReconnectingDevicesState>,
   app_handle: tauri::AppHandle,
 ) -> Result<Vec<String>, String> {
-  use btleplug::api::Peripheral;
-  
   let mut actually_connected = Vec::new();
   let mut devices_to_remove = Vec::new();
   
   {
     let mut connected = connected_devices.0.lock().await;
+    let reconnecting = reconnecting_devices.0.lock().await;
     
     // Check each device's actual connection status
     for (device_id, peripheral) in connected.iter() {
+      // Devices being reconnected stay registered until the supervisor gives up
+      if reconnecting.contains(device_id) {
+        continue;
+      }
       match peripheral.is_connected().await {
         Ok(true) => {
           actually_connected.push(device_id.clone());
@@ -1524,8 +2531,554 @@ async fn check_connection_status(
   Ok(actually_connected)
 }
 
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+const INITIAL_RECONNECT_DELAY_MS: u64 = 500;
+const MAX_RECONNECT_DELAY_MS: u64 = 16_000;
+
+fn epoch_ms() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+// Watches the transport's central events: advertisements feed discovery, and unexpected
+// disconnects are handed to a per-device reconnection supervisor. disconnect_device removes
+// a device from ConnectedDevicesState before disconnecting, so user-initiated disconnects
+// are ignored here.
+async fn watch_transport_events(app_handle: tauri::AppHandle) {
+  let transport = app_handle.state::<TransportState>().0.clone();
+  let mut events = match transport.events().await {
+    Ok(events) => events,
+    Err(e) => {
+      println!("[Supervisor] Transport events unavailable, automatic reconnection disabled: {}", e);
+      return;
+    }
+  };
+
+  while let Some(event) = events.next().await {
+    match event {
+      TransportEvent::Discovered(device) | TransportEvent::Updated(device) => {
+        record_advertisement(&app_handle, device).await;
+      }
+      TransportEvent::Connected(device) => touch_discovered_device(&app_handle, &device.id()).await,
+      TransportEvent::Disconnected(device) => {
+        touch_discovered_device(&app_handle, &device.id()).await;
+        handle_disconnect(&app_handle, device.id()).await;
+      }
+    }
+  }
+  println!("[Supervisor] Transport event stream ended");
+}
+
+async fn handle_disconnect(app_handle: &tauri::AppHandle, device_id: String) {
+  let expected = app_handle.state::<ConnectedDevicesState>().0.lock().await.contains_key(&device_id);
+  if !expected {
+    return;
+  }
+  let newly_reconnecting = app_handle.state::<ReconnectingDevicesState>().0.lock().await.insert(device_id.clone());
+  if newly_reconnecting {
+    println!("[Supervisor] Device {} dropped its link unexpectedly", device_id);
+    tauri::async_runtime::spawn(supervise_reconnect(app_handle.clone(), device_id));
+  }
+}
+
+// Reconnects a dropped device with exponential backoff and resumes its stream if it was
+// streaming, recording the interruption as a gap
+async fn supervise_reconnect(app_handle: tauri::AppHandle, device_id: String) {
+  let gap_start_ms = epoch_ms();
+  let was_streaming = app_handle.state::<ActiveNotificationsState>().0.lock().await
+    .get(&device_id).copied().unwrap_or(false);
+
+  // The old stream ends here; a fresh one is started once the link is back
+  if let Some(handle) = app_handle.state::<StreamHandlesState>().0.lock().await.get(&device_id) {
+    cancel_stream(Some(&app_handle), &device_id, handle);
+  }
+
+  let mut attempts = 0;
+  let mut reconnected = false;
+  while attempts < MAX_RECONNECT_ATTEMPTS {
+    attempts += 1;
+    let delay_ms = (INITIAL_RECONNECT_DELAY_MS << (attempts - 1)).min(MAX_RECONNECT_DELAY_MS);
+    let _ = app_handle.emit("device-reconnecting", &ReconnectingEvent {
+      device_id: device_id.clone(),
+      attempt: attempts,
+      max_attempts: MAX_RECONNECT_ATTEMPTS,
+      delay_ms,
+    });
+    async_std::task::sleep(Duration::from_millis(delay_ms)).await;
+
+    // Stop if the user disconnected the device in the meantime
+    let peripheral = app_handle.state::<ConnectedDevicesState>().0.lock().await.get(&device_id).cloned();
+    let Some(peripheral) = peripheral else {
+      println!("[Supervisor] {} was disconnected by the user, stopping reconnection", device_id);
+      app_handle.state::<ReconnectingDevicesState>().0.lock().await.remove(&device_id);
+      return;
+    };
+
+    match peripheral.connect().await {
+      Ok(_) => {
+        println!("[Supervisor] Reconnected to {} (attempt {})", device_id, attempts);
+        tauri::async_runtime::spawn(refresh_device_info(app_handle.clone(), device_id.clone(), peripheral.clone()));
+        reconnected = true;
+        break;
+      }
+      Err(e) => println!("[Supervisor] Reconnect attempt {} for {} failed: {}", attempts, device_id, e),
+    }
+  }
+
+  let mut resumed = false;
+  if reconnected && was_streaming {
+    let result = start_gait_notifications(
+      device_id.clone(),
+      app_handle.state(),
+      app_handle.state(),
+      app_handle.state(),
+      app_handle.state(),
+      app_handle.state(),
+      app_handle.state(),
+      app_handle.state(),
+      app_handle.clone(),
+    ).await;
+    match result {
+      Ok(_) => resumed = true,
+      Err(e) => println!("[Supervisor] Failed to resume stream for {}: {}", device_id, e),
+    }
+  }
+
+  let end_ms = epoch_ms();
+  let gap = StreamGap {
+    device_id: device_id.clone(),
+    start_ms: gap_start_ms,
+    end_ms,
+    duration_ms: end_ms - gap_start_ms,
+    attempts,
+    resumed,
+  };
+  app_handle.state::<StreamGapsState>().0.lock().await.push(gap.clone());
+  app_handle.state::<ReconnectingDevicesState>().0.lock().await.remove(&device_id);
+
+  if reconnected {
+    let _ = app_handle.emit("device-reconnected", &ReconnectResultEvent { device_id, gap });
+  } else {
+    println!("[Supervisor] Giving up on {} after {} attempts", device_id, attempts);
+    app_handle.state::<ActiveNotificationsState>().0.lock().await.insert(device_id.clone(), false);
+    let remaining: Vec<String> = {
+      let connected_devices = app_handle.state::<ConnectedDevicesState>();
+      let mut connected = connected_devices.0.lock().await;
+      connected.remove(&device_id);
+      connected.keys().cloned().collect()
+    };
+    let _ = app_handle.emit("device-reconnect-failed", &ReconnectResultEvent { device_id, gap });
+    let _ = app_handle.emit("connection-status-update", &remaining);
+  }
+}
+
+#[tauri::command]
+async fn load_replay_session(
+  session_id: String,
+  speed: Option<f64>,
+  loop_playback: Option<bool>,
+  replay_devices: tauri::State<'_, ReplayDevicesState>,
+  path_config: tauri::State<'_, PathConfigState>
+) -> Result<Vec<ReplayDeviceInfo>, String> {
+  let sessions = get_sessions(path_config.clone()).await?;
+  let session_metadata = sessions.iter()
+    .find(|s| s.id == session_id)
+    .ok_or("Session not found")?;
+
+  let per_device = SessionContent::read(&session_metadata.file_path, None, &session_metadata.time_offsets_ms).await?.per_device();
+  if per_device.is_empty() {
+    return Err("No valid data points found in file".to_string());
+  }
+
+  let speed = speed.unwrap_or(1.0);
+  let loop_playback = loop_playback.unwrap_or(false);
+  let mut loaded = Vec::new();
+  let mut replays = replay_devices.0.lock().await;
+
+  for (source_device_id, samples) in per_device {
+    let replay = ReplayPeripheral::new(
+      &session_metadata.id,
+      &session_metadata.session_name,
+      &source_device_id,
+      samples,
+      speed,
+      loop_playback,
+    )?;
+    println!("[Replay] Loaded {} ({} samples, {:.1}s at {}x)",
+      replay.info().id, replay.info().samples, replay.info().duration_s, speed);
+    loaded.push(replay.info().clone());
+    replays.insert(replay.info().id.clone(), Arc::new(replay));
+  }
+
+  Ok(loaded)
+}
+
+#[tauri::command]
+async fn get_replay_devices(
+  replay_devices: tauri::State<'_, ReplayDevicesState>
+) -> Result<Vec<ReplayDeviceInfo>, String> {
+  let replays = replay_devices.0.lock().await;
+  Ok(replays.values().map(|r| r.info().clone()).collect())
+}
+
+#[tauri::command]
+async fn remove_replay_device(
+  device_id: String,
+  replay_devices: tauri::State<'_, ReplayDevicesState>,
+  connected_devices: tauri::State<'_, ConnectedDevicesState>,
+  discovered_devices: tauri::State<'_, DiscoveredDevicesState>,
+  active_notifications: tauri::State<'_, ActiveNotificationsState>,
+) -> Result<(), String> {
+  let replay = replay_devices.0.lock().await.remove(&device_id)
+    .ok_or_else(|| format!("Replay device not found: {}", device_id))?;
+
+  active_notifications.0.lock().await.insert(device_id.clone(), false);
+  connected_devices.0.lock().await.remove(&device_id);
+  discovered_devices.0.lock().await.remove(&device_id);
+  replay.disconnect().await?;
+
+  println!("[Replay] Removed {}", device_id);
+  Ok(())
+}
+
+#[tauri::command]
+async fn list_known_devices(
+  known_devices: tauri::State<'_, KnownDevicesState>
+) -> Result<Vec<KnownDevice>, String> {
+  Ok(known_devices.0.lock().await.list())
+}
+
+async fn check_known_device_profile(device: &KnownDevice, profiles: &DeviceProfilesState) -> Result<(), String> {
+  match &device.profile_id {
+    Some(profile_id) if profiles.0.lock().await.get(profile_id).is_none() => {
+      Err(format!("Profile not found: {}", profile_id))
+    }
+    _ => Ok(()),
+  }
+}
+
+#[tauri::command]
+async fn create_known_device(
+  device: KnownDevice,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  known_devices: tauri::State<'_, KnownDevicesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  app_handle: tauri::AppHandle
+) -> Result<KnownDevice, String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  check_known_device_profile(&device, &profiles).await?;
+  let created = known_devices.0.lock().await.create(device).await?;
+  refresh_known_devices(&app_handle).await;
+  Ok(created)
+}
+
+// Takes effect for the device's profile on its next connection
+#[tauri::command]
+async fn update_known_device(
+  device: KnownDevice,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  known_devices: tauri::State<'_, KnownDevicesState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  app_handle: tauri::AppHandle
+) -> Result<KnownDevice, String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  check_known_device_profile(&device, &profiles).await?;
+  let updated = known_devices.0.lock().await.update(device).await?;
+  refresh_known_devices(&app_handle).await;
+  Ok(updated)
+}
+
+#[tauri::command]
+async fn delete_known_device(
+  address: String,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  known_devices: tauri::State<'_, KnownDevicesState>,
+  app_handle: tauri::AppHandle
+) -> Result<(), String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  known_devices.0.lock().await.delete(&address).await?;
+  refresh_known_devices(&app_handle).await;
+  Ok(())
+}
+
+#[tauri::command]
+async fn list_device_profiles(
+  profiles: tauri::State<'_, DeviceProfilesState>
+) -> Result<Vec<DeviceProfile>, String> {
+  Ok(profiles.0.lock().await.list())
+}
+
+#[tauri::command]
+async fn create_device_profile(
+  profile: DeviceProfile,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  profiles: tauri::State<'_, DeviceProfilesState>
+) -> Result<DeviceProfile, String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  profiles.0.lock().await.create(profile).await
+}
+
+#[tauri::command]
+async fn update_device_profile(
+  profile: DeviceProfile,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<DeviceProfile, String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  let updated = profiles.0.lock().await.update(profile).await?;
+
+  // Devices already using this profile pick up the change on their next stream start
+  let mut assignments = profile_assignments.0.lock().await;
+  for assigned in assignments.values_mut().filter(|p| p.id == updated.id) {
+    *assigned = updated.clone();
+  }
+  Ok(updated)
+}
+
+#[tauri::command]
+async fn delete_device_profile(
+  profile_id: String,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  profiles: tauri::State<'_, DeviceProfilesState>
+) -> Result<(), String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  profiles.0.lock().await.delete(&profile_id).await
+}
+
+#[tauri::command]
+async fn get_device_profile(
+  device_id: String,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<Option<DeviceProfile>, String> {
+  Ok(profile_assignments.0.lock().await.get(&device_id).cloned())
+}
+
+#[tauri::command]
+async fn get_packet_loss_stats(
+  stream_handles: tauri::State<'_, StreamHandlesState>
+) -> Result<HashMap<String, PacketLossStats>, String> {
+  let handles = stream_handles.0.lock().await;
+  Ok(handles.iter().filter_map(|(id, handle)| handle.stats().packet_loss.map(|stats| (id.clone(), stats))).collect())
+}
+
+#[tauri::command]
+async fn get_clock_sync_stats(
+  stream_handles: tauri::State<'_, StreamHandlesState>
+) -> Result<HashMap<String, ClockSyncStats>, String> {
+  let handles = stream_handles.0.lock().await;
+  Ok(handles.iter().filter_map(|(id, handle)| handle.stats().clock_sync.map(|stats| (id.clone(), stats))).collect())
+}
+
+// Delivers samples to the frontend as binary batches over `on_batch` (see sample_stream);
+// a new subscription replaces the previous one. Returns the subscription id.
+#[tauri::command]
+async fn subscribe_sample_stream(
+  on_batch: Channel,
+  sample_stream_state: tauri::State<'_, SampleStreamState>,
+) -> Result<u32, String> {
+  let subscription_id = sample_stream_state.0.lock().await.subscribe(on_batch);
+  println!("[SampleStream] Subscriber {} attached", subscription_id);
+  Ok(subscription_id)
+}
+
+#[tauri::command]
+async fn unsubscribe_sample_stream(
+  subscription_id: u32,
+  sample_stream_state: tauri::State<'_, SampleStreamState>,
+) -> Result<bool, String> {
+  Ok(sample_stream_state.0.lock().await.unsubscribe(subscription_id))
+}
+
+#[tauri::command]
+async fn get_stream_settings(
+  sample_stream_state: tauri::State<'_, SampleStreamState>,
+) -> Result<StreamSettings, String> {
+  Ok(sample_stream_state.0.lock().await.settings().clone())
+}
+
+// Saved in the app data directory; applies to running streams immediately
+#[tauri::command]
+async fn set_stream_settings(
+  settings: StreamSettings,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  sample_stream_state: tauri::State<'_, SampleStreamState>,
+) -> Result<(), String> {
+  validate_csrf!(csrf_state, &csrf_token);
+  sample_stream_state.0.lock().await.set_settings(settings).await
+}
+
+// Diagnostics of every device that has streamed since launch, or of one device
+#[tauri::command]
+async fn get_stream_diagnostics(
+  device_id: Option<String>,
+  stream_handles: tauri::State<'_, StreamHandlesState>,
+) -> Result<Vec<StreamDiagnosticsSnapshot>, String> {
+  let handles = stream_handles.0.lock().await;
+  Ok(handles.iter()
+    .filter(|(id, _)| device_id.as_ref().map_or(true, |wanted| wanted == *id))
+    .filter_map(|(_, handle)| handle.diagnostics())
+    .collect())
+}
+
+#[tauri::command]
+async fn get_stream_states(
+  stream_handles: tauri::State<'_, StreamHandlesState>,
+) -> Result<HashMap<String, StreamState>, String> {
+  let handles = stream_handles.0.lock().await;
+  Ok(handles.iter().map(|(device_id, handle)| (device_id.clone(), handle.state())).collect())
+}
+
+// Streams simulated devices through the notification pipeline and reports the throughput.
+// Connected devices, events and the frontend's sample stream are left alone.
+#[tauri::command]
+async fn run_stream_benchmark(
+  device_count: Option<usize>,
+  rate_hz: Option<u16>,
+  duration_s: Option<u64>,
+) -> Result<StreamBenchmarkReport, String> {
+  let device_count = device_count.unwrap_or(stream_benchmark::DEFAULT_DEVICE_COUNT);
+  let rate_hz = rate_hz.unwrap_or(stream_benchmark::DEFAULT_RATE_HZ);
+  let duration_s = duration_s.unwrap_or(stream_benchmark::DEFAULT_DURATION_S);
+  stream_benchmark::validate(device_count, rate_hz, duration_s)?;
+  println!("[Benchmark] Streaming {} simulated devices at {} Hz for {} s", device_count, rate_hz, duration_s);
+
+  let profile = DeviceProfile::gaitble_default();
+  let control = profile.control.clone().ok_or("The default profile has no control characteristic")?;
+  let (sink, sample_rx) = SampleSink::new();
+  let stop_encoder = Arc::new(std::sync::atomic::AtomicBool::new(false));
+  let encoder = tauri::async_runtime::spawn(stream_benchmark::run_encoder(sample_rx, stop_encoder.clone()));
+  let time_base = TimeBase::now();
+
+  let mut streams = Vec::new();
+  for peripheral in transport::simulated::benchmark_devices(device_count) {
+    let handle = Arc::new(StreamHandle::new());
+    let setup: Result<_, String> = async {
+      peripheral.connect().await?;
+      device_control::send_control_command(&peripheral, &control, ControlCommand::SetSampleRate(rate_hz)).await?;
+      let characteristic = gatt_explorer::find_gatt_characteristic(&peripheral, profile.service_uuid, profile.characteristic_uuid).await?;
+      spawn_stream(peripheral.clone(), StreamContext {
+        device_id: peripheral.id(),
+        profile: profile.clone(),
+        handle: handle.clone(),
+        sink: sink.clone(),
+        time_base,
+        stall_timeout: Duration::from_millis(StreamSettings::default().stall_timeout_ms),
+        carryover: StreamCarryover::default(),
+        app_handle: None,
+      });
+      peripheral.subscribe(&characteristic).await?;
+      Ok(characteristic)
+    }.await;
+
+    match setup {
+      Ok(characteristic) => streams.push((peripheral, characteristic, handle, Instant::now())),
+      // Stop every stream started so far, and the encoder, before giving up
+      Err(e) => {
+        handle.cancel();
+        let _ = peripheral.disconnect().await;
+        for (peripheral, characteristic, handle, _) in streams {
+          let _ = peripheral.unsubscribe(&characteristic).await;
+          handle.cancel();
+          let _ = peripheral.disconnect().await;
+        }
+        stop_encoder.store(true, std::sync::atomic::Ordering::Relaxed);
+        let _ = encoder.await;
+        return Err(e);
+      }
+    }
+  }
+
+  let started = Instant::now();
+  async_std::task::sleep(Duration::from_secs(duration_s)).await;
+
+  let mut subscribed = Vec::new();
+  for (peripheral, characteristic, _, subscribed_at) in &streams {
+    let _ = peripheral.unsubscribe(characteristic).await;
+    subscribed.push(subscribed_at.elapsed());
+  }
+  let duration = started.elapsed();
+  // Let samples already in the pipeline reach the encoder
+  async_std::task::sleep(Duration::from_millis(200)).await;
+  stop_encoder.store(true, std::sync::atomic::Ordering::Relaxed);
+  let totals = encoder.await.map_err(|e| format!("Benchmark encoder failed: {}", e))?;
+
+  let mut streamed = Vec::new();
+  for ((peripheral, _, handle, _), subscribed) in streams.into_iter().zip(subscribed) {
+    handle.cancel();
+    let _ = peripheral.disconnect().await;
+    if let Some(snapshot) = handle.diagnostics() {
+      streamed.push((snapshot, subscribed));
+    }
+  }
+
+  let report = stream_benchmark::build_report(rate_hz, duration, streamed, &totals);
+  report.print_summary();
+  Ok(report)
+}
+
+#[tauri::command]
+async fn get_decode_errors(
+  stream_handles: tauri::State<'_, StreamHandlesState>
+) -> Result<HashMap<String, DecodeErrorStats>, String> {
+  let handles = stream_handles.0.lock().await;
+  Ok(handles.iter().map(|(id, handle)| (id.clone(), handle.stats().decode_errors)).collect())
+}
+
+// Manual override of the automatically resolved profile
+#[tauri::command]
+async fn set_device_profile(
+  device_id: String,
+  profile_id: String,
+  profiles: tauri::State<'_, DeviceProfilesState>,
+  profile_assignments: tauri::State<'_, DeviceProfileAssignmentsState>
+) -> Result<DeviceProfile, String> {
+  let profile = profiles.0.lock().await.get(&profile_id)
+    .ok_or_else(|| format!("Profile not found: {}", profile_id))?;
+  profile_assignments.0.lock().await.insert(device_id, profile.clone());
+  Ok(profile)
+}
+
 // File system and data management commands
 
+// A recorded device with the registry alias and role it had at save time
+#[derive(Serialize, serde::Deserialize, Clone)]
+#[serde(from = "SessionDeviceEntry")]
+struct SessionDevice {
+  id: String,
+  alias: Option<String>,
+  role: Option<DeviceRole>,
+}
+
+// Sessions saved before the known-device registry list bare device ids
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum SessionDeviceEntry {
+  Id(String),
+  Device {
+    id: String,
+    #[serde(default)]
+    alias: Option<String>,
+    #[serde(default)]
+    role: Option<DeviceRole>,
+  },
+}
+
+impl From<SessionDeviceEntry> for SessionDevice {
+  fn from(entry: SessionDeviceEntry) -> Self {
+    match entry {
+      SessionDeviceEntry::Id(id) => SessionDevice { id, alias: None, role: None },
+      SessionDeviceEntry::Device { id, alias, role } => SessionDevice { id, alias, role },
+    }
+  }
+}
+
 #[derive(Serialize, serde::Deserialize, Clone)]
 struct SessionMetadata {
   id: String,
@@ -1535,25 +3088,176 @@ struct SessionMetadata {
   timestamp: u64,
   data_points: usize,
   file_path: String,
-  devices: Vec<String>,
+  devices: Vec<SessionDevice>,
+  // Sequence-counter loss per device, for devices whose profile has a counter
+  #[serde(default)]
+  packet_loss: HashMap<String, PacketLossStats>,
+  // Per-device corrections from align_session_devices, added to the stored timestamps
+  #[serde(default)]
+  time_offsets_ms: HashMap<String, f64>,
+  // Link drops during the recording
+  #[serde(default)]
+  gaps: Vec<StreamGap>,
+  // Serial number and firmware revision each device reported when connected
+  #[serde(default)]
+  device_info: HashMap<String, DeviceIdentity>,
+  // Set while a backend recording is writing the session's journal
+  #[serde(default)]
+  in_progress: bool,
+  // Finished by the recovery pass after the app quit mid-recording
+  #[serde(default)]
+  recovered: bool,
+  // Host timestamps (epoch ms) of the first and last sample in the file
+  #[serde(default)]
+  captured_range_ms: Option<(u64, u64)>,
+}
+
+// Storage directory of a new session: the requested one if it is allowed, else the default
+fn session_storage_path(storage_path: Option<String>, config: &path_manager::PathConfig) -> Result<PathBuf, String> {
+  let Some(user_path) = storage_path else {
+    return Ok(config.get_default_storage_path());
+  };
+  let path = Path::new(&user_path);
+
+  // Security checks for path traversal
+  if user_path.contains("..") || user_path.contains("~") {
+    return Err("Invalid path: Path traversal not allowed".to_string());
+  }
+
+  // Ensure path exists and is allowed
+  if !config.is_path_allowed(path) {
+    return Err("Invalid path: Path is not within allowed directories".to_string());
+  }
+
+  Ok(path.to_path_buf())
+}
+
+// gait_<start time>_<sanitized session name>.gait
+fn session_file_name(session_name: &str, timestamp_secs: u64) -> String {
+  format!("gait_{}_{}.{}",
+    chrono::DateTime::from_timestamp(timestamp_secs as i64, 0)
+      .unwrap()
+      .format("%Y%m%d_%H%M%S"),
+    path_manager::PathConfig::sanitize_filename(session_name),
+    session_file::FILE_EXTENSION
+  )
+}
+
+// Loss summary for the devices in this session that report a sequence counter
+async fn session_packet_loss(devices: &HashSet<String>, stream_handles: &StreamHandlesState) -> HashMap<String, PacketLossStats> {
+  let handles = stream_handles.0.lock().await;
+  devices.iter()
+    .filter_map(|d| handles.get(d).and_then(|h| h.stats().packet_loss).map(|stats| (d.clone(), stats)))
+    .collect()
+}
+
+// Link drops of these devices that overlap the recorded data
+async fn session_gaps(devices: &HashSet<String>, first_ts: u64, last_ts: u64, stream_gaps_state: &StreamGapsState) -> Vec<StreamGap> {
+  stream_gaps_state.0.lock().await.iter()
+    .filter(|g| devices.contains(&g.device_id) && g.end_ms >= first_ts && g.start_ms <= last_ts)
+    .cloned()
+    .collect()
+}
+
+// Hardware identity of the devices that reported one
+async fn session_device_info(devices: &HashSet<String>, device_info_state: &DeviceInfoState) -> HashMap<String, DeviceIdentity> {
+  let infos = device_info_state.0.lock().await;
+  devices.iter()
+    .filter_map(|d| infos.get(d).map(|info| (d.clone(), info.identity())))
+    .collect()
+}
+
+// Registry alias and role of each device, as the registry has them now
+async fn session_devices(devices: &HashSet<String>, known_devices: &KnownDevicesState) -> Vec<SessionDevice> {
+  let registry = known_devices.0.lock().await;
+  let mut ids: Vec<&String> = devices.iter().collect();
+  ids.sort();
+  ids.into_iter()
+    .map(|id| {
+      let known = registry.get(id);
+      SessionDevice {
+        id: id.clone(),
+        alias: known.map(|d| d.alias.clone()),
+        role: known.and_then(|d| d.role),
+      }
+    })
+    .collect()
+}
+
+fn session_summary_comments(packet_loss: &HashMap<String, PacketLossStats>, gaps: &[StreamGap]) -> String {
+  let mut comments = String::new();
+  for (device_id, stats) in packet_loss {
+    comments.push_str(&format!("# Packet Loss [{}]: {} lost of {} ({:.2}%)\n",
+      device_id, stats.lost, stats.received + stats.lost, stats.loss_percent));
+  }
+  for gap in gaps {
+    comments.push_str(&format!("# Gap [{}]: {} - {} ({} ms, {})\n",
+      gap.device_id, gap.start_ms, gap.end_ms, gap.duration_ms, if gap.resumed { "resumed" } else { "not resumed" }));
+  }
+  comments
+}
+
+fn session_device_comments(device_info: &HashMap<String, DeviceIdentity>, session_devices: &[SessionDevice]) -> String {
+  let mut comments = String::new();
+  for (device_id, identity) in device_info {
+    comments.push_str(&format!("# Device [{}]: serial {}, firmware {}\n",
+      device_id,
+      identity.serial_number.as_deref().unwrap_or("unknown"),
+      identity.firmware_revision.as_deref().unwrap_or("unknown")));
+  }
+  for device in session_devices.iter().filter(|d| d.alias.is_some()) {
+    comments.push_str(&format!("# Alias [{}]: {} ({})\n",
+      device.id,
+      device.alias.as_deref().unwrap_or_default(),
+      device.role.map_or("no role", |role| role.as_str())));
+  }
+  comments
+}
+
+// Name of a session device in exported channel labels: its alias, or the end of its id
+fn session_device_label(session: &SessionMetadata, device_id: &str) -> String {
+  session.devices.iter()
+    .find(|d| d.id == device_id)
+    .and_then(|d| d.alias.clone())
+    .unwrap_or_else(|| {
+      // Last five characters; ids are not guaranteed to be ASCII
+      let start = device_id.char_indices().rev().nth(4).map_or(0, |(i, _)| i);
+      device_id[start..].to_string()
+    })
+}
+
+// Devices as listed in a session file's header
+fn session_file_devices(session_devices: &[SessionDevice]) -> Vec<DeviceDescriptor> {
+  session_devices.iter()
+    .map(|d| DeviceDescriptor {
+      id: d.id.clone(),
+      alias: d.alias.clone(),
+      role: d.role.map(|role| role.as_str().to_string()),
+    })
+    .collect()
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn save_session_data(
   session_name: String,
   subject_id: String,
   notes: String,
   data: Vec<GaitData>,
   storage_path: Option<String>,
+  compress: Option<bool>,
   csrf_token: String,
   csrf_state: tauri::State<'_, CSRFTokenState>,
-  path_config: tauri::State<'_, PathConfigState>
+  path_config: tauri::State<'_, PathConfigState>,
+  stream_handles: tauri::State<'_, StreamHandlesState>,
+  stream_gaps_state: tauri::State<'_, StreamGapsState>,
+  device_info_state: tauri::State<'_, DeviceInfoState>,
+  known_devices: tauri::State<'_, KnownDevicesState>
 ) -> Result<String, String> {
   // CSRF Protection with rate limiting
   validate_file_operation!(csrf_state, &csrf_token, "save_session_data");
   
   use tokio::fs;
-  use std::path::Path;
   
   // Input validation
   if data.is_empty() {
@@ -1563,34 +3267,13 @@ async fn save_session_data(
   if session_name.trim().is_empty() {
     return Err("Session name cannot be empty".to_string());
   }
-  
-  if subject_id.trim().is_empty() {
-    return Err("Subject ID cannot be empty".to_string());
-  }
-
-  // Get path configuration
-  let config = path_config.0.lock().await;
-
-  // Validate and determine storage path
-  let base_path = if let Some(user_path) = storage_path {
-    // Validate the user-provided path
-    let path = Path::new(&user_path);
-    
-    // Security checks for path traversal
-    if user_path.contains("..") || user_path.contains("~") {
-      return Err("Invalid path: Path traversal not allowed".to_string());
-    }
-    
-    // Ensure path exists and is allowed
-    if !config.is_path_allowed(path) {
-      return Err("Invalid path: Path is not within allowed directories".to_string());
-    }
-    
-    path.to_path_buf()
-  } else {
-    // Use default storage path
-    config.get_default_storage_path()
-  };
+  
+  if subject_id.trim().is_empty() {
+    return Err("Subject ID cannot be empty".to_string());
+  }
+
+  // Validate and determine storage path
+  let base_path = session_storage_path(storage_path, &*path_config.0.lock().await)?;
 
   // Create directory if it doesn't exist
   fs::create_dir_all(&base_path).await
@@ -1608,59 +3291,52 @@ async fn save_session_data(
     .unwrap()
     .as_millis() as u64;
   
-  // Use the path manager to sanitize the filename
-  let safe_session_name = path_manager::PathConfig::sanitize_filename(&session_name);
-  
-  let filename = format!("gait_{}_{}.csv", 
-    chrono::DateTime::from_timestamp(file_timestamp as i64, 0)
-      .unwrap()
-      .format("%Y%m%d_%H%M%S"),
-    safe_session_name
-  );
-  
-  let file_path = base_path.join(&filename);
+  let file_path = base_path.join(session_file_name(&session_name, file_timestamp));
 
-  // Generate CSV content
-  let mut csv_content = String::new();
-  
-  // Header with metadata
-  csv_content.push_str(&format!("# Gait Monitor Data Export\n"));
-  csv_content.push_str(&format!("# Session: {}\n", session_name));
-  csv_content.push_str(&format!("# Subject: {}\n", subject_id));
-  csv_content.push_str(&format!("# Notes: {}\n", notes));
-  csv_content.push_str(&format!("# Export Time: {}\n", 
-    chrono::DateTime::from_timestamp((metadata_timestamp / 1000) as i64, 0)
-      .unwrap()
-      .format("%Y-%m-%d %H:%M:%S UTC")
-  ));
-  csv_content.push_str(&format!("# Data Points: {}\n", data.len()));
-  
   // Get unique devices
   let devices: std::collections::HashSet<String> = data.iter()
     .map(|d| d.device_id.clone())
     .collect();
-  csv_content.push_str(&format!("# Devices: {}\n", devices.iter().cloned().collect::<Vec<_>>().join(", ")));
-  csv_content.push_str("#\n");
-  
-  // CSV column headers
-  csv_content.push_str("device_id,timestamp,r1,r2,r3,x,y,z\n");
-  
-  // Data rows
+
+  let first_ts = data.iter().map(|d| d.timestamp).min().unwrap_or(0);
+  let last_ts = data.iter().map(|d| d.timestamp).max().unwrap_or(0);
+  let packet_loss = session_packet_loss(&devices, &stream_handles).await;
+  let gaps = session_gaps(&devices, first_ts, last_ts, &stream_gaps_state).await;
+  let device_info = session_device_info(&devices, &device_info_state).await;
+  let session_devices = session_devices(&devices, &known_devices).await;
+
+  // Samples grouped by device, each in the order it was collected
+  let mut samples: Vec<(String, SampleColumns)> = session_devices.iter()
+    .map(|d| (d.id.clone(), SampleColumns::default()))
+    .collect();
+  let device_index: HashMap<String, usize> = session_devices.iter()
+    .enumerate()
+    .map(|(i, d)| (d.id.clone(), i))
+    .collect();
+  // The frontend's rows carry no clock model output, so session time counts from the first row
   for row in &data {
-    csv_content.push_str(&format!("{},{},{},{},{},{},{},{}\n",
-      row.device_id,
-      row.timestamp,
-      row.r1,
-      row.r2,
-      row.r3,
-      row.x,
-      row.y,
-      row.z
-    ));
+    let timing = SampleTiming {
+      session_time_s: (row.timestamp - first_ts) as f64 / 1000.0,
+      device_timestamp_ms: row.device_timestamp_ms,
+      reconstructed_timestamp_us: None,
+    };
+    samples[device_index[&row.device_id]].1.push(row.timestamp * 1000, timing, [row.r1, row.r2, row.r3, row.x, row.y, row.z]);
   }
 
+  let header = SessionHeader::new(
+    &session_name,
+    &subject_id,
+    &notes,
+    metadata_timestamp,
+    session_file_devices(&session_devices),
+    session_device_comments(&device_info, &session_devices),
+  );
+  let mut footer = format!("# Data Points: {}\n", data.len());
+  footer.push_str(&session_summary_comments(&packet_loss, &gaps));
+  let content = session_file::encode_session(&header, &samples, &footer, compress.unwrap_or(true))?;
+
   // Write file asynchronously
-  fs::write(&file_path, csv_content).await
+  fs::write(&file_path, content).await
     .map_err(|e| format!("Failed to write file: {}", e))?;
 
   // Save session metadata
@@ -1673,7 +3349,14 @@ async fn save_session_data(
     timestamp: metadata_timestamp,
     data_points: data.len(),
     file_path: file_path.to_string_lossy().to_string(),
-    devices: devices.into_iter().collect(),
+    devices: session_devices,
+    packet_loss,
+    time_offsets_ms: HashMap::new(),
+    gaps,
+    device_info,
+    in_progress: false,
+    recovered: false,
+    captured_range_ms: Some((first_ts, last_ts)),
   };
 
   save_session_metadata(&base_path, &metadata).await?;
@@ -1681,6 +3364,266 @@ async fn save_session_data(
   Ok(file_path.to_string_lossy().to_string())
 }
 
+// The session the backend is recording, if any
+#[derive(Clone)]
+pub struct RecordingState(Arc<Mutex<Option<ActiveRecording>>>);
+
+pub struct ActiveRecording {
+  status: RecordingStatus,
+  base_path: PathBuf,
+  // Index entry of the session, marked in progress until the recording stops
+  metadata: SessionMetadata,
+  samples_written: Arc<std::sync::atomic::AtomicU64>,
+  writer: tauri::async_runtime::JoinHandle<Result<(tokio::fs::File, SessionEncoder, WriterTotals), String>>,
+}
+
+// Starts writing the samples of `device_ids` to a new session as they arrive. The devices
+// keep streaming to the frontend as before; starting or stopping them does not affect the
+// recording.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn start_recording(
+  session_name: String,
+  subject_id: String,
+  notes: String,
+  device_ids: Vec<String>,
+  storage_path: Option<String>,
+  compress: Option<bool>,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  path_config: tauri::State<'_, PathConfigState>,
+  recording_state: tauri::State<'_, RecordingState>,
+  sample_stream_state: tauri::State<'_, SampleStreamState>,
+  device_info_state: tauri::State<'_, DeviceInfoState>,
+  known_devices: tauri::State<'_, KnownDevicesState>
+) -> Result<RecordingStatus, String> {
+  // CSRF Protection with rate limiting
+  validate_file_operation!(csrf_state, &csrf_token, "start_recording");
+
+  use tokio::io::AsyncWriteExt;
+
+  // Input validation
+  if session_name.trim().is_empty() {
+    return Err("Session name cannot be empty".to_string());
+  }
+
+  if subject_id.trim().is_empty() {
+    return Err("Subject ID cannot be empty".to_string());
+  }
+
+  let devices: HashSet<String> = device_ids.into_iter().collect();
+  if devices.is_empty() {
+    return Err("No devices to record".to_string());
+  }
+
+  // Held until the tap is in place so two recordings cannot start at once
+  let mut recording = recording_state.0.lock().await;
+  if let Some(active) = recording.as_ref() {
+    return Err(format!("Already recording session '{}'", active.status.session_name));
+  }
+
+  let base_path = session_storage_path(storage_path, &*path_config.0.lock().await)?;
+  tokio::fs::create_dir_all(&base_path).await
+    .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+  let started_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+  let file_path = base_path.join(session_file_name(&session_name, started_at / 1000));
+
+  // The header holds what is known now; stop_recording appends the rest
+  let device_info = session_device_info(&devices, &device_info_state).await;
+  let session_devices = session_devices(&devices, &known_devices).await;
+  let header = SessionHeader::new(
+    &session_name,
+    &subject_id,
+    &notes,
+    started_at,
+    session_file_devices(&session_devices),
+    session_device_comments(&device_info, &session_devices),
+  );
+  let (encoder, header) = SessionEncoder::new(&header, compress.unwrap_or(true))?;
+
+  let mut file = tokio::fs::File::create(recording::journal_path(&file_path)).await
+    .map_err(|e| format!("Failed to create file: {}", e))?;
+  file.write_all(&header).await
+    .map_err(|e| format!("Failed to write file: {}", e))?;
+  file.sync_data().await
+    .map_err(|e| format!("Failed to sync file: {}", e))?;
+
+  // Indexed up front so recovery finds the session if the app never gets to stop it
+  let metadata = SessionMetadata {
+    id: uuid::Uuid::new_v4().to_string(),
+    session_name,
+    subject_id,
+    notes,
+    timestamp: started_at,
+    data_points: 0,
+    file_path: file_path.to_string_lossy().to_string(),
+    devices: session_devices,
+    packet_loss: HashMap::new(),
+    time_offsets_ms: HashMap::new(),
+    gaps: Vec::new(),
+    device_info,
+    in_progress: true,
+    recovered: false,
+    captured_range_ms: None,
+  };
+  save_session_metadata(&base_path, &metadata).await?;
+
+  let (tx, rx) = async_std::channel::unbounded();
+  sample_stream_state.0.lock().await.set_recording(Some(RecordingTap { device_ids: devices, tx }));
+  let samples_written = Arc::new(std::sync::atomic::AtomicU64::new(0));
+  let writer = tauri::async_runtime::spawn(recording::write_samples(
+    file,
+    encoder,
+    rx,
+    samples_written.clone(),
+  ));
+
+  let status = RecordingStatus {
+    id: metadata.id.clone(),
+    session_name: metadata.session_name.clone(),
+    subject_id: metadata.subject_id.clone(),
+    device_ids: metadata.devices.iter().map(|d| d.id.clone()).collect(),
+    started_at,
+    file_path: metadata.file_path.clone(),
+    samples_written: 0,
+  };
+  println!("[Recording] Started '{}' for {:?} -> {}", status.session_name, status.device_ids, status.file_path);
+  *recording = Some(ActiveRecording {
+    status: status.clone(),
+    base_path,
+    metadata,
+    samples_written,
+    writer,
+  });
+  Ok(status)
+}
+
+// Stops the recording once every queued sample is on disk, appends the summary and moves
+// the journal to the session file
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn stop_recording(
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  recording_state: tauri::State<'_, RecordingState>,
+  sample_stream_state: tauri::State<'_, SampleStreamState>,
+  stream_handles: tauri::State<'_, StreamHandlesState>,
+  stream_gaps_state: tauri::State<'_, StreamGapsState>,
+  active_notifications: tauri::State<'_, ActiveNotificationsState>,
+  time_base_state: tauri::State<'_, TimeBaseState>
+) -> Result<SessionMetadata, String> {
+  // CSRF Protection with rate limiting
+  validate_file_operation!(csrf_state, &csrf_token, "stop_recording");
+
+  let Some(active) = recording_state.0.lock().await.take() else {
+    return Err("No recording in progress".to_string());
+  };
+  // Devices that stopped streaming during the recording kept the time base for it
+  if !active_notifications.0.lock().await.values().any(|&streaming| streaming) {
+    *time_base_state.0.lock().await = None;
+  }
+  // Dropping the tap lets the writer drain what is queued and return
+  sample_stream_state.0.lock().await.set_recording(None);
+  // On failure the journal stays in place for the recovery pass
+  let (file, encoder, totals) = active.writer.await
+    .map_err(|e| format!("Recording writer failed: {}", e))??;
+
+  let stopped_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+  let mut metadata = active.metadata;
+  let devices: HashSet<String> = metadata.devices.iter().map(|d| d.id.clone()).collect();
+  metadata.packet_loss = session_packet_loss(&devices, &stream_handles).await;
+  metadata.gaps = session_gaps(&devices, metadata.timestamp, stopped_at, &stream_gaps_state).await;
+  metadata.data_points = totals.samples as usize;
+  metadata.captured_range_ms = totals.captured_range_ms();
+  metadata.in_progress = false;
+
+  let mut footer = String::new();
+  footer.push_str(&format!("# Recording Stopped: {}\n",
+    chrono::DateTime::from_timestamp_millis(stopped_at as i64)
+      .unwrap()
+      .format("%Y-%m-%d %H:%M:%S UTC")
+  ));
+  footer.push_str(&totals.comments());
+  footer.push_str(&session_summary_comments(&metadata.packet_loss, &metadata.gaps));
+  recording::finish_journal(file, Path::new(&metadata.file_path), &encoder.finish(&footer)).await?;
+  save_session_metadata(&active.base_path, &metadata).await?;
+
+  println!("[Recording] Stopped '{}': {} samples in {:.1} s",
+    metadata.session_name, totals.samples, stopped_at.saturating_sub(metadata.timestamp) as f64 / 1000.0);
+  Ok(metadata)
+}
+
+// Sessions finished by the recovery pass at startup
+#[derive(Clone)]
+pub struct RecoveredSessionsState(Arc<Mutex<Vec<SessionMetadata>>>);
+
+// Finishes the journals of recordings that were still in progress when the app last quit,
+// marking them recovered with the time range their rows cover. Entries without a journal
+// either reached their session file (and only the index update was lost) or never wrote a
+// row, and are dropped unless the file is there.
+async fn recover_sessions(base_path: &Path) -> Result<Vec<SessionMetadata>, String> {
+  let mut sessions = read_sessions_index(base_path).await?;
+  if !sessions.iter().any(|s| s.in_progress) {
+    return Ok(vec![]);
+  }
+
+  let mut recovered = Vec::new();
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+  for session in sessions.iter_mut().filter(|s| s.in_progress) {
+    let file_path = PathBuf::from(&session.file_path);
+    let journal = recording::journal_path(&file_path);
+    if !journal.exists() {
+      continue;
+    }
+    let result = async {
+      let (file, encoder, totals) = recording::recover_journal(&journal).await?;
+      let mut footer = format!("# Recovered: {}\n",
+        chrono::DateTime::from_timestamp_millis(now as i64)
+          .unwrap()
+          .format("%Y-%m-%d %H:%M:%S UTC"));
+      footer.push_str(&totals.comments());
+      recording::finish_journal(file, &file_path, &encoder.finish(&footer)).await?;
+      Ok::<_, String>(totals)
+    }.await;
+    match result {
+      Ok(totals) => {
+        println!("[Recording] Recovered '{}': {} samples -> {}", session.session_name, totals.samples, session.file_path);
+        session.data_points = totals.samples as usize;
+        session.captured_range_ms = totals.captured_range_ms();
+        session.in_progress = false;
+        session.recovered = true;
+        recovered.push(session.clone());
+      }
+      Err(e) => println!("[Recording] Failed to recover '{}' from {:?}: {}", session.session_name, journal, e),
+    }
+  }
+
+  for session in sessions.iter_mut().filter(|s| s.in_progress && Path::new(&s.file_path).exists()) {
+    session.in_progress = false;
+  }
+  sessions.retain(|s| !s.in_progress || recording::journal_path(Path::new(&s.file_path)).exists());
+  save_sessions_metadata(base_path, &sessions).await?;
+  Ok(recovered)
+}
+
+#[tauri::command]
+async fn get_recovered_sessions(
+  recovered_sessions: tauri::State<'_, RecoveredSessionsState>
+) -> Result<Vec<SessionMetadata>, String> {
+  Ok(recovered_sessions.0.lock().await.clone())
+}
+
+#[tauri::command]
+async fn get_recording_status(
+  recording_state: tauri::State<'_, RecordingState>
+) -> Result<Option<RecordingStatus>, String> {
+  Ok(recording_state.0.lock().await.as_ref().map(|active| RecordingStatus {
+    samples_written: active.samples_written.load(std::sync::atomic::Ordering::Relaxed),
+    ..active.status.clone()
+  }))
+}
+
 #[tauri::command]
 async fn get_sessions(
   path_config: tauri::State<'_, PathConfigState>
@@ -1711,19 +3654,16 @@ async fn delete_session(
   let sessions = get_sessions(path_config.clone()).await?;
 
   if let Some(session) = sessions.iter().find(|s| s.id == session_id).cloned() {
-    // Delete the data file asynchronously
-    if Path::new(&session.file_path).exists() {
-      tokio::fs::remove_file(&session.file_path).await
-        .map_err(|e| format!("Failed to delete data file: {}", e))?;
-    }
+    recording::delete_session_file(&session.session_name, Path::new(&session.file_path), session.in_progress).await?;
     
     // Remove from metadata
     let base_path = Path::new(&session.file_path).parent()
       .ok_or("Invalid file path")?;
     
-    let remaining_sessions: Vec<SessionMetadata> = sessions
+    // Sessions still being recorded have no file yet and stay indexed
+    let remaining_sessions: Vec<SessionMetadata> = read_sessions_index(base_path).await?
       .into_iter()
-      .filter(|s| s.id != session_id)
+      .filter(|s| s.id != session_id && (s.in_progress || Path::new(&s.file_path).exists()))
       .collect();
     
     save_sessions_metadata(base_path, &remaining_sessions).await?;
@@ -1732,6 +3672,73 @@ async fn delete_session(
   Ok(())
 }
 
+// Refines the relative timing of the devices in a saved session by cross-correlating
+// accelerometer transients (e.g. a sync tap) and stores the offsets in the session metadata
+#[tauri::command]
+async fn align_session_devices(
+  session_id: String,
+  reference_device: Option<String>,
+  max_lag_ms: Option<f64>,
+  path_config: tauri::State<'_, PathConfigState>
+) -> Result<Vec<DeviceAlignment>, String> {
+  let sessions = get_sessions(path_config.clone()).await?;
+  let mut session = sessions.into_iter()
+    .find(|s| s.id == session_id)
+    .ok_or("Session not found")?;
+
+  // Measured on the stored timestamps: the new offsets replace the previous ones
+  let per_device = SessionContent::read(&session.file_path, None, &HashMap::new()).await?.per_device();
+  if per_device.len() < 2 {
+    return Err("Alignment needs at least two devices in the session".to_string());
+  }
+
+  let reference_id = match reference_device {
+    Some(id) if per_device.contains_key(&id) => id,
+    Some(id) => return Err(format!("Device {} not found in session", id)),
+    None => per_device.keys().next().cloned().unwrap_or_default(),
+  };
+  let reference = &per_device[&reference_id];
+  let max_lag_ms = max_lag_ms.unwrap_or(500.0);
+
+  let mut alignments = vec![DeviceAlignment {
+    device_id: reference_id.clone(),
+    offset_ms: 0.0,
+    correlation: 1.0,
+  }];
+  for (device_id, samples) in per_device.iter().filter(|(id, _)| **id != reference_id) {
+    let (offset_ms, correlation) = alignment::align_to_reference(reference, samples, max_lag_ms)
+      .map_err(|e| format!("Failed to align {}: {}", device_id, e))?;
+    println!("[Alignment] {} vs {}: offset {:.2} ms (correlation {:.3})", device_id, reference_id, offset_ms, correlation);
+    alignments.push(DeviceAlignment {
+      device_id: device_id.clone(),
+      offset_ms,
+      correlation,
+    });
+  }
+
+  session.time_offsets_ms = alignments.iter().map(|a| (a.device_id.clone(), a.offset_ms)).collect();
+  let base_path = Path::new(&session.file_path).parent()
+    .ok_or("Invalid file path")?
+    .to_path_buf();
+  save_session_metadata(&base_path, &session).await?;
+
+  Ok(alignments)
+}
+
+#[tauri::command]
+async fn get_stream_gaps(
+  stream_gaps_state: tauri::State<'_, StreamGapsState>
+) -> Result<Vec<StreamGap>, String> {
+  Ok(stream_gaps_state.0.lock().await.clone())
+}
+
+#[tauri::command]
+async fn get_time_base(
+  time_base_state: tauri::State<'_, TimeBaseState>
+) -> Result<Option<u64>, String> {
+  Ok(time_base_state.0.lock().await.map(|base| base.epoch_us))
+}
+
 #[tauri::command]
 async fn choose_storage_directory(
   app_handle: tauri::AppHandle,
@@ -1778,6 +3785,7 @@ async fn choose_storage_directory(
 }
 
 // Helper functions for session metadata management
+// Adds the session to the index, replacing an earlier entry with the same id
 async fn save_session_metadata(base_path: &Path, metadata: &SessionMetadata) -> Result<(), String> {
   let metadata_path = base_path.join("sessions_index.json");
   
@@ -1791,24 +3799,31 @@ async fn save_session_metadata(base_path: &Path, metadata: &SessionMetadata) ->
     vec![]
   };
   
-  // Add new session
+  sessions.retain(|s| s.id != metadata.id);
   sessions.push(metadata.clone());
   
   // Save updated sessions
   save_sessions_metadata(Path::new(base_path), &sessions).await
 }
 
+// Written to a temporary file and renamed over the index, so a crash mid-write leaves the
+// previous index intact
 async fn save_sessions_metadata(base_path: &Path, sessions: &[SessionMetadata]) -> Result<(), String> {
-  let metadata_path = base_path.join("sessions_index.json");  let content = serde_json::to_string_pretty(sessions)
+  let metadata_path = base_path.join("sessions_index.json");
+  let temp_path = base_path.join("sessions_index.json.tmp");
+  let content = serde_json::to_string_pretty(sessions)
     .map_err(|e| format!("Failed to serialize sessions: {}", e))?;
 
-  tokio::fs::write(&metadata_path, content).await
+  tokio::fs::write(&temp_path, content).await
+    .map_err(|e| format!("Failed to write sessions index: {}", e))?;
+  tokio::fs::rename(&temp_path, &metadata_path).await
     .map_err(|e| format!("Failed to write sessions index: {}", e))?;
   
   Ok(())
 }
 
-async fn load_sessions_from_path(base_path: &Path) -> Result<Vec<SessionMetadata>, String> {
+// Every indexed session, including ones whose file is missing or still being recorded
+async fn read_sessions_index(base_path: &Path) -> Result<Vec<SessionMetadata>, String> {
   let metadata_path = base_path.join("sessions_index.json");
   
   if !metadata_path.exists() {
@@ -1818,10 +3833,14 @@ async fn load_sessions_from_path(base_path: &Path) -> Result<Vec<SessionMetadata
   let content = tokio::fs::read_to_string(&metadata_path).await
     .map_err(|e| format!("Failed to read sessions index: {}", e))?;
   
-  let sessions: Vec<SessionMetadata> = serde_json::from_str(&content)
-    .map_err(|e| format!("Failed to parse sessions index: {}", e))?;
+  serde_json::from_str(&content)
+    .map_err(|e| format!("Failed to parse sessions index: {}", e))
+}
+
+async fn load_sessions_from_path(base_path: &Path) -> Result<Vec<SessionMetadata>, String> {
+  let sessions = read_sessions_index(base_path).await?;
   
-  // Filter out sessions with missing files
+  // Filter out sessions with missing files (including ones still being recorded)
   let valid_sessions: Vec<SessionMetadata> = sessions
     .into_iter()
     .filter(|s| Path::new(&s.file_path).exists())
@@ -1830,28 +3849,58 @@ async fn load_sessions_from_path(base_path: &Path) -> Result<Vec<SessionMetadata
   Ok(valid_sessions)
 }
 
-fn parse_gait_data(data: &[u8], device_id: &str, timestamp_ms: u64) -> Result<GaitData, String> {
-  if data.len() != 24 {
-    return Err(format!("Invalid data length: {} (expected 24)", data.len()));
-  }
-  
-  // Parse 6 floats in little-endian format
-  let r1 = f32::from_le_bytes([data[0], data[1], data[2], data[3]]);
-  let r2 = f32::from_le_bytes([data[4], data[5], data[6], data[7]]);
-  let r3 = f32::from_le_bytes([data[8], data[9], data[10], data[11]]);
-  let x = f32::from_le_bytes([data[12], data[13], data[14], data[15]]);
-  let y = f32::from_le_bytes([data[16], data[17], data[18], data[19]]);
-  let z = f32::from_le_bytes([data[20], data[21], data[22], data[23]]);
-  
-  Ok(GaitData {
-    device_id: device_id.to_string(),
-    r1,
-    r2,
-    r3,
-    x,
-    y,
-    z,
-  timestamp: timestamp_ms,
+// Decodes one notification that arrived at `timestamp_us`. The sample blocks of a packet were
+// taken before it was sent, so the last one is placed at the arrival time and the others one
+// sample interval apart before it; the interval is the profile's, else that of the measured
+// `sample_rate`, and until either is known the blocks share the arrival time.
+fn parse_gait_data(profile: &DeviceProfile, data: &[u8], device_id: &str, timestamp_us: u64, sample_rate: Option<f64>) -> Result<GaitPacket, String> {
+  let packet = profile.packet_layout.decode(data)?;
+  let interval_us = profile.packet_layout.sample_interval_ms
+    .filter(|&ms| ms > 0.0)
+    .map(|ms| ms * 1000.0)
+    .or_else(|| sample_rate.filter(|&rate| rate > 0.0).map(|rate| 1_000_000.0 / rate))
+    .unwrap_or(0.0);
+  let last_block = packet.samples.len().saturating_sub(1);
+  let sample_sequences: Vec<Option<u64>> = packet.samples.iter()
+    .map(|sample| sample.sequence.filter(|_| profile.packet_layout.sequence_per_block()))
+    .collect();
+  let arrival_offsets_us: Vec<u64> = (0..packet.samples.len())
+    .map(|i| ((last_block - i) as f64 * interval_us).round() as u64)
+    .collect();
+  
+  // Map decoded profile channels onto the gait channels; channels the profile doesn't provide stay at 0
+  let samples = packet.samples.into_iter().zip(&arrival_offsets_us).map(|(sample, &arrival_offset_us)| {
+    let mut gait_data = GaitData {
+      device_id: device_id.to_string(),
+      r1: 0.0,
+      r2: 0.0,
+      r3: 0.0,
+      x: 0.0,
+      y: 0.0,
+      z: 0.0,
+      timestamp: timestamp_us.saturating_sub(arrival_offset_us) / 1000,
+      device_timestamp_ms: sample.device_timestamp_ms,
+    };
+    for (channel, value) in sample.values {
+      let value = value as f32;
+      match channel {
+        "r1" => gait_data.r1 = value,
+        "r2" => gait_data.r2 = value,
+        "r3" => gait_data.r3 = value,
+        "x" => gait_data.x = value,
+        "y" => gait_data.y = value,
+        "z" => gait_data.z = value,
+        _ => {}
+      }
+    }
+    gait_data
+  }).collect();
+  
+  Ok(GaitPacket {
+    sequence: packet.sequence,
+    samples,
+    sample_sequences,
+    arrival_offsets_us,
   })
 }
 
@@ -1916,6 +3965,157 @@ async fn copy_file_to_downloads(
   Ok(dest_path.to_string_lossy().to_string())
 }
 
+// Path in the downloads folder for an export, refusing to overwrite an existing file
+async fn export_destination(path_config: &PathConfigState, file_name: &str) -> Result<PathBuf, String> {
+  let safe_filename = path_manager::PathConfig::sanitize_filename(file_name);
+  if safe_filename.is_empty() {
+    return Err("Invalid file name".to_string());
+  }
+
+  let dest_path = path_config.0.lock().await.get_safe_download_path(&safe_filename)
+    .ok_or("Could not determine safe download location")?;
+  if let Some(parent) = dest_path.parent() {
+    tokio::fs::create_dir_all(parent).await
+      .map_err(|e| format!("Failed to create download directory: {}", e))?;
+  }
+
+  // Prevent overwriting existing files without confirmation
+  if dest_path.exists() {
+    return Err("Destination file already exists".to_string());
+  }
+  Ok(dest_path)
+}
+
+// Writes a session to the downloads folder as CSV with its alignment offsets applied;
+// unaligned sessions saved as CSV are copied as is
+#[tauri::command]
+async fn export_session_csv(
+  session_id: String,
+  file_name: String,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  path_config: tauri::State<'_, PathConfigState>
+) -> Result<String, String> {
+  // CSRF Protection with rate limiting
+  validate_file_operation!(csrf_state, &csrf_token, "export_session_csv");
+
+  let sessions = get_sessions(path_config.clone()).await?;
+  let session = sessions.iter()
+    .find(|s| s.id == session_id)
+    .ok_or("Session not found")?;
+  let source_path = Path::new(&session.file_path);
+  let dest_path = export_destination(&path_config, &file_name).await?;
+
+  if session_file::is_session_file(source_path).await {
+    let file = SessionFile::open(source_path).await?;
+    let mut samples = file.read_samples(None).await?;
+    session_content::shift_session_samples(&mut samples, &session.time_offsets_ms);
+    let csv = file.to_csv(&samples, epoch_ms());
+    tokio::fs::write(&dest_path, csv).await
+      .map_err(|e| format!("Failed to write file: {}", e))?;
+  } else if !session.time_offsets_ms.is_empty() {
+    let content = tokio::fs::read_to_string(source_path).await
+      .map_err(|e| format!("Failed to read data file: {}", e))?;
+    tokio::fs::write(&dest_path, session_content::shift_session_csv(&content, &session.time_offsets_ms)).await
+      .map_err(|e| format!("Failed to write file: {}", e))?;
+  } else {
+    tokio::fs::copy(source_path, &dest_path).await
+      .map_err(|e| format!("Failed to copy file: {}", e))?;
+  }
+
+  Ok(dest_path.to_string_lossy().to_string())
+}
+
+// Writes a session to the downloads folder as EDF+ (16-bit samples, the default) or BDF+
+// (24-bit), with the session's link gaps as annotations
+#[tauri::command]
+async fn export_session_edf(
+  session_id: String,
+  file_name: String,
+  format: Option<EdfFormat>,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  path_config: tauri::State<'_, PathConfigState>
+) -> Result<String, String> {
+  // CSRF Protection with rate limiting
+  validate_file_operation!(csrf_state, &csrf_token, "export_session_edf");
+
+  let format = format.unwrap_or(EdfFormat::Edf);
+  let sessions = get_sessions(path_config.clone()).await?;
+  let session = sessions.iter()
+    .find(|s| s.id == session_id)
+    .ok_or("Session not found")?;
+
+  let label = |device_id: &str| session_device_label(session, device_id);
+  let per_device = SessionContent::read(&session.file_path, None, &session.time_offsets_ms).await?.per_device();
+  let edf_session = EdfSession {
+    subject_id: session.subject_id.clone(),
+    session_name: session.session_name.clone(),
+    devices: per_device.into_iter()
+      .map(|(id, samples)| EdfDevice { label: label(&id), id, samples })
+      .collect(),
+    annotations: session.gaps.iter()
+      .map(|gap| EdfAnnotation {
+        onset_ms: gap.start_ms,
+        duration_ms: Some(gap.duration_ms),
+        text: format!("Link gap {}{}", label(&gap.device_id), if gap.resumed { "" } else { " (not resumed)" }),
+      })
+      .chain(session.captured_range_ms.filter(|_| session.recovered).map(|(_, last)| EdfAnnotation {
+        onset_ms: last,
+        duration_ms: None,
+        text: "Recording interrupted".to_string(),
+      }))
+      .collect(),
+  };
+  let content = edf_export::encode(&edf_session, format)?;
+
+  let file_name = Path::new(&file_name).with_extension(format.extension());
+  let dest_path = export_destination(&path_config, &file_name.to_string_lossy()).await?;
+  tokio::fs::write(&dest_path, content).await
+    .map_err(|e| format!("Failed to write file: {}", e))?;
+
+  println!("[Export] {} as {} -> {:?}", session.session_name, format.extension(), dest_path);
+  Ok(dest_path.to_string_lossy().to_string())
+}
+
+// Writes a session to the downloads folder as a C3D trial, one analog channel per device
+// channel, for motion analysis software
+#[tauri::command]
+async fn export_session_c3d(
+  session_id: String,
+  file_name: String,
+  csrf_token: String,
+  csrf_state: tauri::State<'_, CSRFTokenState>,
+  path_config: tauri::State<'_, PathConfigState>
+) -> Result<String, String> {
+  // CSRF Protection with rate limiting
+  validate_file_operation!(csrf_state, &csrf_token, "export_session_c3d");
+
+  let sessions = get_sessions(path_config.clone()).await?;
+  let session = sessions.iter()
+    .find(|s| s.id == session_id)
+    .ok_or("Session not found")?;
+
+  let per_device = SessionContent::read(&session.file_path, None, &session.time_offsets_ms).await?.per_device();
+  let c3d_session = C3dSession {
+    subject_id: session.subject_id.clone(),
+    session_name: session.session_name.clone(),
+    notes: session.notes.clone(),
+    devices: per_device.into_iter()
+      .map(|(id, samples)| C3dDevice { label: session_device_label(session, &id), id, samples })
+      .collect(),
+  };
+  let content = c3d_export::encode(&c3d_session)?;
+
+  let file_name = Path::new(&file_name).with_extension("c3d");
+  let dest_path = export_destination(&path_config, &file_name.to_string_lossy()).await?;
+  tokio::fs::write(&dest_path, content).await
+    .map_err(|e| format!("Failed to write file: {}", e))?;
+
+  println!("[Export] {} as c3d -> {:?}", session.session_name, dest_path);
+  Ok(dest_path.to_string_lossy().to_string())
+}
+
 // Path Configuration Commands
 #[tauri::command]
 async fn get_path_config(
@@ -2006,14 +4206,13 @@ async fn load_session_data(
     .find(|s| s.id == session_id)
     .ok_or("Session not found")?;
 
-  // Parse the CSV file to load actual data
+  // Load the samples from the session file (binary or CSV)
   let file_path = &session_metadata.file_path;
   if !std::path::Path::new(file_path).exists() {
     return Err("Data file not found".to_string());
   }
 
-  let content = tokio::fs::read_to_string(file_path).await
-    .map_err(|e| format!("Failed to read data file: {}", e))?;
+  let content = SessionContent::read(file_path, None, &session_metadata.time_offsets_ms).await?;
 
   let mut data_points = Vec::new();
   let mut devices = std::collections::HashSet::new();
@@ -2024,66 +4223,33 @@ async fn load_session_data(
   // Track per-device unique timestamps
   let mut device_timestamps: std::collections::HashMap<String, std::collections::HashSet<u64>> = std::collections::HashMap::new();
 
-  // Parse CSV content (skip comments and header)
-  let mut header_found = false;
-  for line in content.lines() {
-    let trimmed = line.trim();
-    
-    // Skip comment lines that start with #
-    if trimmed.starts_with('#') || trimmed.is_empty() {
-      continue;
-    }
-    
-    // Skip the header line (device_id,timestamp,r1,r2,r3,x,y,z)
-    if !header_found && trimmed.starts_with("device_id") {
-      header_found = true;
-      continue;
-    }
-    
-    let parts: Vec<&str> = line.split(',').collect();
-    // Expected format: device_id,timestamp,r1,r2,r3,x,y,z
-    if parts.len() >= 8 {
-      if let (Ok(timestamp), device_id) = (
-        parts[1].parse::<u64>(),
-        parts[0]
-      ) {
-        let device_id = device_id.to_string();
-        devices.insert(device_id.clone());
-        min_timestamp = min_timestamp.min(timestamp);
-        max_timestamp = max_timestamp.max(timestamp);
-
-        // Record timestamp once per line per device
-        device_timestamps
-          .entry(device_id.clone())
-          .or_insert_with(std::collections::HashSet::new)
-          .insert(timestamp);
-
-        // Parse each sensor value as a separate data point
-        let sensor_data = [
-          ("r1", parts[2], "Ω"),
-          ("r2", parts[3], "Ω"), 
-          ("r3", parts[4], "Ω"),
-          ("x", parts[5], "g"),
-          ("y", parts[6], "g"),
-          ("z", parts[7], "g"),
-        ];
-
-        for (data_type, value_str, unit) in sensor_data {
-          if let Ok(value) = value_str.parse::<f64>() {
-            data_types.insert(data_type.to_string());
-            
-            data_points.push(DataPoint {
-              timestamp,
-              device_id: device_id.clone(),
-              data_type: data_type.to_string(),
-              value,
-              unit: unit.to_string(),
-            });
-          }
-        }
+  content.for_each_row(|device_id, timestamp, values| {
+    let device_id = device_id.to_string();
+    devices.insert(device_id.clone());
+    min_timestamp = min_timestamp.min(timestamp);
+    max_timestamp = max_timestamp.max(timestamp);
+
+    // Record timestamp once per line per device
+    device_timestamps
+      .entry(device_id.clone())
+      .or_insert_with(std::collections::HashSet::new)
+      .insert(timestamp);
+
+    // Each sensor value becomes a separate data point
+    for ((data_type, unit), value) in session_file::CHANNELS.into_iter().zip(values) {
+      if let Some(value) = value {
+        data_types.insert(data_type.to_string());
+
+        data_points.push(DataPoint {
+          timestamp,
+          device_id: device_id.clone(),
+          data_type: data_type.to_string(),
+          value,
+          unit: unit.to_string(),
+        });
       }
     }
-  }
+  });
 
   if data_points.is_empty() {
     return Err("No valid data points found in file".to_string());
@@ -2143,14 +4309,14 @@ async fn load_optimized_chart_data(
     .find(|s| s.id == session_id)
     .ok_or("Session not found")?;
 
-  // Parse the CSV file - same as original
+  // Load the samples - same as load_session_data, but binary sessions only decode the
+  // chunks within the requested time range
   let file_path = &session_metadata.file_path;
   if !std::path::Path::new(file_path).exists() {
     return Err("Data file not found".to_string());
   }
 
-  let content = tokio::fs::read_to_string(file_path).await
-    .map_err(|e| format!("Failed to read data file: {}", e))?;
+  let content = SessionContent::read(file_path, start_time.zip(end_time), &session_metadata.time_offsets_ms).await?;
 
   // If only metadata requested, we can scan minimally without collecting all points
   let only_meta = metadata_only.unwrap_or(false);
@@ -2165,86 +4331,52 @@ async fn load_optimized_chart_data(
   let mut device_timestamps: std::collections::HashMap<String, std::collections::HashSet<u64>> = std::collections::HashMap::new();
 
   // Create the device -> data_type -> points structure directly (skip allocation if metadata only)
-  let mut datasets: std::collections::HashMap<String, std::collections::HashMap<String, Vec<ChartPoint>>> = if only_meta { std::collections::HashMap::new() } else { std::collections::HashMap::new() };
+  let mut datasets: std::collections::HashMap<String, std::collections::HashMap<String, Vec<ChartPoint>>> = std::collections::HashMap::new();
 
-  // Parse CSV content - same logic as original load_session_data
-  let mut header_found = false;
-  for line in content.lines() {
-    let trimmed = line.trim();
-    
-    // Skip comment lines that start with #
-    if trimmed.starts_with('#') || trimmed.is_empty() {
-      continue;
+  content.for_each_row(|device_id, timestamp, values| {
+    // Apply time range filter if specified
+    if let (Some(start), Some(end)) = (start_time, end_time) {
+      if timestamp < start || timestamp > end {
+        return;
+      }
     }
-    
-    // Skip the header line (device_id,timestamp,r1,r2,r3,x,y,z)
-    if !header_found && trimmed.starts_with("device_id") {
-      header_found = true;
-      continue;
+
+    // If devices filter is specified and non-empty, apply it
+    if !selected_devices.is_empty() && !selected_devices.iter().any(|d| d == device_id) {
+      return;
     }
-    
-    let parts: Vec<&str> = line.split(',').collect();
-    // Expected format: device_id,timestamp,r1,r2,r3,x,y,z
-    if parts.len() >= 8 {
-      if let (Ok(timestamp), device_id) = (
-        parts[1].parse::<u64>(),
-        parts[0]
-      ) {
-        let device_id = device_id.to_string();
-        
-        // Apply time range filter if specified
-        if let (Some(start), Some(end)) = (start_time, end_time) {
-          if timestamp < start || timestamp > end {
-            continue;
-          }
-        }
-        
-        // If devices filter is specified and non-empty, apply it
-        if !selected_devices.is_empty() && !selected_devices.contains(&device_id) {
+
+    let device_id = device_id.to_string();
+    all_devices.insert(device_id.clone());
+    min_timestamp = min_timestamp.min(timestamp);
+    max_timestamp = max_timestamp.max(timestamp);
+    device_timestamps
+      .entry(device_id.clone())
+      .or_insert_with(std::collections::HashSet::new)
+      .insert(timestamp);
+
+    // Prepare mutable device map reference when collecting full data
+    // (In metadata-only mode we skip allocating point vectors entirely)
+    let mut device_map_opt = if only_meta {
+      None
+    } else {
+      Some(datasets.entry(device_id).or_insert_with(std::collections::HashMap::new))
+    };
+
+    // Each sensor value is a separate data type - same as load_session_data
+    for ((data_type, _), value) in session_file::CHANNELS.into_iter().zip(values) {
+      if let Some(value) = value {
+        if !selected_data_types.is_empty() && !selected_data_types.iter().any(|t| t == data_type) {
           continue;
         }
-        
-        all_devices.insert(device_id.clone());
-        min_timestamp = min_timestamp.min(timestamp);
-        max_timestamp = max_timestamp.max(timestamp);
-        device_timestamps
-          .entry(device_id.clone())
-          .or_insert_with(std::collections::HashSet::new)
-          .insert(timestamp);
-
-        // Prepare mutable device map reference when collecting full data
-        // (In metadata-only mode we skip allocating point vectors entirely)
-        #[allow(unused_mut)]
-        let mut device_map_opt: Option<&mut std::collections::HashMap<String, Vec<ChartPoint>>> = None;
-        if !only_meta {
-          device_map_opt = Some(datasets.entry(device_id.clone()).or_insert_with(std::collections::HashMap::new));
-        }
-
-        // Parse each sensor value as a separate data type - same as original
-        let sensor_data = [
-          ("r1", parts[2]),
-          ("r2", parts[3]), 
-          ("r3", parts[4]),
-          ("x", parts[5]),
-          ("y", parts[6]),
-          ("z", parts[7]),
-        ];
-
-        for (data_type, value_str) in sensor_data {
-          if let Ok(value) = value_str.parse::<f64>() {
-            if !selected_data_types.is_empty() && !selected_data_types.contains(&data_type.to_string()) {
-              continue;
-            }
-            all_data_types.insert(data_type.to_string());
-            if let Some(device_map) = device_map_opt.as_mut() {
-              let data_type_points = device_map.entry(data_type.to_string()).or_insert_with(Vec::new);
-              data_type_points.push(ChartPoint { x: timestamp, y: value });
-            }
-          }
+        all_data_types.insert(data_type.to_string());
+        if let Some(device_map) = device_map_opt.as_mut() {
+          let data_type_points = device_map.entry(data_type.to_string()).or_insert_with(Vec::new);
+          data_type_points.push(ChartPoint { x: timestamp, y: value });
         }
       }
     }
-  }
+  });
 
   if !only_meta && datasets.is_empty() {
     return Err("No valid data points found in file".to_string());
@@ -2402,13 +4534,46 @@ fn main() {
 
   let connected_devices = ConnectedDevicesState(Arc::new(Mutex::new(HashMap::new())));
   let discovered_devices = DiscoveredDevicesState(Arc::new(Mutex::new(HashMap::new())));
-  let bt_manager = BluetoothManagerState(Arc::new(Mutex::new(None)));
+  let discovery_state = DiscoveryState(Arc::new(Mutex::new(Discovery::default())));
+  let transport_kind = TransportKind::from_env();
+  info!("Using {:?} sensor transport (set {} to change)", transport_kind, transport::TRANSPORT_ENV_VAR);
+  let transport_state = TransportState(transport_kind.create_transport());
+  let replay_devices = ReplayDevicesState(Arc::new(Mutex::new(HashMap::new())));
   let active_notifications = ActiveNotificationsState(Arc::new(Mutex::new(HashMap::new())));
   let rate_limiting_state = RateLimitingState::new();
   let csrf_token_state = CSRFTokenState::new();
   let path_config_state = PathConfigState::new().expect("Failed to initialize path config");
-  let sample_rate_state = SampleRateState::new();
-  let duplicate_detection_state = DuplicateDetectionState::new();
+  let app_data_dir = async_std::task::block_on(path_config_state.0.lock()).app_data_dir.clone();
+  let device_profiles = DeviceProfilesState(Arc::new(Mutex::new(ProfileRegistry::load(&app_data_dir))));
+  let scan_filter_state = ScanFilterState(Arc::new(Mutex::new(ScanFilterStore::load(&app_data_dir))));
+  let (sample_stream, sample_rx) = SampleStream::load(&app_data_dir);
+  let sample_stream_state = SampleStreamState(Arc::new(Mutex::new(sample_stream)));
+  let sample_flusher = sample_stream_state.0.clone();
+  let known_devices = KnownDevicesState(Arc::new(Mutex::new(KnownDeviceRegistry::load(&app_data_dir))));
+  if let Some(adapter_id) = transport::load_adapter_preference(&app_data_dir) {
+    info!("Using preferred Bluetooth adapter: {}", adapter_id);
+    if let Err(e) = async_std::task::block_on(transport_state.0.select_adapter(Some(adapter_id))) {
+      warn!("Ignoring saved Bluetooth adapter preference: {}", e);
+    }
+  }
+  let profile_assignments = DeviceProfileAssignmentsState(Arc::new(Mutex::new(HashMap::new())));
+  let time_base_state = TimeBaseState(Arc::new(Mutex::new(None)));
+  let stream_handles = StreamHandlesState(Arc::new(Mutex::new(HashMap::new())));
+  let reconnecting_devices = ReconnectingDevicesState(Arc::new(Mutex::new(HashSet::new())));
+  let stream_gaps_state = StreamGapsState(Arc::new(Mutex::new(Vec::new())));
+  let device_info_state = DeviceInfoState(Arc::new(Mutex::new(HashMap::new())));
+  let battery_listeners = BatteryListenersState(Arc::new(Mutex::new(HashMap::new())));
+  let gatt_subscriptions = GattSubscriptionsState(Arc::new(Mutex::new(HashMap::new())));
+  let recording_state = RecordingState(Arc::new(Mutex::new(None)));
+  let session_storage = async_std::task::block_on(path_config_state.0.lock()).get_default_storage_path();
+  let recovered = async_std::task::block_on(recover_sessions(&session_storage)).unwrap_or_else(|e| {
+    warn!("Session recovery failed: {}", e);
+    Vec::new()
+  });
+  if !recovered.is_empty() {
+    info!("Recovered {} interrupted recording(s)", recovered.len());
+  }
+  let recovered_sessions = RecoveredSessionsState(Arc::new(Mutex::new(recovered)));
 
   info!("All application states initialized successfully");
   
@@ -2428,15 +4593,41 @@ fn main() {
     .plugin(tauri_plugin_dialog::init())
     .manage(connected_devices)
     .manage(discovered_devices)
-    .manage(bt_manager)
+    .manage(discovery_state)
+    .manage(scan_filter_state)
+    .manage(transport_state)
+    .manage(replay_devices)
     .manage(active_notifications)
     .manage(rate_limiting_state)
     .manage(csrf_token_state)
     .manage(path_config_state)
-    .manage(sample_rate_state)
-    .manage(duplicate_detection_state)
+    .manage(device_profiles)
+    .manage(known_devices)
+    .manage(profile_assignments)
+    .manage(sample_stream_state)
+    .manage(time_base_state)
+    .manage(stream_handles)
+    .manage(reconnecting_devices)
+    .manage(stream_gaps_state)
+    .manage(device_info_state)
+    .manage(battery_listeners)
+    .manage(gatt_subscriptions)
+    .manage(recording_state)
+    .manage(recovered_sessions)
+    .setup(|app| {
+      tauri::async_runtime::spawn(watch_transport_events(app.handle().clone()));
+      tauri::async_runtime::spawn(sample_stream::run_flusher(sample_flusher, sample_rx));
+      Ok(())
+    })
     .invoke_handler(tauri::generate_handler![
       scan_devices, 
+      start_discovery,
+      stop_discovery,
+      get_discovered_devices,
+      get_default_scan_filter,
+      set_default_scan_filter,
+      list_bluetooth_adapters,
+      select_bluetooth_adapter,
       connect_device, 
       disconnect_device, 
       get_connected_devices, 
@@ -2445,13 +4636,24 @@ fn main() {
       stop_gait_notifications, 
       get_active_notifications, 
       is_device_collecting, 
-      debug_device_services, 
+      get_gatt_tree,
+      read_characteristic,
+      write_characteristic,
+      subscribe_characteristic,
+      unsubscribe_characteristic,
       check_connection_status, 
       save_session_data, 
+      start_recording,
+      stop_recording,
+      get_recording_status,
+      get_recovered_sessions,
       get_sessions, 
       delete_session, 
       choose_storage_directory, 
       copy_file_to_downloads, 
+      export_session_csv,
+      export_session_edf,
+      export_session_c3d,
       get_csrf_token, 
       refresh_csrf_token, 
       get_security_events,
@@ -2463,7 +4665,40 @@ fn main() {
       save_filtered_data, 
       get_storage_path, 
   get_file_size,
-      get_sample_rate
+      get_sample_rate,
+      get_transport_kind,
+      load_replay_session,
+      get_replay_devices,
+      remove_replay_device,
+      list_device_profiles,
+      create_device_profile,
+      update_device_profile,
+      delete_device_profile,
+      get_device_profile,
+      set_device_profile,
+      get_decode_errors,
+      get_stream_diagnostics,
+      get_stream_states,
+      run_stream_benchmark,
+      subscribe_sample_stream,
+      unsubscribe_sample_stream,
+      get_stream_settings,
+      set_stream_settings,
+      get_packet_loss_stats,
+      get_clock_sync_stats,
+      get_time_base,
+      align_session_devices,
+      get_stream_gaps,
+      get_device_info,
+      set_device_sample_rate,
+      device_start_sampling,
+      device_stop_sampling,
+      device_identify,
+      device_zero,
+      list_known_devices,
+      create_known_device,
+      update_known_device,
+      delete_known_device
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
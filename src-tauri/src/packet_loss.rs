@@ -0,0 +1,174 @@
+// Lost-packet accounting from device sequence counters
+//
+// Profiles with a sequence field let the consumer tell over-the-air loss apart from
+// local channel overflow. Counters are compared modulo the field width so wrap-arounds
+// are not mistaken for gaps; a packet more than half the counter range behind the
+// newest one is treated as arriving late (reordered) rather than as a huge jump. Only a
+// late packet whose number is still outstanding from a gap fills that gap; any other old
+// number is a late duplicate.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+// Most recent skipped sequence numbers remembered for late arrivals
+const MAX_OUTSTANDING: usize = 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PacketLossStats {
+    // Packets with a sequence number that reached the consumer
+    pub received: u64,
+    // Packets skipped by the counter and never seen
+    pub lost: u64,
+    // Number of separate gaps
+    pub gaps: u64,
+    pub largest_gap: u64,
+    pub duplicates: u64,
+    pub reordered: u64,
+    pub wraps: u64,
+    // lost / (received + lost) * 100
+    pub loss_percent: f64,
+}
+
+impl PacketLossStats {
+    fn update_loss_percent(&mut self) {
+        let expected = self.received + self.lost;
+        self.loss_percent = if expected > 0 {
+            self.lost as f64 / expected as f64 * 100.0
+        } else {
+            0.0
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceEvent {
+    InOrder,
+    // `lost` packets between `expected` and `received` never arrived
+    Gap { expected: u64, received: u64, lost: u64 },
+    Duplicate,
+    // A packet older than the newest one seen, arriving after it
+    Reordered,
+}
+
+pub struct SequenceTracker {
+    modulus: u64,
+    last: Option<u64>,
+    // Sequence numbers skipped by gaps, oldest first
+    outstanding: VecDeque<u64>,
+    stats: PacketLossStats,
+}
+
+impl SequenceTracker {
+    // `modulus` is the counter range, e.g. 65536 for a u16 sequence field
+    pub fn new(modulus: u64) -> Self {
+        Self {
+            modulus: modulus.max(2),
+            last: None,
+            outstanding: VecDeque::new(),
+            stats: PacketLossStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &PacketLossStats {
+        &self.stats
+    }
+
+    pub fn record(&mut self, sequence: u64) -> SequenceEvent {
+        let sequence = sequence % self.modulus;
+        let Some(last) = self.last else {
+            self.last = Some(sequence);
+            self.stats.received += 1;
+            self.stats.update_loss_percent();
+            return SequenceEvent::InOrder;
+        };
+
+        let delta = (sequence + self.modulus - last) % self.modulus;
+        if delta == 0 {
+            self.stats.duplicates += 1;
+            return SequenceEvent::Duplicate;
+        }
+
+        let event = if delta <= self.modulus / 2 {
+            if sequence < last {
+                self.stats.wraps += 1;
+            }
+            self.last = Some(sequence);
+            self.stats.received += 1;
+            let lost = delta - 1;
+            if lost == 0 {
+                SequenceEvent::InOrder
+            } else {
+                let remembered = lost.min(MAX_OUTSTANDING as u64);
+                self.outstanding.extend((delta - remembered..delta).map(|i| (last + i) % self.modulus));
+                self.stats.lost += lost;
+                self.stats.gaps += 1;
+                self.stats.largest_gap = self.stats.largest_gap.max(lost);
+                SequenceEvent::Gap {
+                    expected: (last + 1) % self.modulus,
+                    received: sequence,
+                    lost,
+                }
+            }
+        } else if let Some(i) = self.outstanding.iter().position(|&missing| missing == sequence) {
+            // A late packet fills a slot that was already counted as lost
+            self.outstanding.remove(i);
+            self.stats.received += 1;
+            self.stats.reordered += 1;
+            self.stats.lost = self.stats.lost.saturating_sub(1);
+            SequenceEvent::Reordered
+        } else {
+            self.stats.duplicates += 1;
+            return SequenceEvent::Duplicate;
+        };
+        self.forget_stale();
+        self.stats.update_loss_percent();
+        event
+    }
+
+    // Drops skipped numbers too far behind the newest to be told apart from future ones
+    fn forget_stale(&mut self) {
+        let Some(last) = self.last else {
+            return;
+        };
+        while self.outstanding.len() > MAX_OUTSTANDING
+            || self.outstanding.front().is_some_and(|&missing| (last + self.modulus - missing) % self.modulus > self.modulus / 2)
+        {
+            self.outstanding.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn late_packets_fill_gaps_once() {
+        let mut tracker = SequenceTracker::new(256);
+        for sequence in [10, 11, 14] {
+            tracker.record(sequence);
+        }
+        assert_eq!(tracker.stats().lost, 2);
+
+        assert_eq!(tracker.record(12), SequenceEvent::Reordered);
+        assert_eq!(tracker.stats().lost, 1);
+        // Already filled, and 11 was never missing
+        assert_eq!(tracker.record(12), SequenceEvent::Duplicate);
+        assert_eq!(tracker.record(11), SequenceEvent::Duplicate);
+        assert_eq!(tracker.stats().lost, 1);
+        assert_eq!(tracker.stats().received, 4);
+        assert_eq!(tracker.stats().duplicates, 2);
+    }
+
+    #[test]
+    fn gaps_across_a_wrap() {
+        let mut tracker = SequenceTracker::new(256);
+        tracker.record(254);
+        assert_eq!(tracker.record(1), SequenceEvent::Gap { expected: 255, received: 1, lost: 2 });
+        assert_eq!(tracker.stats().wraps, 1);
+        assert_eq!(tracker.record(0), SequenceEvent::Reordered);
+        assert_eq!(tracker.record(255), SequenceEvent::Reordered);
+        assert_eq!(tracker.stats().lost, 0);
+    }
+}
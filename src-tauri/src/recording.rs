@@ -0,0 +1,252 @@
+// Backend-owned session recording
+//
+// While a recording runs, the flusher copies every sample of the recorded devices to a
+// writer task that appends them to the session file as they arrive, one chunk per device
+// per write (see session_file). Samples never cross IPC to be saved, and reloading the
+// webview does not lose the recording. The header holds what is known when the recording
+// starts; the chunk index and the sample count, packet loss and link gaps (as comment
+// lines) are appended when it stops.
+//
+// Until it stops, the session is written to a journal next to its final path (`.journal`
+// appended) and synced to disk every second, so a crash or power loss costs at most the
+// last second. Stopping appends the index and renames the journal to the session file.
+// A journal found on the next launch belongs to a recording that never stopped: recovery
+// drops its torn last chunk, appends the index with what the chunks themselves tell
+// (count and time range) and renames it like a normal stop.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_std::channel::Receiver;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+use crate::sample_stream::QueuedSample;
+use crate::session_file::{SampleColumns, SampleTiming, SessionEncoder, SessionFile, SAMPLE_BYTES};
+
+// Chunks are written once this many bytes are buffered or the oldest is this old
+const WRITE_BUFFER_BYTES: usize = 64 * 1024;
+const WRITE_INTERVAL: Duration = Duration::from_millis(250);
+// Written rows are synced to disk at least this often
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingStatus {
+    pub id: String,
+    pub session_name: String,
+    pub subject_id: String,
+    pub device_ids: Vec<String>,
+    // Epoch milliseconds
+    pub started_at: u64,
+    pub file_path: String,
+    // Rows written to the file so far
+    pub samples_written: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct WriterTotals {
+    pub samples: u64,
+    pub samples_per_device: BTreeMap<String, u64>,
+    // Host timestamps (epoch ms) of the first and last sample
+    pub first_timestamp_ms: Option<u64>,
+    pub last_timestamp_ms: Option<u64>,
+}
+
+impl WriterTotals {
+    fn record(&mut self, device_id: &str, timestamp_ms: u64) {
+        self.samples += 1;
+        *self.samples_per_device.entry(device_id.to_string()).or_default() += 1;
+        self.first_timestamp_ms.get_or_insert(timestamp_ms);
+        self.last_timestamp_ms = Some(timestamp_ms);
+    }
+
+    pub fn captured_range_ms(&self) -> Option<(u64, u64)> {
+        self.first_timestamp_ms.zip(self.last_timestamp_ms)
+    }
+
+    // Summary comment lines for the end of the session file
+    pub fn comments(&self) -> String {
+        let mut comments = format!("# Data Points: {}\n", self.samples);
+        for (device_id, samples) in &self.samples_per_device {
+            let _ = writeln!(comments, "# Data Points [{}]: {}", device_id, samples);
+        }
+        if let Some((first, last)) = self.captured_range_ms() {
+            let _ = writeln!(comments, "# Captured: {} - {}", first, last);
+        }
+        comments
+    }
+}
+
+// Where a session is written until its recording stops
+pub fn journal_path(file_path: &Path) -> PathBuf {
+    let mut journal = file_path.as_os_str().to_owned();
+    journal.push(".journal");
+    PathBuf::from(journal)
+}
+
+// Appends the queued samples to the file as chunks until the recording tap is dropped, then
+// returns the file and encoder for the index
+pub async fn write_samples(
+    mut file: tokio::fs::File,
+    mut encoder: SessionEncoder,
+    rx: Receiver<QueuedSample>,
+    samples_written: Arc<AtomicU64>,
+) -> Result<(tokio::fs::File, SessionEncoder, WriterTotals), String> {
+    let mut totals = WriterTotals::default();
+    let mut buffer: BTreeMap<Arc<str>, SampleColumns> = BTreeMap::new();
+    let mut buffered = 0usize;
+    let mut last_write = Instant::now();
+    let mut last_sync = Instant::now();
+    loop {
+        let closed = match async_std::future::timeout(WRITE_INTERVAL, rx.recv()).await {
+            Ok(Ok(queued)) => {
+                let sample = queued.sample;
+                totals.record(&queued.device_id, sample.timestamp_us / 1000);
+                let timing = SampleTiming {
+                    session_time_s: sample.session_time_s,
+                    device_timestamp_ms: sample.device_timestamp_ms,
+                    reconstructed_timestamp_us: sample.reconstructed_timestamp_us,
+                };
+                buffer.entry(queued.device_id).or_default().push(sample.timestamp_us, timing, sample.values);
+                buffered += 1;
+                false
+            }
+            Ok(Err(_)) => true,
+            Err(_) => false,
+        };
+
+        if closed || buffered * SAMPLE_BYTES >= WRITE_BUFFER_BYTES || (buffered > 0 && last_write.elapsed() >= WRITE_INTERVAL) {
+            let mut bytes = Vec::with_capacity(buffered * SAMPLE_BYTES);
+            for (device_id, columns) in buffer.iter_mut() {
+                bytes.extend(encoder.chunk(device_id, columns)?);
+                columns.clear();
+            }
+            file.write_all(&bytes).await
+                .map_err(|e| format!("Failed to write recording: {}", e))?;
+            buffered = 0;
+            last_write = Instant::now();
+            samples_written.store(totals.samples, Ordering::Relaxed);
+        }
+        if closed {
+            break;
+        }
+        if last_sync.elapsed() >= SYNC_INTERVAL {
+            file.sync_data().await
+                .map_err(|e| format!("Failed to sync recording: {}", e))?;
+            last_sync = Instant::now();
+        }
+    }
+
+    file.flush().await
+        .map_err(|e| format!("Failed to write recording: {}", e))?;
+    Ok((file, encoder, totals))
+}
+
+// Appends the index and summary to the journal, syncs it and moves it to the session's path
+pub async fn finish_journal(mut file: tokio::fs::File, file_path: &Path, trailer: &[u8]) -> Result<(), String> {
+    file.write_all(trailer).await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    file.sync_all().await
+        .map_err(|e| format!("Failed to sync file: {}", e))?;
+    drop(file);
+    tokio::fs::rename(journal_path(file_path), file_path).await
+        .map_err(|e| format!("Failed to finish session file: {}", e))
+}
+
+// Drops a torn last chunk (and an index a stop did not finish writing) from a journal left
+// by an interrupted recording and totals the chunks that made it to disk; returns the
+// journal opened for the index
+pub async fn recover_journal(journal: &Path) -> Result<(tokio::fs::File, SessionEncoder, WriterTotals), String> {
+    let session = SessionFile::open(journal).await?;
+    let mut totals = WriterTotals::default();
+    for chunk in &session.chunks {
+        let device_id = session.device_id(chunk.device)
+            .ok_or_else(|| format!("Chunk at {} names unknown device {}", chunk.offset, chunk.device))?;
+        totals.samples += chunk.samples as u64;
+        *totals.samples_per_device.entry(device_id.to_string()).or_default() += chunk.samples as u64;
+        let (first, last) = (chunk.first_us / 1000, chunk.last_us / 1000);
+        totals.first_timestamp_ms = Some(totals.first_timestamp_ms.map_or(first, |t| t.min(first)));
+        totals.last_timestamp_ms = Some(totals.last_timestamp_ms.map_or(last, |t| t.max(last)));
+    }
+
+    // Appending, so the index lands after the truncated end
+    let file = tokio::fs::OpenOptions::new().append(true).open(journal).await
+        .map_err(|e| format!("Failed to open journal: {}", e))?;
+    file.set_len(session.data_end()).await
+        .map_err(|e| format!("Failed to truncate journal: {}", e))?;
+    Ok((file, SessionEncoder::resume(&session), totals))
+}
+
+// Deletes a saved session's file. Sessions still in progress are refused, as their journal
+// belongs to the running recording (or to the recovery pass on the next launch).
+pub async fn delete_session_file(session_name: &str, file_path: &Path, in_progress: bool) -> Result<(), String> {
+    if in_progress {
+        return Err(format!("Session '{}' is still being recorded; stop the recording before deleting it", session_name));
+    }
+    if file_path.exists() {
+        tokio::fs::remove_file(file_path).await
+            .map_err(|e| format!("Failed to delete data file: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_file::{DeviceDescriptor, SampleTiming, SessionHeader};
+
+    #[test]
+    fn recovers_a_journal_cut_mid_chunk() {
+        let device = DeviceDescriptor { id: "AA:01".to_string(), alias: None, role: None };
+        let header = SessionHeader::new("Walk", "S01", "", 1_000, vec![device], String::new());
+        let (mut encoder, mut bytes) = SessionEncoder::new(&header, true).unwrap();
+        for chunk in 0..3u64 {
+            let mut columns = SampleColumns::default();
+            for i in 0..50u64 {
+                columns.push(1_000_000 + (chunk * 50 + i) * 10_000, SampleTiming::default(), [i as f32; 6]);
+            }
+            bytes.extend(encoder.chunk("AA:01", &columns).unwrap());
+        }
+        // Torn in the middle of the third chunk
+        bytes.truncate(bytes.len() - 20);
+
+        let file_path = std::env::temp_dir().join(format!("recover-{}.gait", uuid::Uuid::new_v4()));
+        let journal = journal_path(&file_path);
+        std::fs::write(&journal, &bytes).unwrap();
+
+        let session = tauri::async_runtime::block_on(async {
+            let (file, encoder, totals) = recover_journal(&journal).await?;
+            assert_eq!(totals.samples, 100);
+            assert_eq!(totals.captured_range_ms(), Some((1_000, 1_990)));
+            finish_journal(file, &file_path, &encoder.finish(&totals.comments())).await?;
+            SessionFile::open(&file_path).await
+        }).unwrap();
+        assert!(!journal.exists());
+        assert!(session.finished);
+        assert_eq!(session.chunks.len(), 2);
+        assert!(session.footer.starts_with("# Data Points: 100\n"));
+        let samples = tauri::async_runtime::block_on(session.read_samples(None)).unwrap();
+        assert_eq!(samples[0].1.len(), 100);
+        std::fs::remove_file(&file_path).unwrap();
+    }
+    #[test]
+    fn refuses_to_delete_a_session_in_progress() {
+        let file_path = std::env::temp_dir().join(format!("delete-{}.gait", uuid::Uuid::new_v4()));
+        let journal = journal_path(&file_path);
+        std::fs::write(&journal, b"journal").unwrap();
+        let error = tauri::async_runtime::block_on(delete_session_file("Walk", &file_path, true)).unwrap_err();
+        assert_eq!(error, "Session 'Walk' is still being recorded; stop the recording before deleting it");
+        assert!(journal.exists());
+        std::fs::remove_file(&journal).unwrap();
+
+        std::fs::write(&file_path, b"session").unwrap();
+        tauri::async_runtime::block_on(delete_session_file("Walk", &file_path, false)).unwrap();
+        assert!(!file_path.exists());
+        // A session whose file is already gone only leaves the index
+        tauri::async_runtime::block_on(delete_session_file("Walk", &file_path, false)).unwrap();
+    }
+}
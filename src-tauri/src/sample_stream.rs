@@ -0,0 +1,363 @@
+// Batched delivery of samples to the frontend
+//
+// Emitting every sample as its own JSON event costs one IPC message per sample, which
+// the webview cannot keep up with at higher rates. Samples are instead buffered per device
+// and sent over a Tauri channel as one binary frame every `flush_interval_ms`, or as soon
+// as `max_batch_samples` are pending. A frame is little endian and column oriented:
+//
+//   u8 version (1), u8 reserved, u16 device count
+//   per device:
+//     u16 id length, id (UTF-8), u32 sample count n, f64 sample rate (NaN when unknown)
+//     f64[n] timestamp_us, f64[n] monotonic_s, f64[n] session_time_s,
+//     f64[n] device_timestamp_ms, f64[n] reconstructed_timestamp_us (NaN when absent)
+//     f32[n] r1, f32[n] r2, f32[n] r3, f32[n] x, f32[n] y, f32[n] z
+//
+// Epoch microseconds stay exact as f64 (below 2^53). The per-sample `gait-data` event is
+// still available through the `legacy_events` setting, in which case no batches are sent.
+//
+// Stream consumers hand samples to a SampleSink, an unbounded lock-free queue drained by
+// the flusher task, so queuing a sample never waits on another device's stream. The flusher
+// also passes the samples of recorded devices on to the recording writer.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_std::channel::{Receiver, Sender};
+use async_std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{Channel, InvokeResponseBody};
+
+const STREAM_SETTINGS_FILE: &str = "stream_settings.json";
+pub const FRAME_VERSION: u8 = 1;
+
+fn default_flush_interval_ms() -> u64 {
+    50
+}
+
+fn default_max_batch_samples() -> usize {
+    512
+}
+
+fn default_stall_timeout_ms() -> u64 {
+    2000
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamSettings {
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    // Pending samples (all devices) that trigger a flush before the interval elapses
+    #[serde(default = "default_max_batch_samples")]
+    pub max_batch_samples: usize,
+    // Emit one gait-data event per sample instead of batches
+    #[serde(default)]
+    pub legacy_events: bool,
+    // A stream without samples for this long is reported as stalled; read at stream start
+    #[serde(default = "default_stall_timeout_ms")]
+    pub stall_timeout_ms: u64,
+}
+
+impl Default for StreamSettings {
+    fn default() -> Self {
+        Self {
+            flush_interval_ms: default_flush_interval_ms(),
+            max_batch_samples: default_max_batch_samples(),
+            legacy_events: false,
+            stall_timeout_ms: default_stall_timeout_ms(),
+        }
+    }
+}
+
+impl StreamSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(5..=1000).contains(&self.flush_interval_ms) {
+            return Err(format!("Flush interval must be 5 - 1000 ms, got {}", self.flush_interval_ms));
+        }
+        if !(1..=10_000).contains(&self.max_batch_samples) {
+            return Err(format!("Batch size must be 1 - 10000 samples, got {}", self.max_batch_samples));
+        }
+        if !(100..=60_000).contains(&self.stall_timeout_ms) {
+            return Err(format!("Stall timeout must be 100 - 60000 ms, got {}", self.stall_timeout_ms));
+        }
+        Ok(())
+    }
+}
+
+// One sample as queued by the notification consumer
+#[derive(Debug, Clone)]
+pub struct BatchSample {
+    pub timestamp_us: u64,
+    pub monotonic_s: f64,
+    pub session_time_s: f64,
+    pub device_timestamp_ms: Option<f64>,
+    pub reconstructed_timestamp_us: Option<u64>,
+    // r1, r2, r3, x, y, z
+    pub values: [f32; 6],
+}
+
+#[derive(Default)]
+struct DeviceColumns {
+    sample_rate: Option<f64>,
+    timestamp_us: Vec<f64>,
+    monotonic_s: Vec<f64>,
+    session_time_s: Vec<f64>,
+    device_timestamp_ms: Vec<f64>,
+    reconstructed_timestamp_us: Vec<f64>,
+    values: [Vec<f32>; 6],
+}
+
+impl DeviceColumns {
+    fn len(&self) -> usize {
+        self.timestamp_us.len()
+    }
+
+    fn push(&mut self, sample_rate: Option<f64>, sample: &BatchSample) {
+        self.sample_rate = sample_rate.or(self.sample_rate);
+        self.timestamp_us.push(sample.timestamp_us as f64);
+        self.monotonic_s.push(sample.monotonic_s);
+        self.session_time_s.push(sample.session_time_s);
+        self.device_timestamp_ms.push(sample.device_timestamp_ms.unwrap_or(f64::NAN));
+        self.reconstructed_timestamp_us.push(sample.reconstructed_timestamp_us.map_or(f64::NAN, |us| us as f64));
+        for (column, value) in self.values.iter_mut().zip(sample.values) {
+            column.push(value);
+        }
+    }
+
+    fn encode(&self, device_id: &str, frame: &mut Vec<u8>) {
+        // Ids are addresses or "sim-…"/"replay-…" names, far below the u16 limit
+        let id = &device_id.as_bytes()[..device_id.len().min(u16::MAX as usize)];
+        frame.extend_from_slice(&(id.len() as u16).to_le_bytes());
+        frame.extend_from_slice(id);
+        frame.extend_from_slice(&(self.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&self.sample_rate.unwrap_or(f64::NAN).to_le_bytes());
+        for column in [
+            &self.timestamp_us,
+            &self.monotonic_s,
+            &self.session_time_s,
+            &self.device_timestamp_ms,
+            &self.reconstructed_timestamp_us,
+        ] {
+            column.iter().for_each(|v| frame.extend_from_slice(&v.to_le_bytes()));
+        }
+        for column in &self.values {
+            column.iter().for_each(|v| frame.extend_from_slice(&v.to_le_bytes()));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SampleBatcher {
+    devices: BTreeMap<String, DeviceColumns>,
+    pending: usize,
+}
+
+impl SampleBatcher {
+    // Returns the number of samples now pending across all devices
+    pub fn push(&mut self, device_id: &str, sample_rate: Option<f64>, sample: &BatchSample) -> usize {
+        match self.devices.get_mut(device_id) {
+            Some(columns) => columns.push(sample_rate, sample),
+            None => {
+                let mut columns = DeviceColumns::default();
+                columns.push(sample_rate, sample);
+                self.devices.insert(device_id.to_string(), columns);
+            }
+        }
+        self.pending += 1;
+        self.pending
+    }
+
+    pub fn clear(&mut self) {
+        self.devices.clear();
+        self.pending = 0;
+    }
+
+    // Encodes the pending samples into a frame and empties the batch
+    pub fn take_frame(&mut self) -> Option<Vec<u8>> {
+        if self.pending == 0 {
+            return None;
+        }
+        let mut frame = Vec::with_capacity(4 + self.devices.len() * 32 + self.pending * 64);
+        frame.push(FRAME_VERSION);
+        frame.push(0);
+        frame.extend_from_slice(&(self.devices.len() as u16).to_le_bytes());
+        for (device_id, columns) in &self.devices {
+            columns.encode(device_id, &mut frame);
+        }
+        self.clear();
+        Some(frame)
+    }
+}
+
+#[derive(Clone)]
+pub struct QueuedSample {
+    pub device_id: Arc<str>,
+    pub sample_rate: Option<f64>,
+    pub sample: BatchSample,
+}
+
+// Handed to each stream at start; cheap to clone
+#[derive(Clone)]
+pub struct SampleSink {
+    tx: Sender<QueuedSample>,
+    legacy_events: Arc<AtomicBool>,
+}
+
+impl SampleSink {
+    pub fn new() -> (Self, Receiver<QueuedSample>) {
+        let (tx, rx) = async_std::channel::unbounded();
+        (Self { tx, legacy_events: Arc::new(AtomicBool::new(false)) }, rx)
+    }
+
+    pub fn legacy_events(&self) -> bool {
+        self.legacy_events.load(Ordering::Relaxed)
+    }
+
+    pub fn push(&self, device_id: &Arc<str>, sample_rate: Option<f64>, sample: BatchSample) {
+        let _ = self.tx.try_send(QueuedSample { device_id: device_id.clone(), sample_rate, sample });
+    }
+}
+
+// Samples of these devices are copied to the recording writer
+pub struct RecordingTap {
+    pub device_ids: HashSet<String>,
+    pub tx: Sender<QueuedSample>,
+}
+
+// Settings, pending batch, the channel of the subscribed frontend and the recording tap
+pub struct SampleStream {
+    file_path: PathBuf,
+    settings: StreamSettings,
+    batcher: SampleBatcher,
+    channel: Option<Channel>,
+    sink: SampleSink,
+    recording: Option<RecordingTap>,
+}
+
+impl SampleStream {
+    // A missing or invalid file keeps the default settings. The receiver is for run_flusher.
+    pub fn load(app_data_dir: &Path) -> (Self, Receiver<QueuedSample>) {
+        let file_path = app_data_dir.join(STREAM_SETTINGS_FILE);
+        let settings = match std::fs::read_to_string(&file_path) {
+            Ok(content) => serde_json::from_str::<StreamSettings>(&content)
+                .map_err(|e| e.to_string())
+                .and_then(|settings| settings.validate().map(|_| settings))
+                .unwrap_or_else(|e| {
+                    println!("[SampleStream] Ignoring saved settings {:?}: {}", file_path, e);
+                    StreamSettings::default()
+                }),
+            Err(_) => StreamSettings::default(),
+        };
+        let (sink, rx) = SampleSink::new();
+        sink.legacy_events.store(settings.legacy_events, Ordering::Relaxed);
+        (Self { file_path, settings, batcher: SampleBatcher::default(), channel: None, sink, recording: None }, rx)
+    }
+
+    pub fn settings(&self) -> &StreamSettings {
+        &self.settings
+    }
+
+    pub fn sink(&self) -> SampleSink {
+        self.sink.clone()
+    }
+
+    pub async fn set_settings(&mut self, settings: StreamSettings) -> Result<(), String> {
+        settings.validate()?;
+        let content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize stream settings: {}", e))?;
+        tokio::fs::write(&self.file_path, content).await
+            .map_err(|e| format!("Failed to write stream settings: {}", e))?;
+        // Samples queued under the old mode would otherwise arrive late or twice
+        if settings.legacy_events {
+            self.batcher.clear();
+        }
+        self.sink.legacy_events.store(settings.legacy_events, Ordering::Relaxed);
+        self.settings = settings;
+        Ok(())
+    }
+
+    // Replaces any earlier subscriber; returns the channel id
+    pub fn subscribe(&mut self, channel: Channel) -> u32 {
+        let id = channel.id();
+        self.batcher.clear();
+        self.channel = Some(channel);
+        id
+    }
+
+    pub fn unsubscribe(&mut self, channel_id: u32) -> bool {
+        if self.subscription_id() != Some(channel_id) {
+            return false;
+        }
+        self.channel = None;
+        self.batcher.clear();
+        true
+    }
+
+    pub fn subscription_id(&self) -> Option<u32> {
+        self.channel.as_ref().map(Channel::id)
+    }
+
+    // Replaces the recording tap; dropping the old one ends its writer once it has drained
+    pub fn set_recording(&mut self, recording: Option<RecordingTap>) {
+        self.recording = recording;
+    }
+
+    // Copies the sample to the recording and adds it to the batch, sending the batch right
+    // away once it reaches the size limit
+    fn push(&mut self, queued: &QueuedSample) {
+        if let Some(recording) = &self.recording {
+            if recording.device_ids.contains(queued.device_id.as_ref()) {
+                let _ = recording.tx.try_send(queued.clone());
+            }
+        }
+        if self.channel.is_none() || self.settings.legacy_events {
+            return;
+        }
+        if self.batcher.push(&queued.device_id, queued.sample_rate, &queued.sample) >= self.settings.max_batch_samples {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        let Some(frame) = self.batcher.take_frame() else {
+            return;
+        };
+        if let Some(channel) = &self.channel {
+            // Fails once the webview that owned the channel is gone
+            if let Err(e) = channel.send(InvokeResponseBody::Raw(frame)) {
+                println!("[SampleStream] Dropping subscriber {}: {}", channel.id(), e);
+                self.channel = None;
+            }
+        }
+    }
+}
+
+// Moves queued samples into batches and flushes them on the configured interval; runs for
+// the lifetime of the app
+pub async fn run_flusher(stream: Arc<Mutex<SampleStream>>, rx: Receiver<QueuedSample>) {
+    let mut next_flush = Instant::now();
+    loop {
+        // Checked before waiting so a queue that is never empty cannot hold back the flush
+        let now = Instant::now();
+        if now >= next_flush {
+            let mut stream = stream.lock().await;
+            stream.flush();
+            next_flush = now + Duration::from_millis(stream.settings().flush_interval_ms);
+            continue;
+        }
+        match async_std::future::timeout(next_flush - now, rx.recv()).await {
+            Ok(Ok(queued)) => {
+                let mut stream = stream.lock().await;
+                stream.push(&queued);
+                // Take whatever else is already queued under the same lock
+                while let Ok(queued) = rx.try_recv() {
+                    stream.push(&queued);
+                }
+            }
+            Ok(Err(_)) => break,
+            Err(_) => {}
+        }
+    }
+}
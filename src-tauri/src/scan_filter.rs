@@ -0,0 +1,147 @@
+// Scan filters
+//
+// btleplug's ScanFilter only carries service UUIDs, so those are handed to the adapter and
+// everything else is checked per advertisement. Services are checked again in Rust as well:
+// some platforms ignore the adapter filter, and peripherals cached from earlier scans are
+// reported regardless of it.
+
+use std::path::{Path, PathBuf};
+
+use btleplug::api::ScanFilter;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::device_profiles::glob_match;
+
+const SCAN_FILTER_FILE: &str = "scan_filter.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamePatternKind {
+    // '*' and '?' wildcards, e.g. "GaitBLE_*"
+    #[default]
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceScanFilter {
+    // A device matches when it advertises any of these services (empty: any device)
+    #[serde(default)]
+    pub service_uuids: Vec<Uuid>,
+    // Matched against the whole advertised local name; unnamed devices never match
+    #[serde(default)]
+    pub name_pattern: Option<String>,
+    #[serde(default)]
+    pub name_pattern_kind: NamePatternKind,
+    // dBm; devices without a reported RSSI never match
+    #[serde(default)]
+    pub min_rssi: Option<i16>,
+    // btleplug 0.10 does not report the advertisement's connectable flag, so every
+    // advertising device currently counts as connectable
+    #[serde(default)]
+    pub connectable_only: bool,
+}
+
+impl DeviceScanFilter {
+    pub fn compile(&self) -> Result<CompiledScanFilter, String> {
+        if let Some(min_rssi) = self.min_rssi {
+            if !(-127..=20).contains(&min_rssi) {
+                return Err(format!("Minimum RSSI out of range: {} dBm", min_rssi));
+            }
+        }
+        let name_regex = match (&self.name_pattern, self.name_pattern_kind) {
+            (Some(pattern), _) if pattern.is_empty() => return Err("Name pattern is empty".to_string()),
+            (Some(pattern), NamePatternKind::Regex) => Some(
+                Regex::new(&format!("^(?:{})$", pattern))
+                    .map_err(|e| format!("Invalid name pattern '{}': {}", pattern, e))?,
+            ),
+            _ => None,
+        };
+        Ok(CompiledScanFilter {
+            filter: self.clone(),
+            name_regex,
+        })
+    }
+
+    // The part of the filter the adapter can apply itself
+    pub fn adapter_filter(&self) -> ScanFilter {
+        ScanFilter {
+            services: self.service_uuids.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompiledScanFilter {
+    filter: DeviceScanFilter,
+    name_regex: Option<Regex>,
+}
+
+impl CompiledScanFilter {
+    pub fn filter(&self) -> &DeviceScanFilter {
+        &self.filter
+    }
+
+    pub fn matches(&self, local_name: Option<&str>, rssi: Option<i16>, advertised_services: &[Uuid], connectable: bool) -> bool {
+        let filter = &self.filter;
+        if !filter.service_uuids.is_empty() && !filter.service_uuids.iter().any(|uuid| advertised_services.contains(uuid)) {
+            return false;
+        }
+        if let Some(pattern) = &filter.name_pattern {
+            let name_matches = match (local_name, &self.name_regex) {
+                (Some(name), Some(regex)) => regex.is_match(name),
+                (Some(name), None) => glob_match(pattern, name),
+                (None, _) => false,
+            };
+            if !name_matches {
+                return false;
+            }
+        }
+        if let Some(min_rssi) = filter.min_rssi {
+            if rssi.map_or(true, |rssi| rssi < min_rssi) {
+                return false;
+            }
+        }
+        connectable || !filter.connectable_only
+    }
+}
+
+// The operator's default filter, used whenever a scan is started without one
+pub struct ScanFilterStore {
+    file_path: PathBuf,
+    default_filter: DeviceScanFilter,
+}
+
+impl ScanFilterStore {
+    // A missing or invalid file leaves the default filter empty (every device is listed)
+    pub fn load(app_data_dir: &Path) -> Self {
+        let file_path = app_data_dir.join(SCAN_FILTER_FILE);
+        let default_filter = match std::fs::read_to_string(&file_path) {
+            Ok(content) => serde_json::from_str::<DeviceScanFilter>(&content)
+                .map_err(|e| e.to_string())
+                .and_then(|filter| filter.compile().map(|_| filter))
+                .unwrap_or_else(|e| {
+                    println!("[ScanFilter] Ignoring saved filter {:?}: {}", file_path, e);
+                    DeviceScanFilter::default()
+                }),
+            Err(_) => DeviceScanFilter::default(),
+        };
+        Self { file_path, default_filter }
+    }
+
+    pub fn default_filter(&self) -> &DeviceScanFilter {
+        &self.default_filter
+    }
+
+    pub async fn set_default_filter(&mut self, filter: DeviceScanFilter) -> Result<(), String> {
+        filter.compile()?;
+        let content = serde_json::to_string_pretty(&filter)
+            .map_err(|e| format!("Failed to serialize scan filter: {}", e))?;
+        tokio::fs::write(&self.file_path, content).await
+            .map_err(|e| format!("Failed to write scan filter: {}", e))?;
+        self.default_filter = filter;
+        Ok(())
+    }
+}
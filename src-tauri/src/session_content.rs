@@ -0,0 +1,211 @@
+// Samples of a saved session for loading, replay, alignment and export
+//
+// Sessions are binary session files or, for older sessions, CSV. Both are read on one time
+// axis: a binary session's samples are placed at their device time mapped onto the host
+// clock when the device had a clock (free of the host's connection-event jitter) and at
+// their arrival time otherwise, which is what a CSV export writes as the timestamp. The
+// per-device offsets stored by align_session_devices are measured on that axis and
+// applied to it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::alignment;
+use crate::session_file::{self, SampleColumns, SessionFile};
+use crate::transport::replay::{self, ReplaySample};
+
+// Reconstructed sample times stay within this of the arrival times the chunk index covers
+const CLOCK_MARGIN_MS: u64 = 1000;
+
+// Samples of a saved session with the session's alignment offsets applied (the CSV's as
+// its rows are read)
+pub enum SessionContent {
+    Csv(String, HashMap<String, f64>),
+    Binary(Vec<(String, SampleColumns)>),
+}
+
+impl SessionContent {
+    // Binary sessions only decode the chunks that overlap `range_ms`, widened by the largest
+    // offset and the clock margin as the range is in aligned time; a CSV is read whole
+    pub async fn read(file_path: &str, range_ms: Option<(u64, u64)>, time_offsets_ms: &HashMap<String, f64>) -> Result<Self, String> {
+        let path = Path::new(file_path);
+        if !session_file::is_session_file(path).await {
+            return tokio::fs::read_to_string(path).await
+                .map(|content| Self::Csv(content, time_offsets_ms.clone()))
+                .map_err(|e| format!("Failed to read data file: {}", e));
+        }
+        let widen_ms = CLOCK_MARGIN_MS
+            + time_offsets_ms.values().fold(0.0f64, |widest, offset| widest.max(offset.abs())).ceil() as u64;
+        let range_us = range_ms.map(|(start, end)| (
+            start.saturating_sub(widen_ms).saturating_mul(1000),
+            end.saturating_add(widen_ms).saturating_mul(1000).saturating_add(999),
+        ));
+        let session = SessionFile::open(path).await?;
+        let mut devices = session.read_samples(range_us).await?;
+        shift_session_samples(&mut devices, time_offsets_ms);
+        Ok(Self::Binary(devices))
+    }
+
+    // Calls `f` with the device, timestamp (ms) and r1, r2, r3, x, y, z of each row; CSV
+    // fields that do not parse are None
+    pub fn for_each_row(&self, mut f: impl FnMut(&str, u64, [Option<f64>; 6])) {
+        match self {
+            Self::Csv(content, time_offsets_ms) => {
+                let mut header_found = false;
+                for line in content.lines() {
+                    let trimmed = line.trim();
+
+                    // Skip comment lines that start with #
+                    if trimmed.starts_with('#') || trimmed.is_empty() {
+                        continue;
+                    }
+
+                    // Skip the header line (device_id,timestamp,r1,r2,r3,x,y,z)
+                    if !header_found && trimmed.starts_with("device_id") {
+                        header_found = true;
+                        continue;
+                    }
+
+                    let parts: Vec<&str> = line.split(',').collect();
+                    // Expected format: device_id,timestamp,r1,r2,r3,x,y,z
+                    if parts.len() >= 8 {
+                        if let Ok(timestamp) = parts[1].parse::<u64>() {
+                            let timestamp = alignment::shift_ms(timestamp, time_offsets_ms.get(parts[0]).copied().unwrap_or(0.0));
+                            f(parts[0], timestamp, std::array::from_fn(|i| parts[2 + i].parse::<f64>().ok()));
+                        }
+                    }
+                }
+            }
+            Self::Binary(devices) => {
+                for (device_id, columns) in devices {
+                    for i in 0..columns.len() {
+                        f(device_id, columns.best_timestamp_us(i) / 1000, columns.row(i).map(|v| Some(v as f64)));
+                    }
+                }
+            }
+        }
+    }
+
+    // Samples per device in time order, as replay, alignment and the exports use them
+    pub fn per_device(self) -> BTreeMap<String, Vec<ReplaySample>> {
+        match self {
+            Self::Csv(content, time_offsets_ms) => {
+                let mut per_device = replay::parse_session_csv(&content);
+                alignment::apply_time_offsets(&mut per_device, &time_offsets_ms);
+                per_device
+            }
+            Self::Binary(devices) => devices.into_iter()
+                .map(|(device_id, columns)| {
+                    let mut samples: Vec<ReplaySample> = (0..columns.len())
+                        .map(|i| ReplaySample { timestamp_ms: columns.best_timestamp_us(i) / 1000, values: columns.row(i) })
+                        .collect();
+                    samples.sort_by_key(|s| s.timestamp_ms);
+                    (device_id, samples)
+                })
+                .collect(),
+        }
+    }
+}
+
+// Moves each device's samples by its alignment offset
+pub fn shift_session_samples(devices: &mut [(String, SampleColumns)], time_offsets_ms: &HashMap<String, f64>) {
+    for (device_id, columns) in devices {
+        if let Some(offset_ms) = time_offsets_ms.get(device_id.as_str()) {
+            columns.shift((offset_ms * 1000.0).round() as i64);
+        }
+    }
+}
+
+// A session CSV with each row's timestamp moved by its device's alignment offset
+pub fn shift_session_csv(content: &str, time_offsets_ms: &HashMap<String, f64>) -> String {
+    let mut shifted = String::with_capacity(content.len());
+    for line in content.lines() {
+        let mut parts: Vec<String> = line.split(',').map(str::to_string).collect();
+        let offset_ms = time_offsets_ms.get(parts[0].as_str()).copied();
+        match (offset_ms, parts.get(1).and_then(|t| t.parse::<u64>().ok())) {
+            (Some(offset_ms), Some(timestamp)) if !line.starts_with('#') => {
+                parts[1] = alignment::shift_ms(timestamp, offset_ms).to_string();
+                shifted.push_str(&parts.join(","));
+            }
+            _ => shifted.push_str(line),
+        }
+        shifted.push('\n');
+    }
+    shifted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_file::{encode_session, DeviceDescriptor, SampleTiming, SessionHeader, FILE_EXTENSION};
+
+    const START_US: u64 = 1_700_000_000_000_000;
+
+    // 100 Hz of gravity with a tap at `tap_ms` on the device clock; the samples arrive 3 to
+    // 15 ms after their reconstructed time
+    fn device(first_ms: u64, tap_ms: u64) -> SampleColumns {
+        let mut columns = SampleColumns::default();
+        for i in 0..1000u64 {
+            let reconstructed_us = START_US + (first_ms + i * 10) * 1000;
+            let z = if (first_ms + i * 10).abs_diff(tap_ms) < 15 { 3.0 } else { 1.0 };
+            let timing = SampleTiming {
+                session_time_s: (first_ms + i * 10) as f64 / 1000.0,
+                device_timestamp_ms: Some((first_ms + i * 10) as f64),
+                reconstructed_timestamp_us: Some(reconstructed_us),
+            };
+            columns.push(reconstructed_us + ((i * 7) % 13 + 3) * 1000, timing, [i as f32, 0.5, -0.5, 0.0, 0.0, z]);
+        }
+        columns
+    }
+
+    fn rows(content: &SessionContent) -> BTreeMap<String, Vec<ReplaySample>> {
+        let mut rows: BTreeMap<String, Vec<ReplaySample>> = BTreeMap::new();
+        content.for_each_row(|device_id, timestamp_ms, values| {
+            rows.entry(device_id.to_string()).or_default()
+                .push(ReplaySample { timestamp_ms, values: values.map(|v| v.unwrap() as f32) });
+        });
+        rows
+    }
+
+    #[test]
+    fn aligned_binary_sessions_chart_and_export_on_the_replay_axis() {
+        let devices = vec![
+            DeviceDescriptor { id: "AA:01".to_string(), alias: None, role: None },
+            DeviceDescriptor { id: "AA:02".to_string(), alias: None, role: None },
+        ];
+        let header = SessionHeader::new("Walk", "S01", "", START_US / 1000, devices, String::new());
+        // The second device stamped the same tap 60 ms late
+        let samples = vec![("AA:01".to_string(), device(0, 4000)), ("AA:02".to_string(), device(5, 4060))];
+        let path = std::env::temp_dir().join(format!("aligned-{}.{}", uuid::Uuid::new_v4(), FILE_EXTENSION));
+        std::fs::write(&path, encode_session(&header, &samples, "", false).unwrap()).unwrap();
+        let file_path = path.to_str().unwrap();
+        let read = |range_ms, offsets: &HashMap<String, f64>| {
+            tauri::async_runtime::block_on(SessionContent::read(file_path, range_ms, offsets)).unwrap()
+        };
+
+        let unaligned = read(None, &HashMap::new()).per_device();
+        let (offset_ms, _) = alignment::align_to_reference(&unaligned["AA:01"], &unaligned["AA:02"], 500.0).unwrap();
+        assert!((offset_ms + 60.0).abs() < 5.0, "offset {}", offset_ms);
+        let offsets = HashMap::from([("AA:02".to_string(), offset_ms)]);
+        let per_device = read(None, &offsets).per_device();
+        assert_eq!(per_device["AA:02"][0].timestamp_ms, alignment::shift_ms(START_US / 1000 + 5, offset_ms));
+
+        // Chart data, whole and for a range
+        assert_eq!(rows(&read(None, &offsets)), per_device);
+        let (from_ms, to_ms) = (START_US / 1000 + 3000, START_US / 1000 + 5000);
+        let in_range = |samples: &BTreeMap<String, Vec<ReplaySample>>| -> BTreeMap<String, Vec<ReplaySample>> {
+            samples.iter()
+                .map(|(id, s)| (id.clone(), s.iter().filter(|s| (from_ms..=to_ms).contains(&s.timestamp_ms)).copied().collect()))
+                .collect()
+        };
+        assert_eq!(in_range(&rows(&read(Some((from_ms, to_ms)), &offsets))), in_range(&per_device));
+
+        // CSV export
+        let session = tauri::async_runtime::block_on(SessionFile::open(&path)).unwrap();
+        let mut exported = tauri::async_runtime::block_on(session.read_samples(None)).unwrap();
+        shift_session_samples(&mut exported, &offsets);
+        let csv = session.to_csv(&exported, 0);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay::parse_session_csv(&csv), per_device);
+    }
+}
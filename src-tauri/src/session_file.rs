@@ -0,0 +1,787 @@
+// Binary session files
+//
+// Sessions are stored column oriented, so loading one reads numbers instead of parsing
+// text, and a time range only needs the chunks that overlap it. Little endian throughout:
+//
+//   magic "GAITSESS", u16 version (1), u16 flags (bit 0: chunk payloads are deflated)
+//   u32 header length, header (JSON SessionHeader: session, subject, notes, start time,
+//     devices with their alias and role, channel names and units, comment lines)
+//   chunks, each holding consecutive samples of one device:
+//     u8 'C', u16 device (index into the header's devices), u32 sample count n,
+//     u64 first and u64 last timestamp (epoch µs), u32 payload length, payload:
+//     u64[n] timestamp_us (host arrival), f64[n] session_time_s (recording time base),
+//     f64[n] device_timestamp_ms (NaN without a device clock), u64[n]
+//     reconstructed_timestamp_us (epoch µs from the clock model, u64::MAX without one),
+//     then f32[n] per channel (r1, r2, r3, x, y, z)
+//   index, written when the session is finished:
+//     u8 'I', u32 chunk count, per chunk: u16 device, u32 samples, u64 first, u64 last,
+//     u64 chunk offset, u32 payload length; u32 footer length, footer (comment lines)
+//   u64 index offset, magic "GAITEND1"
+//
+// A file without the closing magic (a recording journal, or a copy cut short) is read by
+// walking the chunks after the header up to the last complete one. CSV remains the export
+// format; `to_csv` renders a session the way save_session_data used to write it.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+pub const FILE_EXTENSION: &str = "gait";
+pub const FORMAT_VERSION: u16 = 1;
+const MAGIC: &[u8; 8] = b"GAITSESS";
+const END_MAGIC: &[u8; 8] = b"GAITEND1";
+const FLAG_DEFLATE: u16 = 1;
+const CHUNK_TAG: u8 = b'C';
+const INDEX_TAG: u8 = b'I';
+// Magic, version, flags and header length
+const PREAMBLE_LEN: u64 = 16;
+const CHUNK_HEADER_LEN: u64 = 27;
+const INDEX_ENTRY_LEN: usize = 34;
+// Index offset and end magic
+const TAIL_LEN: u64 = 16;
+// Encoded size of one sample: four time columns plus one f32 per channel
+pub const SAMPLE_BYTES: usize = 4 * 8 + 4 * CHANNELS.len();
+// Stored for a missing reconstructed timestamp
+const NO_TIMESTAMP: u64 = u64::MAX;
+// Samples per chunk when a whole session is written at once
+pub const CHUNK_SAMPLES: usize = 4096;
+
+// Sample channels in column order, with their units
+pub const CHANNELS: [(&str, &str); 6] = [("r1", "Ω"), ("r2", "Ω"), ("r3", "Ω"), ("x", "g"), ("y", "g"), ("z", "g")];
+
+// Column headers of CSV exports; alias and role follow the sample columns so older readers
+// still find the values where they expect them
+pub const CSV_COLUMNS: &str = "device_id,timestamp,r1,r2,r3,x,y,z,alias,role\n";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceDescriptor {
+    pub id: String,
+    pub alias: Option<String>,
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelDescriptor {
+    pub name: String,
+    pub unit: String,
+    // Element type of the channel's columns
+    pub data_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionHeader {
+    pub session_name: String,
+    pub subject_id: String,
+    pub notes: String,
+    // Epoch milliseconds
+    pub started_at_ms: u64,
+    pub devices: Vec<DeviceDescriptor>,
+    pub channels: Vec<ChannelDescriptor>,
+    // "# " comment lines known when the session starts, carried over to CSV exports
+    #[serde(default)]
+    pub comments: String,
+}
+
+impl SessionHeader {
+    pub fn new(
+        session_name: &str,
+        subject_id: &str,
+        notes: &str,
+        started_at_ms: u64,
+        devices: Vec<DeviceDescriptor>,
+        comments: String,
+    ) -> Self {
+        Self {
+            session_name: session_name.to_string(),
+            subject_id: subject_id.to_string(),
+            notes: notes.to_string(),
+            started_at_ms,
+            devices,
+            channels: CHANNELS
+                .iter()
+                .map(|(name, unit)| ChannelDescriptor {
+                    name: name.to_string(),
+                    unit: unit.to_string(),
+                    data_type: "f32".to_string(),
+                })
+                .collect(),
+            comments,
+        }
+    }
+}
+
+// Timing of a sample besides its host arrival time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SampleTiming {
+    // Seconds on the recording's shared time base
+    pub session_time_s: f64,
+    pub device_timestamp_ms: Option<f64>,
+    // Device time mapped onto the host clock (epoch µs)
+    pub reconstructed_timestamp_us: Option<u64>,
+}
+
+// Samples of one device, one column per field
+#[derive(Debug, Clone, Default)]
+pub struct SampleColumns {
+    pub timestamps_us: Vec<u64>,
+    pub session_times_s: Vec<f64>,
+    pub device_timestamps_ms: Vec<Option<f64>>,
+    pub reconstructed_timestamps_us: Vec<Option<u64>>,
+    pub values: [Vec<f32>; 6],
+}
+
+impl SampleColumns {
+    pub fn len(&self) -> usize {
+        self.timestamps_us.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps_us.is_empty()
+    }
+
+    pub fn push(&mut self, timestamp_us: u64, timing: SampleTiming, values: [f32; 6]) {
+        self.timestamps_us.push(timestamp_us);
+        self.session_times_s.push(timing.session_time_s);
+        self.device_timestamps_ms.push(timing.device_timestamp_ms);
+        self.reconstructed_timestamps_us.push(timing.reconstructed_timestamp_us);
+        for (column, value) in self.values.iter_mut().zip(values) {
+            column.push(value);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.timestamps_us.clear();
+        self.session_times_s.clear();
+        self.device_timestamps_ms.clear();
+        self.reconstructed_timestamps_us.clear();
+        self.values.iter_mut().for_each(Vec::clear);
+    }
+
+    pub fn row(&self, i: usize) -> [f32; 6] {
+        std::array::from_fn(|c| self.values[c][i])
+    }
+
+    pub fn timing(&self, i: usize) -> SampleTiming {
+        SampleTiming {
+            session_time_s: self.session_times_s[i],
+            device_timestamp_ms: self.device_timestamps_ms[i],
+            reconstructed_timestamp_us: self.reconstructed_timestamps_us[i],
+        }
+    }
+
+    // Moves every time column by `offset_us`, e.g. an alignment offset
+    pub fn shift(&mut self, offset_us: i64) {
+        if offset_us == 0 {
+            return;
+        }
+        self.timestamps_us.iter_mut().for_each(|t| *t = t.saturating_add_signed(offset_us));
+        self.session_times_s.iter_mut().for_each(|t| *t += offset_us as f64 / 1_000_000.0);
+        self.reconstructed_timestamps_us.iter_mut().flatten().for_each(|t| *t = t.saturating_add_signed(offset_us));
+    }
+
+    // Time of sample `i` on the host clock (epoch µs): the device clock mapped through the
+    // clock model when there was one, the arrival time otherwise
+    pub fn best_timestamp_us(&self, i: usize) -> u64 {
+        self.reconstructed_timestamps_us[i].unwrap_or(self.timestamps_us[i])
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(self.len() * SAMPLE_BYTES);
+        self.timestamps_us.iter().for_each(|v| payload.extend_from_slice(&v.to_le_bytes()));
+        self.session_times_s.iter().for_each(|v| payload.extend_from_slice(&v.to_le_bytes()));
+        self.device_timestamps_ms.iter()
+            .for_each(|v| payload.extend_from_slice(&v.unwrap_or(f64::NAN).to_le_bytes()));
+        self.reconstructed_timestamps_us.iter()
+            .for_each(|v| payload.extend_from_slice(&v.unwrap_or(NO_TIMESTAMP).to_le_bytes()));
+        for column in &self.values {
+            column.iter().for_each(|v| payload.extend_from_slice(&v.to_le_bytes()));
+        }
+        payload
+    }
+
+    // Appends the samples of an encoded payload that fall within `range_us`
+    fn extend_decoded(&mut self, payload: &[u8], samples: usize, range_us: Option<(u64, u64)>) -> Result<(), String> {
+        if payload.len() != samples * SAMPLE_BYTES {
+            return Err(format!("Chunk holds {} bytes, expected {}", payload.len(), samples * SAMPLE_BYTES));
+        }
+        let (times, columns) = payload.split_at(samples * 32);
+        let time = |c: usize, i: usize| {
+            let at = (c * samples + i) * 8;
+            <[u8; 8]>::try_from(&times[at..at + 8]).unwrap()
+        };
+        let value = |c: usize, i: usize| {
+            let at = (c * samples + i) * 4;
+            f32::from_le_bytes(columns[at..at + 4].try_into().unwrap())
+        };
+        for i in 0..samples {
+            let timestamp_us = u64::from_le_bytes(time(0, i));
+            if range_us.is_some_and(|(start, end)| timestamp_us < start || timestamp_us > end) {
+                continue;
+            }
+            let timing = SampleTiming {
+                session_time_s: f64::from_le_bytes(time(1, i)),
+                device_timestamp_ms: Some(f64::from_le_bytes(time(2, i))).filter(|t| !t.is_nan()),
+                reconstructed_timestamp_us: Some(u64::from_le_bytes(time(3, i))).filter(|&t| t != NO_TIMESTAMP),
+            };
+            self.push(timestamp_us, timing, std::array::from_fn(|c| value(c, i)));
+        }
+        Ok(())
+    }
+}
+
+// Where a chunk is and what it covers
+#[derive(Debug, Clone)]
+pub struct ChunkEntry {
+    pub device: u16,
+    pub samples: u32,
+    pub first_us: u64,
+    pub last_us: u64,
+    pub offset: u64,
+    pub length: u32,
+}
+
+impl ChunkEntry {
+    fn overlaps(&self, range_us: Option<(u64, u64)>) -> bool {
+        range_us.map_or(true, |(start, end)| self.last_us >= start && self.first_us <= end)
+    }
+}
+
+// Produces the bytes of a session file; the caller writes them in order
+pub struct SessionEncoder {
+    compress: bool,
+    devices: HashMap<String, u16>,
+    chunks: Vec<ChunkEntry>,
+    offset: u64,
+}
+
+impl SessionEncoder {
+    // Returns the encoder and the bytes that start the file
+    pub fn new(header: &SessionHeader, compress: bool) -> Result<(Self, Vec<u8>), String> {
+        if header.devices.len() > u16::MAX as usize {
+            return Err(format!("Too many devices for one session: {}", header.devices.len()));
+        }
+        let json = serde_json::to_vec(header)
+            .map_err(|e| format!("Failed to serialize session header: {}", e))?;
+        let mut bytes = Vec::with_capacity(PREAMBLE_LEN as usize + json.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(if compress { FLAG_DEFLATE } else { 0 }).to_le_bytes());
+        bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&json);
+        let encoder = Self {
+            compress,
+            devices: device_indices(header),
+            chunks: Vec::new(),
+            offset: bytes.len() as u64,
+        };
+        Ok((encoder, bytes))
+    }
+
+    // Continues a file whose chunks end at `session.data_end()`, e.g. a recovered journal
+    pub fn resume(session: &SessionFile) -> Self {
+        Self {
+            compress: session.compressed,
+            devices: device_indices(&session.header),
+            chunks: session.chunks.clone(),
+            offset: session.data_end,
+        }
+    }
+
+    // Encodes the samples of one device as a chunk; nothing for no samples
+    pub fn chunk(&mut self, device_id: &str, columns: &SampleColumns) -> Result<Vec<u8>, String> {
+        if columns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let device = *self.devices.get(device_id)
+            .ok_or_else(|| format!("Device {} is not part of the session", device_id))?;
+        let mut payload = columns.encode();
+        if self.compress {
+            let mut deflate = DeflateEncoder::new(Vec::with_capacity(payload.len() / 2), Compression::fast());
+            deflate.write_all(&payload)
+                .and_then(|_| deflate.finish())
+                .map(|compressed| payload = compressed)
+                .map_err(|e| format!("Failed to compress chunk: {}", e))?;
+        }
+
+        let entry = ChunkEntry {
+            device,
+            samples: columns.len() as u32,
+            first_us: columns.timestamps_us.iter().copied().min().unwrap_or(0),
+            last_us: columns.timestamps_us.iter().copied().max().unwrap_or(0),
+            offset: self.offset,
+            length: payload.len() as u32,
+        };
+        let mut bytes = Vec::with_capacity(CHUNK_HEADER_LEN as usize + payload.len());
+        bytes.push(CHUNK_TAG);
+        bytes.extend_from_slice(&entry.device.to_le_bytes());
+        bytes.extend_from_slice(&entry.samples.to_le_bytes());
+        bytes.extend_from_slice(&entry.first_us.to_le_bytes());
+        bytes.extend_from_slice(&entry.last_us.to_le_bytes());
+        bytes.extend_from_slice(&entry.length.to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        self.offset += bytes.len() as u64;
+        self.chunks.push(entry);
+        Ok(bytes)
+    }
+
+    // Index, footer and closing magic; the last bytes of the file
+    pub fn finish(&self, footer: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9 + self.chunks.len() * INDEX_ENTRY_LEN + footer.len() + TAIL_LEN as usize);
+        bytes.push(INDEX_TAG);
+        bytes.extend_from_slice(&(self.chunks.len() as u32).to_le_bytes());
+        for chunk in &self.chunks {
+            bytes.extend_from_slice(&chunk.device.to_le_bytes());
+            bytes.extend_from_slice(&chunk.samples.to_le_bytes());
+            bytes.extend_from_slice(&chunk.first_us.to_le_bytes());
+            bytes.extend_from_slice(&chunk.last_us.to_le_bytes());
+            bytes.extend_from_slice(&chunk.offset.to_le_bytes());
+            bytes.extend_from_slice(&chunk.length.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(footer.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(footer.as_bytes());
+        bytes.extend_from_slice(&self.offset.to_le_bytes());
+        bytes.extend_from_slice(END_MAGIC);
+        bytes
+    }
+}
+
+fn device_indices(header: &SessionHeader) -> HashMap<String, u16> {
+    header.devices.iter().enumerate().map(|(i, d)| (d.id.clone(), i as u16)).collect()
+}
+
+// Encodes a whole session at once
+pub fn encode_session(
+    header: &SessionHeader,
+    samples: &[(String, SampleColumns)],
+    footer: &str,
+    compress: bool,
+) -> Result<Vec<u8>, String> {
+    let (mut encoder, mut bytes) = SessionEncoder::new(header, compress)?;
+    let mut part = SampleColumns::default();
+    for (device_id, columns) in samples {
+        for start in (0..columns.len()).step_by(CHUNK_SAMPLES) {
+            part.clear();
+            for i in start..(start + CHUNK_SAMPLES).min(columns.len()) {
+                part.push(columns.timestamps_us[i], columns.timing(i), columns.row(i));
+            }
+            bytes.extend(encoder.chunk(device_id, &part)?);
+        }
+    }
+    bytes.extend(encoder.finish(footer));
+    Ok(bytes)
+}
+
+// Little endian fields of a byte slice, read in order
+struct FieldReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> FieldReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < len {
+            return Err("Session file is truncated".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+async fn read_at(file: &mut tokio::fs::File, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![0u8; len];
+    file.seek(SeekFrom::Start(offset)).await
+        .map_err(|e| format!("Failed to read session file: {}", e))?;
+    file.read_exact(&mut bytes).await
+        .map_err(|e| format!("Failed to read session file: {}", e))?;
+    Ok(bytes)
+}
+
+// Whether the file starts like a binary session (as opposed to a session CSV)
+pub async fn is_session_file(path: &Path) -> bool {
+    let mut magic = [0u8; 8];
+    match tokio::fs::File::open(path).await {
+        Ok(mut file) => file.read_exact(&mut magic).await.is_ok() && &magic == MAGIC,
+        Err(_) => false,
+    }
+}
+
+// Header and chunk index of a session file; samples are read on demand
+pub struct SessionFile {
+    path: PathBuf,
+    pub header: SessionHeader,
+    pub chunks: Vec<ChunkEntry>,
+    // Comment lines written when the session was finished
+    pub footer: String,
+    // False for journals and files cut short, whose chunks were found by walking them
+    pub finished: bool,
+    compressed: bool,
+    data_end: u64,
+}
+
+impl SessionFile {
+    pub async fn open(path: &Path) -> Result<Self, String> {
+        let mut file = tokio::fs::File::open(path).await
+            .map_err(|e| format!("Failed to open session file: {}", e))?;
+        let len = file.metadata().await
+            .map_err(|e| format!("Failed to read session file: {}", e))?
+            .len();
+        if len < PREAMBLE_LEN {
+            return Err("Session file is truncated".to_string());
+        }
+
+        let preamble = read_at(&mut file, 0, PREAMBLE_LEN as usize).await?;
+        let mut fields = FieldReader { bytes: &preamble };
+        if fields.take(8)? != MAGIC {
+            return Err("Not a binary session file".to_string());
+        }
+        let version = fields.u16()?;
+        if version > FORMAT_VERSION {
+            return Err(format!("Session file version {} is newer than this app supports ({})", version, FORMAT_VERSION));
+        }
+        let compressed = fields.u16()? & FLAG_DEFLATE != 0;
+        let header_len = fields.u32()? as u64;
+        if PREAMBLE_LEN + header_len > len {
+            return Err("Session file is truncated".to_string());
+        }
+        let header: SessionHeader = serde_json::from_slice(&read_at(&mut file, PREAMBLE_LEN, header_len as usize).await?)
+            .map_err(|e| format!("Failed to parse session header: {}", e))?;
+        let header_end = PREAMBLE_LEN + header_len;
+
+        let mut session = Self {
+            path: path.to_path_buf(),
+            header,
+            chunks: Vec::new(),
+            footer: String::new(),
+            finished: false,
+            compressed,
+            data_end: header_end,
+        };
+        if len >= header_end + TAIL_LEN {
+            let tail = read_at(&mut file, len - TAIL_LEN, TAIL_LEN as usize).await?;
+            let index_offset = u64::from_le_bytes(tail[..8].try_into().unwrap());
+            if &tail[8..] == END_MAGIC && (header_end..len - TAIL_LEN).contains(&index_offset) {
+                let index = read_at(&mut file, index_offset, (len - TAIL_LEN - index_offset) as usize).await?;
+                session.read_index(&index)?;
+                session.data_end = index_offset;
+                session.finished = true;
+                return Ok(session);
+            }
+        }
+        session.walk_chunks(&mut file, len).await?;
+        Ok(session)
+    }
+
+    fn read_index(&mut self, index: &[u8]) -> Result<(), String> {
+        let mut fields = FieldReader { bytes: index };
+        if fields.u8()? != INDEX_TAG {
+            return Err("Session index is corrupt".to_string());
+        }
+        let count = fields.u32()? as usize;
+        if count > index.len() / INDEX_ENTRY_LEN {
+            return Err("Session index is corrupt".to_string());
+        }
+        self.chunks = (0..count)
+            .map(|_| Ok(ChunkEntry {
+                device: fields.u16()?,
+                samples: fields.u32()?,
+                first_us: fields.u64()?,
+                last_us: fields.u64()?,
+                offset: fields.u64()?,
+                length: fields.u32()?,
+            }))
+            .collect::<Result<_, String>>()?;
+        let footer_len = fields.u32()? as usize;
+        self.footer = String::from_utf8_lossy(fields.take(footer_len)?).into_owned();
+        Ok(())
+    }
+
+    // Collects the complete chunks that follow the header
+    async fn walk_chunks(&mut self, file: &mut tokio::fs::File, len: u64) -> Result<(), String> {
+        let mut offset = self.data_end;
+        while offset + CHUNK_HEADER_LEN <= len {
+            let chunk_header = read_at(file, offset, CHUNK_HEADER_LEN as usize).await?;
+            let mut fields = FieldReader { bytes: &chunk_header };
+            if fields.u8()? != CHUNK_TAG {
+                break;
+            }
+            let entry = ChunkEntry {
+                device: fields.u16()?,
+                samples: fields.u32()?,
+                first_us: fields.u64()?,
+                last_us: fields.u64()?,
+                offset,
+                length: fields.u32()?,
+            };
+            let end = offset + CHUNK_HEADER_LEN + entry.length as u64;
+            if end > len {
+                break;
+            }
+            self.chunks.push(entry);
+            offset = end;
+        }
+        self.data_end = offset;
+        Ok(())
+    }
+
+    // End of the last complete chunk
+    pub fn data_end(&self) -> u64 {
+        self.data_end
+    }
+
+    pub fn device_id(&self, device: u16) -> Option<&str> {
+        self.header.devices.get(device as usize).map(|d| d.id.as_str())
+    }
+
+    // Samples per device (in header order, devices without samples left out), limited to
+    // `range_us` when given; only chunks that overlap the range are read
+    pub async fn read_samples(&self, range_us: Option<(u64, u64)>) -> Result<Vec<(String, SampleColumns)>, String> {
+        let mut file = tokio::fs::File::open(&self.path).await
+            .map_err(|e| format!("Failed to open session file: {}", e))?;
+        let mut devices = vec![SampleColumns::default(); self.header.devices.len()];
+        for chunk in self.chunks.iter().filter(|c| c.overlaps(range_us)) {
+            let columns = devices.get_mut(chunk.device as usize)
+                .ok_or_else(|| format!("Chunk at {} names unknown device {}", chunk.offset, chunk.device))?;
+            let stored = read_at(&mut file, chunk.offset + CHUNK_HEADER_LEN, chunk.length as usize).await?;
+            let samples = chunk.samples as usize;
+            let payload = if self.compressed {
+                let mut payload = Vec::with_capacity(samples * SAMPLE_BYTES);
+                DeflateDecoder::new(stored.as_slice())
+                    .take((samples * SAMPLE_BYTES) as u64 + 1)
+                    .read_to_end(&mut payload)
+                    .map_err(|e| format!("Failed to decompress chunk at {}: {}", chunk.offset, e))?;
+                payload
+            } else {
+                stored
+            };
+            columns.extend_decoded(&payload, samples, range_us)
+                .map_err(|e| format!("Chunk at {} is corrupt: {}", chunk.offset, e))?;
+        }
+        Ok(self.header.devices.iter()
+            .zip(devices)
+            .filter(|(_, columns)| !columns.is_empty())
+            .map(|(device, columns)| (device.id.clone(), columns))
+            .collect())
+    }
+
+    // The session as CSV, rows in time order on the best timestamps: header comments, column headers, rows with the
+    // device's alias and role, then the footer comments
+    pub fn to_csv(&self, samples: &[(String, SampleColumns)], exported_at_ms: u64) -> String {
+        let header = &self.header;
+        let format_time = |ms: u64| chrono::DateTime::from_timestamp_millis(ms as i64)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_default();
+        let rows: usize = samples.iter().map(|(_, columns)| columns.len()).sum();
+        let mut csv = String::with_capacity(1024 + rows * 64);
+        csv.push_str("# Gait Monitor Data Export\n");
+        let _ = writeln!(csv, "# Session: {}", header.session_name);
+        let _ = writeln!(csv, "# Subject: {}", header.subject_id);
+        let _ = writeln!(csv, "# Notes: {}", header.notes);
+        let _ = writeln!(csv, "# Session Start: {}", format_time(header.started_at_ms));
+        let _ = writeln!(csv, "# Export Time: {}", format_time(exported_at_ms));
+        let _ = writeln!(csv, "# Devices: {}", header.devices.iter().map(|d| d.id.as_str()).collect::<Vec<_>>().join(", "));
+        csv.push_str(&header.comments);
+        csv.push_str("#\n");
+        csv.push_str(CSV_COLUMNS);
+
+        let labels: HashMap<&str, (String, &str)> = header.devices.iter()
+            .map(|d| (d.id.as_str(), (d.alias.as_deref().map(csv_field).unwrap_or_default(), d.role.as_deref().unwrap_or(""))))
+            .collect();
+        let mut order: Vec<(u64, usize, usize)> = samples.iter()
+            .enumerate()
+            .flat_map(|(d, (_, columns))| (0..columns.len()).map(move |i| (columns.best_timestamp_us(i), d, i)))
+            .collect();
+        order.sort_unstable();
+        for (timestamp_us, d, i) in order {
+            let (device_id, columns) = &samples[d];
+            let (alias, role) = labels.get(device_id.as_str()).map_or(("", ""), |(alias, role)| (alias.as_str(), *role));
+            let [r1, r2, r3, x, y, z] = columns.row(i);
+            let _ = writeln!(csv, "{},{},{},{},{},{},{},{},{},{}",
+                device_id, timestamp_us / 1000, r1, r2, r3, x, y, z, alias, role);
+        }
+        csv.push_str(&self.footer);
+        csv
+    }
+}
+
+// Quotes a CSV field when it contains a separator or quote
+pub fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.{}", name, uuid::Uuid::new_v4(), FILE_EXTENSION))
+    }
+
+    fn header() -> SessionHeader {
+        let devices = vec![
+            DeviceDescriptor { id: "AA:01".to_string(), alias: Some("Left, heel".to_string()), role: Some("left".to_string()) },
+            DeviceDescriptor { id: "AA:02".to_string(), alias: None, role: None },
+        ];
+        SessionHeader::new("Walk", "S01", "Treadmill", 1_700_000_000_000, devices, "# Calibration: none\n".to_string())
+    }
+
+    // `n` samples 10 ms apart; odd samples have no device clock
+    fn columns(n: usize, first_us: u64, offset: f32) -> SampleColumns {
+        let mut columns = SampleColumns::default();
+        for i in 0..n {
+            let timestamp_us = first_us + i as u64 * 10_000;
+            let timing = SampleTiming {
+                session_time_s: i as f64 * 0.01,
+                device_timestamp_ms: (i % 2 == 0).then_some(i as f64 * 10.0),
+                reconstructed_timestamp_us: (i % 2 == 0).then_some(timestamp_us - 3_000),
+            };
+            columns.push(timestamp_us, timing, std::array::from_fn(|c| offset + i as f32 + c as f32 / 10.0));
+        }
+        columns
+    }
+
+    fn assert_same(read: &SampleColumns, written: &SampleColumns) {
+        assert_eq!(read.timestamps_us, written.timestamps_us);
+        assert_eq!(read.session_times_s, written.session_times_s);
+        assert_eq!(read.device_timestamps_ms, written.device_timestamps_ms);
+        assert_eq!(read.reconstructed_timestamps_us, written.reconstructed_timestamps_us);
+        assert_eq!(read.values, written.values);
+    }
+
+    fn write(path: &Path, bytes: &[u8]) {
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let samples = vec![
+            ("AA:01".to_string(), columns(CHUNK_SAMPLES + 10, 1_700_000_000_000_000, 0.0)),
+            ("AA:02".to_string(), columns(25, 1_700_000_000_005_000, 100.0)),
+        ];
+        for compress in [false, true] {
+            let path = temp_path("round-trip");
+            write(&path, &encode_session(&header(), &samples, "# Data Points: 4131\n", compress).unwrap());
+            let session = tauri::async_runtime::block_on(SessionFile::open(&path)).unwrap();
+            assert!(session.finished);
+            assert_eq!(session.header.subject_id, "S01");
+            assert_eq!(session.header.devices[0].alias.as_deref(), Some("Left, heel"));
+            assert_eq!(session.footer, "# Data Points: 4131\n");
+            // The first device fills one whole chunk and part of another
+            assert_eq!(session.chunks.len(), 3);
+
+            let read = tauri::async_runtime::block_on(session.read_samples(None)).unwrap();
+            assert_eq!(read.len(), 2);
+            for ((read_id, read), (id, written)) in read.iter().zip(&samples) {
+                assert_eq!(read_id, id);
+                assert_same(read, written);
+            }
+
+            // A range only returns the samples inside it
+            let range_us = (1_700_000_000_020_000, 1_700_000_000_040_000);
+            let read = tauri::async_runtime::block_on(session.read_samples(Some(range_us))).unwrap();
+            assert_eq!(read[0].1.timestamps_us, vec![1_700_000_000_020_000, 1_700_000_000_030_000, 1_700_000_000_040_000]);
+            assert_eq!(read[1].1.timestamps_us, vec![1_700_000_000_025_000, 1_700_000_000_035_000]);
+            assert_eq!(read[1].1.timing(0).reconstructed_timestamp_us, Some(1_700_000_000_022_000));
+            assert_eq!(read[1].1.timing(1).device_timestamp_ms, None);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_a_journal_cut_mid_chunk_up_to_the_last_whole_chunk() {
+        let (mut encoder, mut bytes) = SessionEncoder::new(&header(), true).unwrap();
+        let parts = [columns(40, 1_000_000, 0.0), columns(40, 1_400_000, 40.0), columns(40, 1_800_000, 80.0)];
+        let mut chunk_ends = Vec::new();
+        for part in &parts {
+            bytes.extend(encoder.chunk("AA:01", part).unwrap());
+            chunk_ends.push(bytes.len() as u64);
+        }
+        // The last chunk's payload is only partly on disk, as after a crash mid-write
+        bytes.truncate(chunk_ends[2] as usize - 10);
+
+        let path = temp_path("journal");
+        write(&path, &bytes);
+        let session = tauri::async_runtime::block_on(SessionFile::open(&path)).unwrap();
+        assert!(!session.finished);
+        assert_eq!(session.chunks.len(), 2);
+        assert_eq!(session.data_end(), chunk_ends[1]);
+        let read = tauri::async_runtime::block_on(session.read_samples(None)).unwrap();
+        assert_eq!(read[0].1.len(), 80);
+        assert_eq!(read[0].1.timestamps_us[40..], parts[1].timestamps_us[..]);
+
+        // Chunks appended after resuming land where the torn one started
+        let mut resumed = SessionEncoder::resume(&session);
+        bytes.truncate(session.data_end() as usize);
+        bytes.extend(resumed.chunk("AA:02", &parts[2]).unwrap());
+        bytes.extend(resumed.finish("# Recovered\n"));
+        write(&path, &bytes);
+        let session = tauri::async_runtime::block_on(SessionFile::open(&path)).unwrap();
+        assert!(session.finished);
+        assert_eq!(session.chunks.len(), 3);
+        let read = tauri::async_runtime::block_on(session.read_samples(None)).unwrap();
+        assert_eq!(read[1].0, "AA:02");
+        assert_same(&read[1].1, &parts[2]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn csv_export() {
+        let mut left = SampleColumns::default();
+        left.push(1_000_000, SampleTiming::default(), [1.0, 2.0, 3.0, 0.5, -0.5, 1.0]);
+        left.push(1_020_000, SampleTiming::default(), [1.5, 2.5, 3.5, 0.25, -0.25, 1.0]);
+        let mut right = SampleColumns::default();
+        right.push(1_010_000, SampleTiming::default(), [4.0, 5.0, 6.0, 0.0, 0.0, -1.0]);
+        let samples = vec![("AA:01".to_string(), left), ("AA:02".to_string(), right)];
+
+        let path = temp_path("csv");
+        write(&path, &encode_session(&header(), &samples, "# Data Points: 3\n", false).unwrap());
+        let session = tauri::async_runtime::block_on(SessionFile::open(&path)).unwrap();
+        let read = tauri::async_runtime::block_on(session.read_samples(None)).unwrap();
+        let csv = session.to_csv(&read, 1_700_000_060_000);
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = "\
+# Gait Monitor Data Export
+# Session: Walk
+# Subject: S01
+# Notes: Treadmill
+# Session Start: 2023-11-14 22:13:20 UTC
+# Export Time: 2023-11-14 22:14:20 UTC
+# Devices: AA:01, AA:02
+# Calibration: none
+#
+device_id,timestamp,r1,r2,r3,x,y,z,alias,role
+AA:01,1000,1,2,3,0.5,-0.5,1,\"Left, heel\",left
+AA:02,1010,4,5,6,0,0,-1,,
+AA:01,1020,1.5,2.5,3.5,0.25,-0.25,1,\"Left, heel\",left
+# Data Points: 3
+";
+        assert_eq!(csv, expected);
+    }
+}
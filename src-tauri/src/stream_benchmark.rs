@@ -0,0 +1,185 @@
+// Throughput benchmark of the notification pipeline
+//
+// Simulated devices stream through the same producer/consumer tasks as real hardware, and
+// their samples are batched and encoded exactly as they would be for the frontend. The
+// report compares what each device delivered with what it generated and includes the
+// consumer's latency percentiles, so a slow hot path shows up as missing samples, channel
+// overflow or long tails before it shows up in a recording.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_std::channel::Receiver;
+use serde::Serialize;
+
+use crate::sample_stream::{QueuedSample, SampleBatcher, StreamSettings};
+use crate::stream_diagnostics::{LatencyPercentiles, StreamDiagnosticsSnapshot};
+
+pub const DEFAULT_DEVICE_COUNT: usize = 4;
+pub const DEFAULT_RATE_HZ: u16 = 500;
+pub const DEFAULT_DURATION_S: u64 = 10;
+const MAX_DEVICE_COUNT: usize = 16;
+const MAX_DURATION_S: u64 = 120;
+
+pub fn validate(device_count: usize, rate_hz: u16, duration_s: u64) -> Result<(), String> {
+    if !(1..=MAX_DEVICE_COUNT).contains(&device_count) {
+        return Err(format!("Device count must be 1 - {}, got {}", MAX_DEVICE_COUNT, device_count));
+    }
+    if !(1..=1000).contains(&rate_hz) {
+        return Err(format!("Sample rate must be 1 - 1000 Hz, got {}", rate_hz));
+    }
+    if !(1..=MAX_DURATION_S).contains(&duration_s) {
+        return Err(format!("Duration must be 1 - {} s, got {}", MAX_DURATION_S, duration_s));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkDeviceResult {
+    pub device_id: String,
+    // Samples the device generated while subscribed
+    pub expected_samples: u64,
+    // Samples that made it into an encoded batch
+    pub samples_delivered: u64,
+    pub delivered_percent: f64,
+    pub achieved_rate_hz: f64,
+    pub packets_received: u64,
+    pub channel_overflow_drops: u64,
+    pub duplicate_samples: u64,
+    pub parse_latency_us: LatencyPercentiles,
+    pub emit_latency_us: LatencyPercentiles,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamBenchmarkReport {
+    pub device_count: usize,
+    pub rate_hz: u16,
+    pub duration_s: f64,
+    pub devices: Vec<BenchmarkDeviceResult>,
+    pub total_samples: u64,
+    pub samples_per_second: f64,
+    pub frames_encoded: u64,
+    pub bytes_encoded: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct EncoderTotals {
+    pub samples: HashMap<String, u64>,
+    pub frames: u64,
+    pub bytes: u64,
+}
+
+impl EncoderTotals {
+    fn record_frame(&mut self, frame: Option<Vec<u8>>) {
+        if let Some(frame) = frame {
+            self.frames += 1;
+            self.bytes += frame.len() as u64;
+        }
+    }
+}
+
+// Batches and encodes queued samples with the default stream settings, like the flusher,
+// until `stop` is set
+pub async fn run_encoder(rx: Receiver<QueuedSample>, stop: Arc<AtomicBool>) -> EncoderTotals {
+    let settings = StreamSettings::default();
+    let flush_interval = Duration::from_millis(settings.flush_interval_ms);
+    let mut batcher = SampleBatcher::default();
+    let mut totals = EncoderTotals::default();
+    let mut next_flush = Instant::now() + flush_interval;
+    loop {
+        let now = Instant::now();
+        if now >= next_flush {
+            totals.record_frame(batcher.take_frame());
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            next_flush = now + flush_interval;
+            continue;
+        }
+        match async_std::future::timeout(next_flush - now, rx.recv()).await {
+            Ok(Ok(queued)) => {
+                *totals.samples.entry(queued.device_id.to_string()).or_default() += 1;
+                if batcher.push(&queued.device_id, queued.sample_rate, &queued.sample) >= settings.max_batch_samples {
+                    totals.record_frame(batcher.take_frame());
+                }
+            }
+            Ok(Err(_)) => break,
+            Err(_) => {}
+        }
+    }
+    totals.record_frame(batcher.take_frame());
+    totals
+}
+
+// `streamed` holds each device's diagnostics and how long it was subscribed
+pub fn build_report(
+    rate_hz: u16,
+    duration: Duration,
+    streamed: Vec<(StreamDiagnosticsSnapshot, Duration)>,
+    totals: &EncoderTotals,
+) -> StreamBenchmarkReport {
+    let devices: Vec<BenchmarkDeviceResult> = streamed
+        .into_iter()
+        .map(|(snapshot, subscribed)| {
+            let expected_samples = (subscribed.as_secs_f64() * rate_hz as f64).round() as u64;
+            let samples_delivered = totals.samples.get(&snapshot.device_id).copied().unwrap_or(0);
+            BenchmarkDeviceResult {
+                device_id: snapshot.device_id,
+                expected_samples,
+                samples_delivered,
+                delivered_percent: if expected_samples > 0 {
+                    samples_delivered as f64 / expected_samples as f64 * 100.0
+                } else {
+                    0.0
+                },
+                achieved_rate_hz: samples_delivered as f64 / subscribed.as_secs_f64().max(f64::EPSILON),
+                packets_received: snapshot.packets_received,
+                channel_overflow_drops: snapshot.channel_overflow_drops,
+                duplicate_samples: snapshot.duplicate_samples,
+                parse_latency_us: snapshot.parse_latency_us,
+                emit_latency_us: snapshot.emit_latency_us,
+            }
+        })
+        .collect();
+    let total_samples = devices.iter().map(|d| d.samples_delivered).sum();
+    StreamBenchmarkReport {
+        device_count: devices.len(),
+        rate_hz,
+        duration_s: duration.as_secs_f64(),
+        devices,
+        total_samples,
+        samples_per_second: total_samples as f64 / duration.as_secs_f64().max(f64::EPSILON),
+        frames_encoded: totals.frames,
+        bytes_encoded: totals.bytes,
+    }
+}
+
+impl StreamBenchmarkReport {
+    pub fn print_summary(&self) {
+        println!(
+            "[Benchmark] {} devices @ {} Hz for {:.1} s: {} samples ({:.0} samples/s), {} frames / {} bytes encoded",
+            self.device_count,
+            self.rate_hz,
+            self.duration_s,
+            self.total_samples,
+            self.samples_per_second,
+            self.frames_encoded,
+            self.bytes_encoded
+        );
+        for device in &self.devices {
+            println!(
+                "[Benchmark]   {}: {}/{} samples ({:.1}%), {:.1} Hz, {} overflow drops, parse p99 {} us, emit p99 {} us",
+                device.device_id,
+                device.samples_delivered,
+                device.expected_samples,
+                device.delivered_percent,
+                device.achieved_rate_hz,
+                device.channel_overflow_drops,
+                device.parse_latency_us.p99,
+                device.emit_latency_us.p99
+            );
+        }
+    }
+}
@@ -0,0 +1,293 @@
+// Per-device streaming diagnostics
+//
+// The notification producer counts every packet it receives and whether it fit into the
+// channel (atomics, so it never waits on the consumer); the consumer owns everything else:
+// decoding, discarded duplicates and how long parsing and emitting took. Arrival times are
+// grouped into bursts (packets delivered in the same connection event arrive back to back)
+// and their spacing gives the inter-arrival jitter. Statistics cover the current
+// notification subscription and start over whenever the stream is (re)started.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+// Packets closer together than this belong to the same burst
+const BURST_GAP_US: u64 = 5_000;
+// Histogram bins: index 0 => size 1, ... 8 => size 9, 9 => 10 or more
+const BURST_BINS: usize = 10;
+// Latency percentiles are taken over the most recent measurements
+const LATENCY_WINDOW: usize = 1024;
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct LatencyPercentiles {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BurstStats {
+    // Closed bursts; the one in progress is reported as `current_size`
+    pub count: u64,
+    pub average_size: f64,
+    pub max_size: u32,
+    pub histogram: [u64; BURST_BINS],
+    pub current_size: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct InterArrivalStats {
+    pub mean_ms: f64,
+    // Standard deviation of the packet spacing
+    pub jitter_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamDiagnosticsSnapshot {
+    pub device_id: String,
+    // Epoch milliseconds the stream was started
+    pub started_at: u64,
+    pub packets_received: u64,
+    // Packets dropped because the consumer fell behind
+    pub channel_overflow_drops: u64,
+    pub packets_decoded: u64,
+    pub decode_failures: u64,
+    // Packets (or, with a per-block counter, sample blocks) discarded for repeating a
+    // sequence number
+    pub duplicate_packets: u64,
+    // Samples discarded for repeating the previous values
+    pub duplicate_samples: u64,
+    pub samples_emitted: u64,
+    pub parse_latency_us: LatencyPercentiles,
+    pub emit_latency_us: LatencyPercentiles,
+    pub bursts: BurstStats,
+    pub inter_arrival: InterArrivalStats,
+    pub sample_rate_hz: Option<f64>,
+}
+
+// Packet decode failures of a stream
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DecodeErrorStats {
+    pub count: u64,
+    pub last_error: Option<String>,
+    // Epoch milliseconds of the last failure
+    pub last_error_at: Option<u64>,
+}
+
+impl DecodeErrorStats {
+    // Returns the new total
+    pub fn record(&mut self, error: &str, at_ms: u64) -> u64 {
+        self.count += 1;
+        self.last_error = Some(error.to_string());
+        self.last_error_at = Some(at_ms);
+        self.count
+    }
+}
+
+// Updated by the producer for every notification
+#[derive(Default)]
+pub struct ProducerCounters {
+    packets_received: AtomicU64,
+    channel_overflow_drops: AtomicU64,
+}
+
+impl ProducerCounters {
+    // `enqueued` is false when the channel to the consumer was full
+    pub fn record(&self, enqueued: bool) {
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        if !enqueued {
+            self.channel_overflow_drops.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn packets_received(&self) -> u64 {
+        self.packets_received.load(Ordering::Relaxed)
+    }
+
+    pub fn channel_overflow_drops(&self) -> u64 {
+        self.channel_overflow_drops.load(Ordering::Relaxed)
+    }
+}
+
+struct LatencyWindow {
+    values: VecDeque<u64>,
+}
+
+impl LatencyWindow {
+    fn new() -> Self {
+        Self { values: VecDeque::with_capacity(LATENCY_WINDOW) }
+    }
+
+    fn record(&mut self, micros: u64) {
+        if self.values.len() == LATENCY_WINDOW {
+            self.values.pop_front();
+        }
+        self.values.push_back(micros);
+    }
+
+    fn percentiles(&self) -> LatencyPercentiles {
+        if self.values.is_empty() {
+            return LatencyPercentiles::default();
+        }
+        let mut sorted: Vec<u64> = self.values.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let rank = |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+        LatencyPercentiles {
+            p50: rank(0.50),
+            p95: rank(0.95),
+            p99: rank(0.99),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+pub struct StreamDiagnostics {
+    started_at: u64,
+    packets_decoded: u64,
+    decode_failures: u64,
+    duplicate_packets: u64,
+    duplicate_samples: u64,
+    samples_emitted: u64,
+    parse_latency: LatencyWindow,
+    emit_latency: LatencyWindow,
+    last_arrival_us: Option<u64>,
+    current_burst: u32,
+    burst_count: u64,
+    burst_size_sum: u64,
+    max_burst: u32,
+    burst_histogram: [u64; BURST_BINS],
+    // Welford's running mean and variance of the packet spacing
+    intervals: u64,
+    interval_mean_us: f64,
+    interval_m2: f64,
+    max_interval_us: u64,
+}
+
+impl StreamDiagnostics {
+    pub fn new(started_at: u64) -> Self {
+        Self {
+            started_at,
+            packets_decoded: 0,
+            decode_failures: 0,
+            duplicate_packets: 0,
+            duplicate_samples: 0,
+            samples_emitted: 0,
+            parse_latency: LatencyWindow::new(),
+            emit_latency: LatencyWindow::new(),
+            last_arrival_us: None,
+            current_burst: 0,
+            burst_count: 0,
+            burst_size_sum: 0,
+            max_burst: 0,
+            burst_histogram: [0; BURST_BINS],
+            intervals: 0,
+            interval_mean_us: 0.0,
+            interval_m2: 0.0,
+            max_interval_us: 0,
+        }
+    }
+
+    // A packet that reached the consumer was captured at `arrival_us` (epoch microseconds)
+    pub fn record_arrival(&mut self, arrival_us: u64) {
+        let Some(last) = self.last_arrival_us.replace(arrival_us) else {
+            self.current_burst = 1;
+            return;
+        };
+        let delta_us = arrival_us.saturating_sub(last);
+        if delta_us < BURST_GAP_US {
+            self.current_burst += 1;
+        } else {
+            self.close_burst();
+            self.current_burst = 1;
+        }
+
+        self.intervals += 1;
+        let delta = delta_us as f64;
+        let diff = delta - self.interval_mean_us;
+        self.interval_mean_us += diff / self.intervals as f64;
+        self.interval_m2 += diff * (delta - self.interval_mean_us);
+        self.max_interval_us = self.max_interval_us.max(delta_us);
+    }
+
+    fn close_burst(&mut self) {
+        if self.current_burst == 0 {
+            return;
+        }
+        self.burst_count += 1;
+        self.burst_size_sum += self.current_burst as u64;
+        self.max_burst = self.max_burst.max(self.current_burst);
+        self.burst_histogram[(self.current_burst as usize).min(BURST_BINS) - 1] += 1;
+        self.current_burst = 0;
+    }
+
+    pub fn record_decoded(&mut self, parse_us: u64) {
+        self.packets_decoded += 1;
+        self.parse_latency.record(parse_us);
+    }
+
+    pub fn record_decode_failure(&mut self) {
+        self.decode_failures += 1;
+    }
+
+    pub fn record_duplicate_packet(&mut self) {
+        self.duplicate_packets += 1;
+    }
+
+    pub fn record_duplicate_sample(&mut self) {
+        self.duplicate_samples += 1;
+    }
+
+    // Returns the number of samples emitted so far
+    pub fn record_emitted(&mut self, emit_us: u64) -> u64 {
+        self.samples_emitted += 1;
+        self.emit_latency.record(emit_us);
+        self.samples_emitted
+    }
+
+    pub fn snapshot(
+        &self,
+        device_id: &str,
+        producer: &ProducerCounters,
+        sample_rate_hz: Option<f64>,
+    ) -> StreamDiagnosticsSnapshot {
+        let jitter_us = if self.intervals > 1 {
+            (self.interval_m2 / (self.intervals - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        StreamDiagnosticsSnapshot {
+            device_id: device_id.to_string(),
+            started_at: self.started_at,
+            packets_received: producer.packets_received(),
+            channel_overflow_drops: producer.channel_overflow_drops(),
+            packets_decoded: self.packets_decoded,
+            decode_failures: self.decode_failures,
+            duplicate_packets: self.duplicate_packets,
+            duplicate_samples: self.duplicate_samples,
+            samples_emitted: self.samples_emitted,
+            parse_latency_us: self.parse_latency.percentiles(),
+            emit_latency_us: self.emit_latency.percentiles(),
+            bursts: BurstStats {
+                count: self.burst_count,
+                average_size: if self.burst_count > 0 {
+                    self.burst_size_sum as f64 / self.burst_count as f64
+                } else {
+                    0.0
+                },
+                max_size: self.max_burst,
+                histogram: self.burst_histogram,
+                current_size: self.current_burst,
+            },
+            inter_arrival: InterArrivalStats {
+                mean_ms: self.interval_mean_us / 1000.0,
+                jitter_ms: jitter_us / 1000.0,
+                max_ms: self.max_interval_us as f64 / 1000.0,
+            },
+            sample_rate_hz,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// Per-stream handle shared by a device's producer, consumer and the commands inspecting it
+//
+// The stream's tasks own their working state (duplicate cache, rate calculator,
+// diagnostics, sequence tracker, clock model, decode errors) outright, so the per-packet
+// path takes no lock shared with other devices. What others need to see is published here:
+// the lifecycle state, stop signal and counters are atomics, and the consumer refreshes the
+// diagnostics and statistics snapshots a few times a second. When the consumer exits it
+// hands its tracker, clock model and decode errors to the handle, where a stream resumed
+// after a reconnect takes them over.
+//
+// Lifecycle: starting -> streaming <-> stalled -> stopping -> stopped. A stream becomes
+// streaming with its first sample, stalled when the watchdog sees no samples for the stall
+// timeout and streaming again with the next one. Stopping closes the stop channel, which
+// ends both tasks at once; the last task to exit marks the stream stopped.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_std::channel::{Receiver, Sender};
+use serde::Serialize;
+
+use crate::clock_sync::{ClockModel, ClockSyncStats};
+use crate::packet_loss::{PacketLossStats, SequenceTracker};
+use crate::stream_diagnostics::{DecodeErrorStats, ProducerCounters, StreamDiagnosticsSnapshot};
+
+// Producer and consumer
+const STREAM_TASKS: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamState {
+    Starting,
+    Streaming,
+    Stalled,
+    Stopping,
+    Stopped,
+}
+
+impl StreamState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => StreamState::Starting,
+            1 => StreamState::Streaming,
+            2 => StreamState::Stalled,
+            3 => StreamState::Stopping,
+            _ => StreamState::Stopped,
+        }
+    }
+
+    // Starting, streaming and stalled streams still own their device
+    pub fn is_live(self) -> bool {
+        matches!(self, StreamState::Starting | StreamState::Streaming | StreamState::Stalled)
+    }
+}
+
+// Statistics of the consumer's sequence tracker, clock model and decoder
+#[derive(Debug, Clone, Default)]
+pub struct StreamStats {
+    // None for profiles without a sequence counter
+    pub packet_loss: Option<PacketLossStats>,
+    // None until a packet with a device timestamp is decoded
+    pub clock_sync: Option<ClockSyncStats>,
+    pub decode_errors: DecodeErrorStats,
+}
+
+// Consumer state that outlives a stream when it resumes after a reconnect
+#[derive(Default)]
+pub struct StreamCarryover {
+    pub decode_errors: DecodeErrorStats,
+    pub tracker: Option<SequenceTracker>,
+    pub clock_model: Option<ClockModel>,
+}
+
+pub struct StreamHandle {
+    state: AtomicU8,
+    // Closed to stop the stream; never carries a message
+    stop_tx: Sender<()>,
+    stop_rx: Receiver<()>,
+    running_tasks: AtomicU8,
+    created: Instant,
+    // Milliseconds after `created` of the last delivered sample
+    last_sample_ms: AtomicU64,
+    pub producer: ProducerCounters,
+    // f64 bits of the current sample rate; 0 while unknown
+    sample_rate_bits: AtomicU64,
+    // Latest diagnostics published by the consumer
+    diagnostics: Mutex<Option<StreamDiagnosticsSnapshot>>,
+    // Latest statistics published by the consumer
+    stats: Mutex<StreamStats>,
+    // Carries the consumer's state at exit; closed once it has been sent, or when the
+    // consumer never ran
+    carryover_tx: Sender<StreamCarryover>,
+    carryover_rx: Receiver<StreamCarryover>,
+}
+
+impl StreamHandle {
+    pub fn new() -> Self {
+        let (stop_tx, stop_rx) = async_std::channel::bounded(1);
+        let (carryover_tx, carryover_rx) = async_std::channel::bounded(1);
+        Self {
+            state: AtomicU8::new(StreamState::Starting as u8),
+            stop_tx,
+            stop_rx,
+            running_tasks: AtomicU8::new(STREAM_TASKS),
+            created: Instant::now(),
+            last_sample_ms: AtomicU64::new(0),
+            producer: ProducerCounters::default(),
+            sample_rate_bits: AtomicU64::new(0),
+            diagnostics: Mutex::new(None),
+            stats: Mutex::new(StreamStats::default()),
+            carryover_tx,
+            carryover_rx,
+        }
+    }
+
+    pub fn state(&self) -> StreamState {
+        StreamState::from_u8(self.state.load(Ordering::SeqCst))
+    }
+
+    // Moves to `to` if the stream is in one of `from`; true when it did
+    fn transition(&self, from: &[StreamState], to: StreamState) -> bool {
+        from.iter().any(|&state| {
+            self.state
+                .compare_exchange(state as u8, to as u8, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+        })
+    }
+
+    // Asks both tasks to exit; true unless the stream was already stopping or stopped
+    pub fn cancel(&self) -> bool {
+        self.stop_tx.close();
+        self.transition(&[StreamState::Starting, StreamState::Streaming, StreamState::Stalled], StreamState::Stopping)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.stop_tx.is_closed()
+    }
+
+    // Resolves once the stream is cancelled
+    pub fn stopped(&self) -> impl Future<Output = ()> + Send + 'static {
+        let stop_rx = self.stop_rx.clone();
+        async move {
+            let _ = stop_rx.recv().await;
+        }
+    }
+
+    // Called by the producer and consumer on exit; true for the last one, which leaves the
+    // stream stopped
+    pub fn task_finished(&self) -> bool {
+        if self.running_tasks.fetch_sub(1, Ordering::SeqCst) != 1 {
+            return false;
+        }
+        self.stop_tx.close();
+        self.state.swap(StreamState::Stopped as u8, Ordering::SeqCst) != StreamState::Stopped as u8
+    }
+
+    // For a stream whose tasks were never spawned, e.g. because subscribing failed
+    pub fn abandon(&self) {
+        self.running_tasks.store(0, Ordering::SeqCst);
+        self.stop_tx.close();
+        self.carryover_tx.close();
+        self.state.store(StreamState::Stopped as u8, Ordering::SeqCst);
+    }
+
+    // Called by the consumer for every delivered sample; true when it moved the stream
+    // (back) to streaming
+    pub fn record_sample(&self) -> bool {
+        self.last_sample_ms.store(self.created.elapsed().as_millis() as u64, Ordering::Relaxed);
+        self.transition(&[StreamState::Starting, StreamState::Stalled], StreamState::Streaming)
+    }
+
+    // Time since the last sample, or since the stream was created before the first one
+    pub fn idle_for(&self) -> Duration {
+        self.created
+            .elapsed()
+            .saturating_sub(Duration::from_millis(self.last_sample_ms.load(Ordering::Relaxed)))
+    }
+
+    // Called by the watchdog; true when it moved the stream to stalled
+    pub fn mark_stalled(&self) -> bool {
+        self.transition(&[StreamState::Starting, StreamState::Streaming], StreamState::Stalled)
+    }
+
+    pub fn set_sample_rate(&self, rate: Option<f64>) {
+        self.sample_rate_bits.store(rate.unwrap_or(0.0).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn sample_rate(&self) -> Option<f64> {
+        Some(f64::from_bits(self.sample_rate_bits.load(Ordering::Relaxed))).filter(|&rate| rate > 0.0)
+    }
+
+    pub fn publish_diagnostics(&self, snapshot: StreamDiagnosticsSnapshot) {
+        *self.diagnostics.lock().unwrap() = Some(snapshot);
+    }
+
+    // The last published diagnostics with the producer counters and rate brought up to date
+    pub fn diagnostics(&self) -> Option<StreamDiagnosticsSnapshot> {
+        let mut snapshot = self.diagnostics.lock().unwrap().clone()?;
+        snapshot.packets_received = self.producer.packets_received();
+        snapshot.channel_overflow_drops = self.producer.channel_overflow_drops();
+        snapshot.sample_rate_hz = self.sample_rate();
+        Some(snapshot)
+    }
+
+    pub fn publish_stats(&self, stats: StreamStats) {
+        *self.stats.lock().unwrap() = stats;
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    // Called by the consumer as it exits
+    pub fn hand_over(&self, carryover: StreamCarryover) {
+        let _ = self.carryover_tx.try_send(carryover);
+        self.carryover_tx.close();
+    }
+
+    // Waits for the consumer to exit and returns its state; None if it never ran or the
+    // state was already taken
+    pub async fn take_over(&self) -> Option<StreamCarryover> {
+        self.carryover_rx.recv().await.ok()
+    }
+}
@@ -0,0 +1,320 @@
+// btleplug-backed transport for real Bluetooth Low Energy hardware
+//
+// Scanning and connections can be limited to one selected adapter, e.g. a USB dongle with
+// a better antenna than the built-in radio. btleplug has no stable adapter id, so adapters
+// are identified by their adapter_info() description.
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+
+use async_std::sync::Mutex;
+use async_trait::async_trait;
+use btleplug::api::{
+    Central, CentralEvent, Characteristic, Manager as _, Peripheral as _, PeripheralProperties, ScanFilter, Service,
+    WriteType,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures::stream::StreamExt;
+
+use super::{
+    AdapterInfo, EventHub, NotificationStream, SensorDevice, SensorPeripheral, SensorTransport, TransportEvent,
+    TransportEventStream, TransportKind,
+};
+
+// Selected adapter id, shared with event forwarders and peripherals
+type AdapterSelection = Arc<Mutex<Option<String>>>;
+
+pub struct BtleplugTransport {
+    // Created lazily so the app can start on machines without a Bluetooth stack
+    manager: Mutex<Option<Manager>>,
+    selected: AdapterSelection,
+    events: EventHub,
+    // Adapters whose central events are currently forwarded into `events`
+    forwarded: Arc<Mutex<HashSet<String>>>,
+}
+
+impl BtleplugTransport {
+    pub fn new() -> Self {
+        Self {
+            manager: Mutex::new(None),
+            selected: Arc::new(Mutex::new(None)),
+            events: EventHub::default(),
+            forwarded: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    async fn manager(&self) -> Result<Manager, String> {
+        let mut mgr_guard = self.manager.lock().await;
+        if mgr_guard.is_none() {
+            let new_manager = Manager::new().await.map_err(|e| {
+                println!("Failed to create manager: {}", e);
+                e.to_string()
+            })?;
+            *mgr_guard = Some(new_manager);
+        }
+        Ok(mgr_guard.as_ref().unwrap().clone())
+    }
+
+    // Every adapter currently present, with its id
+    async fn all_adapters(&self) -> Result<Vec<(String, Adapter)>, String> {
+        list_adapters(&self.manager().await?).await
+    }
+
+    // The selected adapter, or every adapter when none is selected
+    async fn active_adapters(&self) -> Result<Vec<(String, Adapter)>, String> {
+        let adapters = self.all_adapters().await?;
+        let selected = self.selected.lock().await.clone();
+        let active: Vec<(String, Adapter)> = match &selected {
+            Some(selected) => adapters.into_iter().filter(|(id, _)| id == selected).collect(),
+            None => adapters,
+        };
+
+        if active.is_empty() {
+            return Err(match selected {
+                Some(id) => format!(
+                    "Selected Bluetooth adapter '{}' is not available. Reconnect it or select another adapter.",
+                    id
+                ),
+                None => "No Bluetooth adapters found".to_string(),
+            });
+        }
+        Ok(active)
+    }
+
+    // Forwards an adapter's central events into the hub. An adapter's event stream ends
+    // when it goes away, so a re-plugged adapter is picked up again by the next scan.
+    async fn forward_events(&self, adapter_id: String, adapter: Adapter) -> Result<(), String> {
+        if !self.forwarded.lock().await.insert(adapter_id.clone()) {
+            return Ok(());
+        }
+        let mut events = match adapter.events().await {
+            Ok(events) => events,
+            Err(e) => {
+                println!("Failed to get adapter events: {}", e);
+                self.forwarded.lock().await.remove(&adapter_id);
+                return Err(e.to_string());
+            }
+        };
+
+        let manager = self.manager().await?;
+        let hub = self.events.clone();
+        let selected = self.selected.clone();
+        let forwarded = self.forwarded.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(event) = events.next().await {
+                // Only the selected adapter's devices are reported
+                if selected.lock().await.as_ref().is_some_and(|id| *id != adapter_id) {
+                    continue;
+                }
+                // Resolve platform peripheral ids into our peripheral wrapper
+                let (id, make): (_, fn(SensorDevice) -> TransportEvent) = match event {
+                    CentralEvent::DeviceDiscovered(id) => (id, TransportEvent::Discovered),
+                    CentralEvent::DeviceConnected(id) => (id, TransportEvent::Connected),
+                    CentralEvent::DeviceDisconnected(id) => (id, TransportEvent::Disconnected),
+                    CentralEvent::DeviceUpdated(id)
+                    | CentralEvent::ManufacturerDataAdvertisement { id, .. }
+                    | CentralEvent::ServiceDataAdvertisement { id, .. }
+                    | CentralEvent::ServicesAdvertisement { id, .. } => (id, TransportEvent::Updated),
+                };
+                if let Ok(peripheral) = adapter.peripheral(&id).await {
+                    let device = BtleplugPeripheral::new(peripheral, &adapter_id, &manager, &selected);
+                    hub.publish(make(Arc::new(device)));
+                }
+            }
+            println!("Event stream of adapter {} ended", adapter_id);
+            forwarded.lock().await.remove(&adapter_id);
+        });
+        Ok(())
+    }
+}
+
+async fn list_adapters(manager: &Manager) -> Result<Vec<(String, Adapter)>, String> {
+    let adapters = manager.adapters().await.map_err(|e| {
+        println!("Failed to get adapters: {}", e);
+        e.to_string()
+    })?;
+
+    let mut identified = Vec::with_capacity(adapters.len());
+    for adapter in adapters {
+        let id = adapter.adapter_info().await.map_err(|e| e.to_string())?;
+        identified.push((id, adapter));
+    }
+    Ok(identified)
+}
+
+#[async_trait]
+impl SensorTransport for BtleplugTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::Ble
+    }
+
+    async fn start_scan(&self, filter: ScanFilter) -> Result<(), String> {
+        let adapters = self.active_adapters().await?;
+        println!("Scanning with {} Bluetooth adapter(s)", adapters.len());
+
+        for (id, adapter) in adapters {
+            println!("Using adapter: {}", id);
+            if let Err(e) = self.forward_events(id, adapter.clone()).await {
+                println!("Adapter events unavailable, devices are only listed when the scan completes: {}", e);
+            }
+
+            // Stop any previous scan
+            let _ = adapter.stop_scan().await;
+
+            adapter.start_scan(filter.clone()).await.map_err(|e| {
+                println!("Failed to start scan: {}", e);
+                e.to_string()
+            })?;
+        }
+        Ok(())
+    }
+
+    // Stops every adapter, in case the selection changed while scanning
+    async fn stop_scan(&self) -> Result<(), String> {
+        for (_, adapter) in self.all_adapters().await? {
+            let _ = adapter.stop_scan().await;
+        }
+        Ok(())
+    }
+
+    async fn peripherals(&self) -> Result<Vec<SensorDevice>, String> {
+        let manager = self.manager().await?;
+        let mut devices: Vec<SensorDevice> = Vec::new();
+        for (id, adapter) in self.active_adapters().await? {
+            let peripherals = adapter.peripherals().await.map_err(|e| {
+                println!("Failed to get peripherals: {}", e);
+                e.to_string()
+            })?;
+            devices.extend(peripherals.into_iter().map(|p| {
+                Arc::new(BtleplugPeripheral::new(p, &id, &manager, &self.selected)) as SensorDevice
+            }));
+        }
+        Ok(devices)
+    }
+
+    async fn events(&self) -> Result<TransportEventStream, String> {
+        let stream = self.events.subscribe();
+        for (id, adapter) in self.all_adapters().await? {
+            self.forward_events(id, adapter).await?;
+        }
+        Ok(stream)
+    }
+
+    async fn adapters(&self) -> Result<Vec<AdapterInfo>, String> {
+        let selected = self.selected.lock().await.clone();
+        let mut adapters: Vec<AdapterInfo> = self
+            .all_adapters()
+            .await?
+            .into_iter()
+            .map(|(id, _)| AdapterInfo {
+                selected: selected.as_ref() == Some(&id),
+                id,
+                available: true,
+            })
+            .collect();
+
+        if let Some(selected) = selected {
+            if !adapters.iter().any(|adapter| adapter.selected) {
+                adapters.push(AdapterInfo {
+                    id: selected,
+                    selected: true,
+                    available: false,
+                });
+            }
+        }
+        Ok(adapters)
+    }
+
+    async fn select_adapter(&self, adapter_id: Option<String>) -> Result<(), String> {
+        *self.selected.lock().await = adapter_id;
+        Ok(())
+    }
+}
+
+pub struct BtleplugPeripheral {
+    peripheral: Peripheral,
+    // Adapter the peripheral was found by; connections go through it
+    adapter_id: String,
+    manager: Manager,
+    selected: AdapterSelection,
+}
+
+impl BtleplugPeripheral {
+    fn new(peripheral: Peripheral, adapter_id: &str, manager: &Manager, selected: &AdapterSelection) -> Self {
+        Self {
+            peripheral,
+            adapter_id: adapter_id.to_string(),
+            manager: manager.clone(),
+            selected: selected.clone(),
+        }
+    }
+
+    // Clear error instead of a platform one when the device's adapter was deselected or
+    // has disappeared
+    async fn check_adapter(&self) -> Result<(), String> {
+        let selected = self.selected.lock().await.clone();
+        if let Some(selected) = selected.filter(|selected| *selected != self.adapter_id) {
+            return Err(format!(
+                "Device was found by adapter '{}' but '{}' is selected. Scan again.",
+                self.adapter_id, selected
+            ));
+        }
+        let present = list_adapters(&self.manager).await?.iter().any(|(id, _)| *id == self.adapter_id);
+        if !present {
+            return Err(format!("Bluetooth adapter '{}' is no longer available", self.adapter_id));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SensorPeripheral for BtleplugPeripheral {
+    fn id(&self) -> String {
+        self.peripheral.address().to_string()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>, String> {
+        self.peripheral.properties().await.map_err(|e| e.to_string())
+    }
+
+    async fn is_connected(&self) -> Result<bool, String> {
+        self.peripheral.is_connected().await.map_err(|e| e.to_string())
+    }
+
+    async fn connect(&self) -> Result<(), String> {
+        self.check_adapter().await?;
+        self.peripheral.connect().await.map_err(|e| e.to_string())
+    }
+
+    async fn disconnect(&self) -> Result<(), String> {
+        self.peripheral.disconnect().await.map_err(|e| e.to_string())
+    }
+
+    async fn discover_services(&self) -> Result<(), String> {
+        self.peripheral.discover_services().await.map_err(|e| e.to_string())
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.peripheral.services()
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>, String> {
+        self.peripheral.read(characteristic).await.map_err(|e| e.to_string())
+    }
+
+    async fn write(&self, characteristic: &Characteristic, data: &[u8], write_type: WriteType) -> Result<(), String> {
+        self.peripheral.write(characteristic, data, write_type).await.map_err(|e| e.to_string())
+    }
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<(), String> {
+        self.peripheral.subscribe(characteristic).await.map_err(|e| e.to_string())
+    }
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<(), String> {
+        self.peripheral.unsubscribe(characteristic).await.map_err(|e| e.to_string())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, String> {
+        self.peripheral.notifications().await.map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,197 @@
+// Sensor transport abstraction
+//
+// Commands talk to sensors through these traits instead of btleplug directly so the
+// same pipeline can run against real BLE hardware or a pure-Rust simulated backend.
+// The GATT types (Service, Characteristic, ValueNotification, ...) are reused from
+// btleplug so both backends describe devices the same way.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use btleplug::api::{
+    Characteristic, PeripheralProperties, ScanFilter, Service, ValueNotification, WriteType,
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub mod ble;
+pub mod replay;
+pub mod simulated;
+mod virtual_device;
+
+// Gait service exposed by the gaitBLE mock firmware
+pub const GAIT_SERVICE_UUID: Uuid = Uuid::from_u128(0x48877734_d012_40c4_81de_3ab006f71189);
+pub const GAIT_CHARACTERISTIC_UUID: Uuid = Uuid::from_u128(0x8c4711b4_571b_41ba_a240_73e6884a85eb);
+// Command characteristic of the gait service; written with response, read back for status
+pub const GAIT_CONTROL_CHARACTERISTIC_UUID: Uuid = Uuid::from_u128(0x8c4711b5_571b_41ba_a240_73e6884a85eb);
+
+// 16-bit SIG UUIDs are xxxx in 0000xxxx-0000-1000-8000-00805f9b34fb
+pub const BLUETOOTH_BASE_UUID: u128 = 0x00000000_0000_1000_8000_00805f9b34fb;
+
+const fn sig_uuid(short: u16) -> Uuid {
+    Uuid::from_u128(((short as u128) << 96) | BLUETOOTH_BASE_UUID)
+}
+
+// Standard GATT services and characteristics
+pub const BATTERY_SERVICE_UUID: Uuid = sig_uuid(0x180F);
+pub const BATTERY_LEVEL_UUID: Uuid = sig_uuid(0x2A19);
+pub const DEVICE_INFORMATION_SERVICE_UUID: Uuid = sig_uuid(0x180A);
+pub const MODEL_NUMBER_UUID: Uuid = sig_uuid(0x2A24);
+pub const SERIAL_NUMBER_UUID: Uuid = sig_uuid(0x2A25);
+pub const FIRMWARE_REVISION_UUID: Uuid = sig_uuid(0x2A26);
+pub const HARDWARE_REVISION_UUID: Uuid = sig_uuid(0x2A27);
+pub const MANUFACTURER_NAME_UUID: Uuid = sig_uuid(0x2A29);
+
+// Environment variable used to pick the backend at startup ("ble" or "simulated")
+pub const TRANSPORT_ENV_VAR: &str = "GAIT_MONITOR_TRANSPORT";
+
+// Preferred adapter, kept in the app data directory
+const ADAPTER_PREFERENCE_FILE: &str = "bluetooth_adapter.json";
+
+pub type NotificationStream = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+
+pub type TransportEventStream = Pin<Box<dyn Stream<Item = TransportEvent> + Send>>;
+
+// Shared handle to a device owned by any backend
+pub type SensorDevice = Arc<dyn SensorPeripheral>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    Ble,
+    Simulated,
+}
+
+impl TransportKind {
+    pub fn from_env() -> Self {
+        match std::env::var(TRANSPORT_ENV_VAR) {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                println!("[Transport] Unknown {} value '{}', falling back to BLE", TRANSPORT_ENV_VAR, value);
+                TransportKind::Ble
+            }),
+            Err(_) => TransportKind::Ble,
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ble" | "bluetooth" | "btleplug" => Some(TransportKind::Ble),
+            "sim" | "simulated" | "mock" => Some(TransportKind::Simulated),
+            _ => None,
+        }
+    }
+
+    pub fn create_transport(self) -> Arc<dyn SensorTransport> {
+        match self {
+            TransportKind::Ble => Arc::new(ble::BtleplugTransport::new()),
+            TransportKind::Simulated => Arc::new(simulated::SimulatedTransport::new()),
+        }
+    }
+}
+
+// Central events carrying the affected device (btleplug's CentralEvent only has platform
+// peripheral ids); Updated covers RSSI and advertisement data changes
+#[derive(Clone)]
+pub enum TransportEvent {
+    Discovered(SensorDevice),
+    Updated(SensorDevice),
+    Connected(SensorDevice),
+    Disconnected(SensorDevice),
+}
+
+// Fan-out of central events to every subscriber of SensorTransport::events
+#[derive(Clone, Default)]
+pub(crate) struct EventHub(Arc<Mutex<Vec<async_std::channel::Sender<TransportEvent>>>>);
+
+impl EventHub {
+    pub(crate) fn subscribe(&self) -> TransportEventStream {
+        let (tx, rx) = async_std::channel::unbounded();
+        self.0.lock().unwrap().push(tx);
+        Box::pin(rx)
+    }
+
+    pub(crate) fn publish(&self, event: TransportEvent) {
+        self.0.lock().unwrap().retain(|tx| tx.try_send(event.clone()).is_ok());
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdapterInfo {
+    // Platform description of the radio (e.g. "hci1 (usb:v0A12p0001d8891)" on Linux)
+    pub id: String,
+    pub selected: bool,
+    // False for a selected adapter that is currently unplugged or disabled
+    pub available: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct AdapterPreference {
+    adapter_id: Option<String>,
+}
+
+pub fn load_adapter_preference(app_data_dir: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(app_data_dir.join(ADAPTER_PREFERENCE_FILE)).ok()?;
+    serde_json::from_str::<AdapterPreference>(&content).ok()?.adapter_id
+}
+
+pub async fn save_adapter_preference(app_data_dir: &Path, adapter_id: Option<String>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(&AdapterPreference { adapter_id })
+        .map_err(|e| format!("Failed to serialize adapter preference: {}", e))?;
+    tokio::fs::write(app_data_dir.join(ADAPTER_PREFERENCE_FILE), content).await
+        .map_err(|e| format!("Failed to write adapter preference: {}", e))
+}
+
+// Central side of a backend: scanning, enumerating peripherals and connection events
+#[async_trait]
+pub trait SensorTransport: Send + Sync {
+    fn kind(&self) -> TransportKind;
+
+    async fn start_scan(&self, filter: ScanFilter) -> Result<(), String>;
+
+    async fn stop_scan(&self) -> Result<(), String>;
+
+    // All peripherals seen by the backend so far
+    async fn peripherals(&self) -> Result<Vec<SensorDevice>, String>;
+
+    async fn events(&self) -> Result<TransportEventStream, String>;
+
+    // Radios this backend can scan with, including a selected one that has disappeared
+    async fn adapters(&self) -> Result<Vec<AdapterInfo>, String>;
+
+    // Restricts scanning and connections to one adapter; None uses every adapter
+    async fn select_adapter(&self, adapter_id: Option<String>) -> Result<(), String>;
+}
+
+// Peripheral side of a backend, mirroring the subset of btleplug::api::Peripheral we use
+#[async_trait]
+pub trait SensorPeripheral: Send + Sync {
+    // Stable device id used as the key in the device state maps (the MAC address for BLE)
+    fn id(&self) -> String;
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>, String>;
+
+    async fn is_connected(&self) -> Result<bool, String>;
+
+    async fn connect(&self) -> Result<(), String>;
+
+    async fn disconnect(&self) -> Result<(), String>;
+
+    async fn discover_services(&self) -> Result<(), String>;
+
+    // Empty until discover_services has completed
+    fn services(&self) -> BTreeSet<Service>;
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>, String>;
+
+    async fn write(&self, characteristic: &Characteristic, data: &[u8], write_type: WriteType) -> Result<(), String>;
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<(), String>;
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<(), String>;
+
+    async fn notifications(&self) -> Result<NotificationStream, String>;
+}
@@ -0,0 +1,234 @@
+// Replay of a saved session as a virtual streaming device
+//
+// Each device recorded in the session becomes its own replay peripheral that re-encodes
+// the rows into the firmware's 24-byte gait packets and notifies them with the original
+// inter-sample timing (optionally sped up or slowed down), so the regular
+// producer/consumer path in start_gait_notifications processes them unchanged.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, Characteristic, PeripheralProperties, Service, WriteType};
+use serde::Serialize;
+
+use super::virtual_device::{encode_gait_packet, VirtualDeviceState};
+use super::{NotificationStream, SensorPeripheral, GAIT_SERVICE_UUID};
+
+#[derive(Debug, Clone)]
+pub struct ReplaySample {
+    pub timestamp_ms: u64,
+    pub values: [f32; 6],
+}
+
+// Parses a session CSV (older sessions and CSV exports) into per-device sample lists
+pub fn parse_session_csv(content: &str) -> BTreeMap<String, Vec<ReplaySample>> {
+    let mut devices: BTreeMap<String, Vec<ReplaySample>> = BTreeMap::new();
+    let mut header_found = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') || trimmed.is_empty() {
+            continue;
+        }
+        if !header_found && trimmed.starts_with("device_id") {
+            header_found = true;
+            continue;
+        }
+
+        // Expected format: device_id,timestamp,r1,r2,r3,x,y,z
+        let parts: Vec<&str> = trimmed.split(',').collect();
+        if parts.len() < 8 {
+            continue;
+        }
+        let Ok(timestamp_ms) = parts[1].parse::<u64>() else {
+            continue;
+        };
+        let mut values = [0f32; 6];
+        let mut valid = true;
+        for (part, value) in parts[2..8].iter().zip(values.iter_mut()) {
+            match part.parse::<f32>() {
+                Ok(v) => *value = v,
+                Err(_) => {
+                    valid = false;
+                    break;
+                }
+            }
+        }
+        if !valid {
+            continue;
+        }
+
+        devices
+            .entry(parts[0].to_string())
+            .or_default()
+            .push(ReplaySample { timestamp_ms, values });
+    }
+
+    for samples in devices.values_mut() {
+        samples.sort_by_key(|s| s.timestamp_ms);
+    }
+    devices
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayDeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub session_id: String,
+    pub source_device_id: String,
+    pub samples: usize,
+    pub duration_s: f64,
+    pub speed: f64,
+    pub loop_playback: bool,
+}
+
+pub struct ReplayPeripheral {
+    info: ReplayDeviceInfo,
+    samples: Arc<Vec<ReplaySample>>,
+    state: Arc<VirtualDeviceState>,
+}
+
+impl ReplayPeripheral {
+    pub fn new(
+        session_id: &str,
+        session_name: &str,
+        source_device_id: &str,
+        samples: Vec<ReplaySample>,
+        speed: f64,
+        loop_playback: bool,
+    ) -> Result<Self, String> {
+        if samples.is_empty() {
+            return Err(format!("No samples to replay for device {}", source_device_id));
+        }
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(format!("Invalid replay speed: {}", speed));
+        }
+
+        let duration_s = (samples[samples.len() - 1].timestamp_ms - samples[0].timestamp_ms) as f64 / 1000.0;
+        let short_session: String = session_id.chars().take(8).collect();
+        Ok(Self {
+            info: ReplayDeviceInfo {
+                id: format!("replay-{}-{}", short_session, source_device_id),
+                name: format!("Replay: {} ({})", session_name, source_device_id),
+                session_id: session_id.to_string(),
+                source_device_id: source_device_id.to_string(),
+                samples: samples.len(),
+                duration_s,
+                speed,
+                loop_playback,
+            },
+            samples: Arc::new(samples),
+            state: Arc::new(VirtualDeviceState::new()),
+        })
+    }
+
+    pub fn info(&self) -> &ReplayDeviceInfo {
+        &self.info
+    }
+
+    fn spawn_playback(&self, generation: u64) {
+        let state = self.state.clone();
+        let samples = self.samples.clone();
+        let speed = self.info.speed;
+        let loop_playback = self.info.loop_playback;
+        let device_id = self.info.id.clone();
+
+        tauri::async_runtime::spawn(async move {
+            let first_ts = samples[0].timestamp_ms;
+            // Gap inserted between passes when looping, one average sample interval
+            let pass_gap = if samples.len() > 1 {
+                let span_ms = samples[samples.len() - 1].timestamp_ms - first_ts;
+                Duration::from_secs_f64(span_ms as f64 / 1000.0 / (samples.len() - 1) as f64 / speed)
+            } else {
+                Duration::from_millis(10)
+            };
+
+            let mut pass_start = Instant::now();
+            'playback: loop {
+                for sample in samples.iter() {
+                    let offset_s = (sample.timestamp_ms - first_ts) as f64 / 1000.0 / speed;
+                    let due = pass_start + Duration::from_secs_f64(offset_s);
+                    let now = Instant::now();
+                    if due > now {
+                        async_std::task::sleep(due - now).await;
+                    }
+                    if !state.is_current(generation) {
+                        break 'playback;
+                    }
+                    state.notify(encode_gait_packet(&sample.values).to_vec()).await;
+                }
+
+                if !loop_playback {
+                    println!("[Replay] {} reached end of recording", device_id);
+                    break;
+                }
+                pass_start = Instant::now() + pass_gap;
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl SensorPeripheral for ReplayPeripheral {
+    fn id(&self) -> String {
+        self.info.id.clone()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>, String> {
+        Ok(Some(PeripheralProperties {
+            address: BDAddr::default(),
+            local_name: Some(self.info.name.clone()),
+            services: vec![GAIT_SERVICE_UUID],
+            ..Default::default()
+        }))
+    }
+
+    async fn is_connected(&self) -> Result<bool, String> {
+        Ok(self.state.is_connected())
+    }
+
+    async fn connect(&self) -> Result<(), String> {
+        if self.state.connect() {
+            println!("[Replay] {} connected", self.info.id);
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), String> {
+        self.state.disconnect();
+        println!("[Replay] {} disconnected", self.info.id);
+        Ok(())
+    }
+
+    async fn discover_services(&self) -> Result<(), String> {
+        self.state.discover_services(&self.info.name)
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.state.services()
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>, String> {
+        self.state.read(&self.info.name, characteristic)
+    }
+
+    async fn write(&self, characteristic: &Characteristic, data: &[u8], _write_type: WriteType) -> Result<(), String> {
+        self.state.write(&self.info.name, characteristic, data)
+    }
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<(), String> {
+        let generation = self.state.subscribe(&self.info.name, characteristic)?;
+        self.spawn_playback(generation);
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<(), String> {
+        self.state.unsubscribe(characteristic)
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, String> {
+        Ok(self.state.notifications())
+    }
+}
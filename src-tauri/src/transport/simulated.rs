@@ -0,0 +1,411 @@
+// Simulated transport reproducing the gaitBLE_LeftFoot / gaitBLE_RightFoot mock firmware
+//
+// Same advertised names, service/characteristic UUIDs, 24-byte packets (six little-endian
+// f32s: R1, R2, R3, X, Y, Z) and 100 Hz notification rate, so the whole pipeline can be
+// exercised on machines without a Bluetooth radio. The control characteristic is emulated
+// too: sampling can be stopped, the rate changed and the resistive channels zeroed.
+//
+// Setting GAIT_MONITOR_SIM_DROPOUT_S makes every streaming device drop its link after that
+// many seconds and stay unreachable for a moment, to exercise reconnection handling.
+
+use std::collections::BTreeSet;
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use btleplug::api::{AddressType, BDAddr, Characteristic, PeripheralProperties, ScanFilter, Service, WriteType};
+
+use super::virtual_device::{encode_gait_packet, VirtualDeviceInfo, VirtualDeviceState};
+use super::{
+    AdapterInfo, EventHub, NotificationStream, SensorDevice, SensorPeripheral, SensorTransport, TransportEvent,
+    TransportEventStream, TransportKind, GAIT_SERVICE_UUID,
+};
+
+pub const SIM_DROPOUT_ENV_VAR: &str = "GAIT_MONITOR_SIM_DROPOUT_S";
+// How long a device stays unreachable after a simulated dropout
+const DROPOUT_UNAVAILABLE: Duration = Duration::from_secs(2);
+// Advertising interval of unconnected devices while a scan is running
+const ADVERTISING_INTERVAL: Duration = Duration::from_secs(1);
+const SIMULATED_ADAPTER_ID: &str = "Simulated adapter";
+// Battery drains by one percent per interval while connected, down to a floor
+const BATTERY_DRAIN_INTERVAL: Duration = Duration::from_secs(30);
+const MIN_BATTERY_LEVEL: u8 = 5;
+// Benchmark devices get 02:5A:17:00:B0:nn, next to the two feet
+const BENCHMARK_ADDRESS_PREFIX: [u8; 5] = [0x02, 0x5A, 0x17, 0x00, 0xB0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Foot {
+    Left,
+    Right,
+}
+
+impl Foot {
+    fn local_name(self) -> &'static str {
+        match self {
+            Foot::Left => "GaitBLE_LeftFoot",
+            Foot::Right => "GaitBLE_RightFoot",
+        }
+    }
+
+    fn serial_number(self) -> &'static str {
+        match self {
+            Foot::Left => "SIM-L-0001",
+            Foot::Right => "SIM-R-0002",
+        }
+    }
+
+    // Locally administered addresses so simulated ids never collide with real hardware
+    fn address(self) -> BDAddr {
+        match self {
+            Foot::Left => BDAddr::from([0x02, 0x5A, 0x17, 0x00, 0x00, 0x01]),
+            Foot::Right => BDAddr::from([0x02, 0x5A, 0x17, 0x00, 0x00, 0x02]),
+        }
+    }
+}
+
+// Sample generation copied from the firmware's mock data section
+pub fn mock_values(foot: Foot, time_seconds: f32) -> [f32; 6] {
+    match foot {
+        Foot::Left => {
+            let walk_cycle = (time_seconds * 2.0 * PI).sin();
+            let step_phase = (time_seconds * 4.0 * PI).sin();
+            [
+                12.0 + walk_cycle * 8.0 + step_phase * 2.0,
+                13.0 + walk_cycle * 7.0 + step_phase * 1.5,
+                14.0 + walk_cycle * 6.0 + step_phase * 1.0,
+                0.5 + walk_cycle * 0.8 + step_phase * 0.3,
+                0.2 + (time_seconds * 2.0 * PI + PI / 4.0).sin() * 0.6,
+                9.8 + (time_seconds * 4.0 * PI).sin() * 1.5 + walk_cycle * 0.5,
+            ]
+        }
+        Foot::Right => {
+            let walk_cycle = (time_seconds * 2.0 * PI + PI).sin();
+            let step_phase = (time_seconds * 4.0 * PI + PI).sin();
+            [
+                8.0 + walk_cycle * 9.0 + step_phase * 2.5,
+                9.0 + walk_cycle * 8.0 + step_phase * 2.0,
+                10.0 + walk_cycle * 7.0 + step_phase * 1.5,
+                0.5 + walk_cycle * 0.8 + step_phase * 0.3,
+                -0.2 + (time_seconds * 2.0 * PI - PI / 4.0).sin() * 0.6,
+                9.8 + (time_seconds * 4.0 * PI + PI).sin() * 1.5 + walk_cycle * 0.5,
+            ]
+        }
+    }
+}
+
+pub struct SimulatedTransport {
+    devices: Vec<SensorDevice>,
+    events: EventHub,
+    // Bumped by every start/stop so only the latest advertiser task keeps running
+    scan_generation: Arc<AtomicU64>,
+    adapter_selected: AtomicBool,
+}
+
+impl SimulatedTransport {
+    pub fn new() -> Self {
+        let events = EventHub::default();
+        let dropout = std::env::var(SIM_DROPOUT_ENV_VAR)
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .filter(|secs| secs.is_finite() && *secs > 0.0)
+            .map(Duration::from_secs_f64);
+        if let Some(dropout) = dropout {
+            println!("[Simulated] Devices will drop their link every {:?} while streaming", dropout);
+        }
+
+        let device = |foot: Foot| -> SensorDevice {
+            Arc::new_cyclic(|me| SimulatedPeripheral::new(foot, foot.address(), events.clone(), dropout, me.clone()))
+        };
+        Self {
+            devices: vec![device(Foot::Left), device(Foot::Right)],
+            events,
+            scan_generation: Arc::new(AtomicU64::new(0)),
+            adapter_selected: AtomicBool::new(false),
+        }
+    }
+}
+
+// Standalone devices for the stream benchmark: not advertised by any transport and never
+// dropping their link. Left and right feet alternate so neighbouring devices differ.
+pub fn benchmark_devices(count: usize) -> Vec<SensorDevice> {
+    let events = EventHub::default();
+    (0..count.min(u8::MAX as usize))
+        .map(|index| -> SensorDevice {
+            let foot = if index % 2 == 0 { Foot::Left } else { Foot::Right };
+            let mut address = [0; 6];
+            address[..5].copy_from_slice(&BENCHMARK_ADDRESS_PREFIX);
+            address[5] = index as u8 + 1;
+            let address = BDAddr::from(address);
+            Arc::new_cyclic(|me| SimulatedPeripheral::new(foot, address, events.clone(), None, me.clone()))
+        })
+        .collect()
+}
+
+#[async_trait]
+impl SensorTransport for SimulatedTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::Simulated
+    }
+
+    async fn start_scan(&self, _filter: ScanFilter) -> Result<(), String> {
+        println!("[Simulated] Scan started ({} virtual devices advertising)", self.devices.len());
+        for device in &self.devices {
+            self.events.publish(TransportEvent::Discovered(device.clone()));
+        }
+
+        // Unconnected devices keep advertising until the scan is stopped
+        let generation = self.scan_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let scan_generation = self.scan_generation.clone();
+        let devices = self.devices.clone();
+        let events = self.events.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                async_std::task::sleep(ADVERTISING_INTERVAL).await;
+                if scan_generation.load(Ordering::SeqCst) != generation {
+                    break;
+                }
+                for device in &devices {
+                    if !device.is_connected().await.unwrap_or(false) {
+                        events.publish(TransportEvent::Updated(device.clone()));
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn stop_scan(&self) -> Result<(), String> {
+        self.scan_generation.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn peripherals(&self) -> Result<Vec<SensorDevice>, String> {
+        Ok(self.devices.clone())
+    }
+
+    async fn events(&self) -> Result<TransportEventStream, String> {
+        Ok(self.events.subscribe())
+    }
+
+    async fn adapters(&self) -> Result<Vec<AdapterInfo>, String> {
+        Ok(vec![AdapterInfo {
+            id: SIMULATED_ADAPTER_ID.to_string(),
+            selected: self.adapter_selected.load(Ordering::SeqCst),
+            available: true,
+        }])
+    }
+
+    async fn select_adapter(&self, adapter_id: Option<String>) -> Result<(), String> {
+        match adapter_id.as_deref() {
+            None => self.adapter_selected.store(false, Ordering::SeqCst),
+            Some(SIMULATED_ADAPTER_ID) => self.adapter_selected.store(true, Ordering::SeqCst),
+            Some(other) => return Err(format!("Unknown Bluetooth adapter: {}", other)),
+        }
+        Ok(())
+    }
+}
+
+pub struct SimulatedPeripheral {
+    foot: Foot,
+    address: BDAddr,
+    state: Arc<VirtualDeviceState>,
+    connected_at: Arc<Mutex<Instant>>,
+    events: EventHub,
+    dropout: Option<Duration>,
+    // Set by a simulated dropout; connect attempts fail until then
+    unreachable_until: Arc<Mutex<Option<Instant>>>,
+    // R1-R3 readings subtracted from every sample since the last zero command
+    tare: Arc<Mutex<[f32; 3]>>,
+    // Handle to our own Arc, attached to published transport events
+    me: Weak<SimulatedPeripheral>,
+}
+
+impl SimulatedPeripheral {
+    fn new(
+        foot: Foot,
+        address: BDAddr,
+        events: EventHub,
+        dropout: Option<Duration>,
+        me: Weak<SimulatedPeripheral>,
+    ) -> Self {
+        Self {
+            foot,
+            address,
+            state: Arc::new(VirtualDeviceState::with_device_info(VirtualDeviceInfo {
+                manufacturer_name: "GaitBLE".to_string(),
+                model_number: "gaitBLE mock".to_string(),
+                serial_number: foot.serial_number().to_string(),
+                firmware_revision: "1.0.0-sim".to_string(),
+                hardware_revision: "virtual".to_string(),
+            })),
+            connected_at: Arc::new(Mutex::new(Instant::now())),
+            events,
+            dropout,
+            unreachable_until: Arc::new(Mutex::new(None)),
+            tare: Arc::new(Mutex::new([0.0; 3])),
+            me,
+        }
+    }
+
+    fn publish(&self, event: fn(SensorDevice) -> TransportEvent) {
+        if let Some(me) = self.me.upgrade() {
+            self.events.publish(event(me));
+        }
+    }
+
+    fn spawn_battery_drain(&self) {
+        let state = self.state.clone();
+        let connected_at = self.connected_at.clone();
+        let connection = *connected_at.lock().unwrap();
+
+        tauri::async_runtime::spawn(async move {
+            loop {
+                async_std::task::sleep(BATTERY_DRAIN_INTERVAL).await;
+                // A reconnect starts its own drain task
+                if !state.is_connected() || *connected_at.lock().unwrap() != connection {
+                    break;
+                }
+                let level = state.battery_level().saturating_sub(1).max(MIN_BATTERY_LEVEL);
+                state.set_battery_level(level).await;
+            }
+        });
+    }
+
+    fn spawn_generator(&self, generation: u64) {
+        let state = self.state.clone();
+        let foot = self.foot;
+        let connected_at = *self.connected_at.lock().unwrap();
+        let events = self.events.clone();
+        let me = self.me.clone();
+        let dropout_at = self.dropout.map(|dropout| Instant::now() + dropout);
+        let unreachable_until = self.unreachable_until.clone();
+        let tare = self.tare.clone();
+
+        tauri::async_runtime::spawn(async move {
+            // Advance the deadline by the interval (not to "now") to avoid drift, like the firmware
+            let mut next_sample = Instant::now() + state.sample_interval();
+            loop {
+                let now = Instant::now();
+                if next_sample > now {
+                    async_std::task::sleep(next_sample - now).await;
+                }
+                next_sample += state.sample_interval();
+
+                if !state.is_current(generation) {
+                    break;
+                }
+
+                if dropout_at.is_some_and(|at| Instant::now() >= at) {
+                    println!("[Simulated] {} dropped its link", foot.local_name());
+                    *unreachable_until.lock().unwrap() = Some(Instant::now() + DROPOUT_UNAVAILABLE);
+                    state.disconnect();
+                    if let Some(me) = me.upgrade() {
+                        events.publish(TransportEvent::Disconnected(me));
+                    }
+                    break;
+                }
+
+                if !state.is_sampling() {
+                    continue;
+                }
+
+                // Firmware uses millis() resolution for the waveform time base
+                let elapsed_ms = connected_at.elapsed().as_millis() as f32;
+                let mut values = mock_values(foot, elapsed_ms / 1000.0);
+                let tare = {
+                    let mut tare = tare.lock().unwrap();
+                    if state.take_zero_request() {
+                        tare.copy_from_slice(&values[..3]);
+                    }
+                    *tare
+                };
+                for (value, offset) in values.iter_mut().zip(tare) {
+                    *value -= offset;
+                }
+                state.notify(encode_gait_packet(&values).to_vec()).await;
+            }
+            println!("[Simulated] {} stopped notifying", foot.local_name());
+        });
+    }
+}
+
+#[async_trait]
+impl SensorPeripheral for SimulatedPeripheral {
+    fn id(&self) -> String {
+        self.address.to_string()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>, String> {
+        Ok(Some(PeripheralProperties {
+            address: self.address,
+            address_type: Some(AddressType::Random),
+            local_name: Some(self.foot.local_name().to_string()),
+            tx_power_level: None,
+            rssi: Some(-55),
+            services: vec![GAIT_SERVICE_UUID],
+            ..Default::default()
+        }))
+    }
+
+    async fn is_connected(&self) -> Result<bool, String> {
+        Ok(self.state.is_connected())
+    }
+
+    async fn connect(&self) -> Result<(), String> {
+        if self.unreachable_until.lock().unwrap().is_some_and(|until| Instant::now() < until) {
+            return Err(format!("{} is not reachable", self.foot.local_name()));
+        }
+        if self.state.connect() {
+            *self.connected_at.lock().unwrap() = Instant::now();
+            println!("[Simulated] {} connected", self.foot.local_name());
+            self.spawn_battery_drain();
+            self.publish(TransportEvent::Connected);
+        }
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), String> {
+        self.state.disconnect();
+        println!("[Simulated] {} disconnected", self.foot.local_name());
+        self.publish(TransportEvent::Disconnected);
+        Ok(())
+    }
+
+    async fn discover_services(&self) -> Result<(), String> {
+        self.state.discover_services(self.foot.local_name())
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.state.services()
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>, String> {
+        self.state.read(self.foot.local_name(), characteristic)
+    }
+
+    async fn write(&self, characteristic: &Characteristic, data: &[u8], _write_type: WriteType) -> Result<(), String> {
+        self.state.write(self.foot.local_name(), characteristic, data)
+    }
+
+    async fn subscribe(&self, characteristic: &Characteristic) -> Result<(), String> {
+        if self.state.is_battery_level(characteristic) {
+            return self.state.set_battery_subscribed(self.foot.local_name(), true);
+        }
+        let generation = self.state.subscribe(self.foot.local_name(), characteristic)?;
+        self.spawn_generator(generation);
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, characteristic: &Characteristic) -> Result<(), String> {
+        if self.state.is_battery_level(characteristic) {
+            return self.state.set_battery_subscribed(self.foot.local_name(), false);
+        }
+        self.state.unsubscribe(characteristic)
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, String> {
+        Ok(self.state.notifications())
+    }
+}
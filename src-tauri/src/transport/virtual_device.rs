@@ -0,0 +1,346 @@
+// Connection/subscription bookkeeping shared by the software-only peripherals
+// (simulated firmware and session replay)
+
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use btleplug::api::{CharPropFlags, Characteristic, Service, ValueNotification};
+use uuid::Uuid;
+
+use super::{
+    NotificationStream, BATTERY_LEVEL_UUID, BATTERY_SERVICE_UUID, DEVICE_INFORMATION_SERVICE_UUID,
+    FIRMWARE_REVISION_UUID, GAIT_CHARACTERISTIC_UUID, GAIT_CONTROL_CHARACTERISTIC_UUID, GAIT_SERVICE_UUID,
+    HARDWARE_REVISION_UUID,
+    MANUFACTURER_NAME_UUID, MODEL_NUMBER_UUID, SERIAL_NUMBER_UUID,
+};
+
+// Control opcodes and status results of the firmware's control characteristic
+const CONTROL_START_SAMPLING: u8 = 0x01;
+const CONTROL_STOP_SAMPLING: u8 = 0x02;
+const CONTROL_SET_SAMPLE_RATE: u8 = 0x03;
+const CONTROL_IDENTIFY: u8 = 0x04;
+const CONTROL_ZERO: u8 = 0x05;
+const STATUS_OK: u8 = 0x00;
+const STATUS_UNKNOWN_COMMAND: u8 = 0x01;
+const STATUS_INVALID_ARGUMENT: u8 = 0x02;
+
+const DEFAULT_SAMPLE_RATE_HZ: u16 = 100;
+const MAX_SAMPLE_RATE_HZ: u16 = 1000;
+
+// Device Information Service strings; devices that have them also expose a Battery Service
+// and the control characteristic
+pub struct VirtualDeviceInfo {
+    pub manufacturer_name: String,
+    pub model_number: String,
+    pub serial_number: String,
+    pub firmware_revision: String,
+    pub hardware_revision: String,
+}
+
+pub struct VirtualDeviceState {
+    connected: AtomicBool,
+    services_discovered: AtomicBool,
+    subscribed: AtomicBool,
+    // Bumped on every subscribe/unsubscribe so a stale generator task exits
+    generation: AtomicU64,
+    subscribers: Mutex<Vec<async_std::channel::Sender<ValueNotification>>>,
+    device_info: Option<VirtualDeviceInfo>,
+    battery_level: AtomicU8,
+    battery_subscribed: AtomicBool,
+    // Driven by the control characteristic
+    sampling: AtomicBool,
+    sample_rate_hz: AtomicU16,
+    zero_requested: AtomicBool,
+    // Opcode, sequence and result of the last control command
+    last_command: Mutex<(u8, u8, u8)>,
+}
+
+impl VirtualDeviceState {
+    pub fn new() -> Self {
+        Self {
+            connected: AtomicBool::new(false),
+            services_discovered: AtomicBool::new(false),
+            subscribed: AtomicBool::new(false),
+            generation: AtomicU64::new(0),
+            subscribers: Mutex::new(Vec::new()),
+            device_info: None,
+            battery_level: AtomicU8::new(100),
+            battery_subscribed: AtomicBool::new(false),
+            sampling: AtomicBool::new(true),
+            sample_rate_hz: AtomicU16::new(DEFAULT_SAMPLE_RATE_HZ),
+            zero_requested: AtomicBool::new(false),
+            last_command: Mutex::new((0, 0, STATUS_OK)),
+        }
+    }
+
+    pub fn with_device_info(device_info: VirtualDeviceInfo) -> Self {
+        Self {
+            device_info: Some(device_info),
+            ..Self::new()
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    // Returns true when this call changed the state from disconnected to connected
+    pub fn connect(&self) -> bool {
+        !self.connected.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn disconnect(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+        self.subscribed.store(false, Ordering::SeqCst);
+        self.battery_subscribed.store(false, Ordering::SeqCst);
+        self.services_discovered.store(false, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn ensure_connected(&self, name: &str) -> Result<(), String> {
+        if self.is_connected() {
+            Ok(())
+        } else {
+            Err(format!("Virtual device {} is not connected", name))
+        }
+    }
+
+    pub fn discover_services(&self, name: &str) -> Result<(), String> {
+        self.ensure_connected(name)?;
+        self.services_discovered.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    // The gait service as exposed by the mock firmware, once services are discovered
+    pub fn services(&self) -> BTreeSet<Service> {
+        if !self.services_discovered.load(Ordering::Relaxed) {
+            return BTreeSet::new();
+        }
+        let mut characteristics = BTreeSet::new();
+        characteristics.insert(gait_characteristic());
+        if self.has_control() {
+            characteristics.insert(Characteristic {
+                uuid: GAIT_CONTROL_CHARACTERISTIC_UUID,
+                service_uuid: GAIT_SERVICE_UUID,
+                properties: CharPropFlags::READ | CharPropFlags::WRITE,
+            });
+        }
+        let mut services = BTreeSet::new();
+        services.insert(Service {
+            uuid: GAIT_SERVICE_UUID,
+            primary: true,
+            characteristics,
+        });
+
+        if self.device_info.is_some() {
+            let service = |uuid: Uuid, characteristics: &[(Uuid, CharPropFlags)]| Service {
+                uuid,
+                primary: true,
+                characteristics: characteristics
+                    .iter()
+                    .map(|&(characteristic_uuid, properties)| Characteristic {
+                        uuid: characteristic_uuid,
+                        service_uuid: uuid,
+                        properties,
+                    })
+                    .collect(),
+            };
+            services.insert(service(
+                BATTERY_SERVICE_UUID,
+                &[(BATTERY_LEVEL_UUID, CharPropFlags::READ | CharPropFlags::NOTIFY)],
+            ));
+            services.insert(service(
+                DEVICE_INFORMATION_SERVICE_UUID,
+                &[
+                    (MANUFACTURER_NAME_UUID, CharPropFlags::READ),
+                    (MODEL_NUMBER_UUID, CharPropFlags::READ),
+                    (SERIAL_NUMBER_UUID, CharPropFlags::READ),
+                    (FIRMWARE_REVISION_UUID, CharPropFlags::READ),
+                    (HARDWARE_REVISION_UUID, CharPropFlags::READ),
+                ],
+            ));
+        }
+        services
+    }
+
+    pub fn read(&self, name: &str, characteristic: &Characteristic) -> Result<Vec<u8>, String> {
+        self.ensure_connected(name)?;
+        let info = self.device_info.as_ref();
+        let text = |value: Option<&String>| value.map(|v| v.as_bytes().to_vec());
+        let value = match characteristic.uuid {
+            BATTERY_LEVEL_UUID if info.is_some() => Some(vec![self.battery_level.load(Ordering::Relaxed)]),
+            GAIT_CONTROL_CHARACTERISTIC_UUID if self.has_control() => Some(self.control_status()),
+            MANUFACTURER_NAME_UUID => text(info.map(|i| &i.manufacturer_name)),
+            MODEL_NUMBER_UUID => text(info.map(|i| &i.model_number)),
+            SERIAL_NUMBER_UUID => text(info.map(|i| &i.serial_number)),
+            FIRMWARE_REVISION_UUID => text(info.map(|i| &i.firmware_revision)),
+            HARDWARE_REVISION_UUID => text(info.map(|i| &i.hardware_revision)),
+            _ => None,
+        };
+        value.ok_or_else(|| format!("Characteristic {} cannot be read on virtual device {}", characteristic.uuid, name))
+    }
+
+    // Applies a control command the way the firmware does: the write itself always succeeds
+    // and the outcome is reported through the status read back afterwards
+    pub fn write(&self, name: &str, characteristic: &Characteristic, data: &[u8]) -> Result<(), String> {
+        self.ensure_connected(name)?;
+        if !self.has_control() || characteristic.uuid != GAIT_CONTROL_CHARACTERISTIC_UUID {
+            return Err(format!("Characteristic {} cannot be written on virtual device {}", characteristic.uuid, name));
+        }
+        let [opcode, sequence, payload @ ..] = data else {
+            return Err(format!("Control command too short for virtual device {}", name));
+        };
+        let (opcode, sequence) = (*opcode, *sequence);
+
+        let result = match (opcode, payload) {
+            (CONTROL_START_SAMPLING, []) => {
+                self.sampling.store(true, Ordering::SeqCst);
+                STATUS_OK
+            }
+            (CONTROL_STOP_SAMPLING, []) => {
+                self.sampling.store(false, Ordering::SeqCst);
+                STATUS_OK
+            }
+            (CONTROL_SET_SAMPLE_RATE, &[low, high]) => {
+                let rate = u16::from_le_bytes([low, high]);
+                if (1..=MAX_SAMPLE_RATE_HZ).contains(&rate) {
+                    self.sample_rate_hz.store(rate, Ordering::SeqCst);
+                    STATUS_OK
+                } else {
+                    STATUS_INVALID_ARGUMENT
+                }
+            }
+            (CONTROL_IDENTIFY, &[seconds]) => {
+                println!("[Virtual] {} blinking its LED for {} s", name, seconds);
+                STATUS_OK
+            }
+            (CONTROL_ZERO, []) => {
+                self.zero_requested.store(true, Ordering::SeqCst);
+                STATUS_OK
+            }
+            (CONTROL_START_SAMPLING..=CONTROL_ZERO, _) => STATUS_INVALID_ARGUMENT,
+            _ => STATUS_UNKNOWN_COMMAND,
+        };
+        *self.last_command.lock().unwrap() = (opcode, sequence, result);
+        Ok(())
+    }
+
+    fn has_control(&self) -> bool {
+        self.device_info.is_some()
+    }
+
+    // [last opcode, result, flags (bit 0: sampling), sample rate (u16 LE), last sequence]
+    fn control_status(&self) -> Vec<u8> {
+        let (opcode, sequence, result) = *self.last_command.lock().unwrap();
+        let rate = self.sample_rate_hz.load(Ordering::Relaxed).to_le_bytes();
+        vec![opcode, result, self.is_sampling() as u8, rate[0], rate[1], sequence]
+    }
+
+    pub fn is_sampling(&self) -> bool {
+        self.sampling.load(Ordering::Relaxed)
+    }
+
+    pub fn sample_interval(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.sample_rate_hz.load(Ordering::Relaxed) as f64)
+    }
+
+    // True once per zero command
+    pub fn take_zero_request(&self) -> bool {
+        self.zero_requested.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn is_battery_level(&self, characteristic: &Characteristic) -> bool {
+        self.device_info.is_some() && characteristic.uuid == BATTERY_LEVEL_UUID
+    }
+
+    pub fn set_battery_subscribed(&self, name: &str, subscribed: bool) -> Result<(), String> {
+        if subscribed {
+            self.ensure_connected(name)?;
+        }
+        self.battery_subscribed.store(subscribed, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn battery_level(&self) -> u8 {
+        self.battery_level.load(Ordering::Relaxed)
+    }
+
+    // Notifies subscribers of the Battery Level characteristic when the level changes
+    pub async fn set_battery_level(&self, level: u8) {
+        if self.battery_level.swap(level, Ordering::SeqCst) != level
+            && self.battery_subscribed.load(Ordering::Relaxed)
+            && self.is_connected()
+        {
+            self.publish(BATTERY_LEVEL_UUID, vec![level]).await;
+        }
+    }
+
+    // Marks the gait characteristic as subscribed and returns the generation the new
+    // generator task must keep checking with `is_current`
+    pub fn subscribe(&self, name: &str, characteristic: &Characteristic) -> Result<u64, String> {
+        self.ensure_connected(name)?;
+        check_characteristic(characteristic)?;
+        self.subscribed.store(true, Ordering::SeqCst);
+        Ok(self.generation.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    pub fn unsubscribe(&self, characteristic: &Characteristic) -> Result<(), String> {
+        check_characteristic(characteristic)?;
+        self.subscribed.store(false, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+            && self.subscribed.load(Ordering::Relaxed)
+            && self.connected.load(Ordering::Relaxed)
+    }
+
+    pub fn notifications(&self) -> NotificationStream {
+        // Like btleplug, the stream stays valid across connections
+        let (tx, rx) = async_std::channel::bounded(1024);
+        self.subscribers.lock().unwrap().push(tx);
+        Box::pin(rx)
+    }
+
+    pub async fn notify(&self, value: Vec<u8>) {
+        self.publish(GAIT_CHARACTERISTIC_UUID, value).await;
+    }
+
+    async fn publish(&self, uuid: Uuid, value: Vec<u8>) {
+        let notification = ValueNotification { uuid, value };
+        let subscribers: Vec<_> = self.subscribers.lock().unwrap().clone();
+        for tx in subscribers {
+            let _ = tx.send(notification.clone()).await;
+        }
+        self.subscribers.lock().unwrap().retain(|tx| !tx.is_closed());
+    }
+}
+
+pub fn gait_characteristic() -> Characteristic {
+    Characteristic {
+        uuid: GAIT_CHARACTERISTIC_UUID,
+        service_uuid: GAIT_SERVICE_UUID,
+        properties: CharPropFlags::READ | CharPropFlags::NOTIFY,
+    }
+}
+
+fn check_characteristic(characteristic: &Characteristic) -> Result<(), String> {
+    if characteristic.uuid == GAIT_CHARACTERISTIC_UUID {
+        Ok(())
+    } else {
+        Err(format!("Characteristic {} not present on virtual device", characteristic.uuid))
+    }
+}
+
+// Packs six channel values into the firmware's 24-byte little-endian f32 layout
+pub fn encode_gait_packet(values: &[f32; 6]) -> [u8; 24] {
+    let mut packet = [0u8; 24];
+    for (i, value) in values.iter().enumerate() {
+        packet[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    packet
+}
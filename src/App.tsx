@@ -1,4 +1,5 @@
 import { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api/core'
 import Sidebar from './components/Sidebar'
 import ConnectTab from './components/ConnectTab'
 import CollectTab from './components/CollectTab'
@@ -7,10 +8,11 @@ import SettingsTab from './components/SettingsTab'
 import ScrollableContainer from './components/ScrollableContainer'
 import KeyboardHelpDialog from './components/KeyboardHelpDialog'
 import { DeviceConnectionProvider } from './contexts/DeviceConnectionContext'
-import { ToastProvider } from './contexts/ToastContext'
+import { ToastProvider, useToast } from './contexts/ToastContext'
 import { ScrollProvider } from './contexts/ScrollContext'
 import { useTabScrollReset } from './hooks/useTabScrollReset'
 import { useKeyboardShortcuts, createCommonShortcuts } from './hooks/useKeyboardShortcuts'
+import type { SessionMetadata } from './types'
 
 type Page = 'connect' | 'collect' | 'logs' | 'settings'
 
@@ -21,6 +23,22 @@ function AppContent() {
 
   useTabScrollReset([page])
 
+  const { showWarning } = useToast()
+
+  // Recordings interrupted by a crash are finished by the backend at startup
+  useEffect(() => {
+    invoke<SessionMetadata[]>('get_recovered_sessions')
+      .then((sessions) => {
+        if (sessions?.length > 0) {
+          showWarning(
+            'Recordings Recovered',
+            `${sessions.length} recording(s) interrupted when the app last quit were recovered: ${sessions.map((s) => s.session_name).join(', ')}. They are listed in the Logs tab.`,
+          )
+        }
+      })
+      .catch((error) => console.warn('[Recovery] Failed to get recovered sessions:', error))
+  }, [showWarning])
+
   const shortcuts = createCommonShortcuts(
     setPage,
     () => setDarkMode(!darkMode),
@@ -154,10 +154,14 @@ jest.mock('../contexts/DeviceConnectionContext', () => ({
     React.createElement('div', { 'data-testid': 'device-provider' }, children),
 }))
 
-jest.mock('../contexts/ToastContext', () => ({
-  ToastProvider: ({ children }: { children: React.ReactNode }) =>
-    React.createElement('div', { 'data-testid': 'toast-provider' }, children),
-}))
+jest.mock('../contexts/ToastContext', () => {
+  const toast = { showWarning: jest.fn() }
+  return {
+    ToastProvider: ({ children }: { children: React.ReactNode }) =>
+      React.createElement('div', { 'data-testid': 'toast-provider' }, children),
+    useToast: () => toast,
+  }
+})
 
 jest.mock('../contexts/ScrollContext', () => ({
   ScrollProvider: ({ children }: { children: React.ReactNode }) =>
@@ -1,4 +1,5 @@
-import { useEffect, useRef } from 'react'
+import { useEffect, useRef, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
 import MetadataForm from './MetadataForm'
 import LiveChart from './LiveChart'
 import DeviceStatusViewer from './MultiDeviceSelector'
@@ -10,29 +11,23 @@ import { useConfirmation } from '../hooks/useConfirmation'
 import { usePersistentWorkflow } from '../hooks/usePersistentWorkflow'
 import ErrorBoundary from './ErrorBoundary'
 import { protectedOperations, securityMonitor } from '../services/csrfProtection'
+import type { RecordingStatus, SessionMetadata } from '../types'
 import '../styles/modal.css'
 import '../styles/tabs.css'
 
 type CollectStep = 'metadata' | 'live' | 'review'
 
-interface GaitDataPoint {
-  device_id: string
-  r1: number
-  r2: number
-  r3: number
-  x: number
-  y: number
-  z: number
-  timestamp: number
-  sample_rate?: number
-}
+// How often the live step refreshes the recording progress
+const RECORDING_STATUS_POLL_MS = 1000
 
 interface CollectedData {
   sessionName: string
   subjectId: string
   notes: string
-  dataPoints: GaitDataPoint[]
   timestamp: Date
+  // Set once the backend has finished writing the recording
+  session: SessionMetadata | null
+  stoppedAt: number | null
 }
 
 interface CollectTabProps {
@@ -82,22 +77,51 @@ export default function CollectTab({ onNavigateToConnect }: CollectTabProps) {
   const setIsSaving = (saving: boolean) => updateWorkflowField('isSaving', saving)
   const setIsStopping = (stopping: boolean) => updateWorkflowField('isStopping', stopping)
 
+  const [recordingStatus, setRecordingStatus] = useState<RecordingStatus | null>(null)
+
   const restorationAppliedRef = useRef(false)
   useEffect(() => {
     if (restorationAppliedRef.current) return
     if (isInitialized && hasSavedData()) {
       const currentState = workflowState
       if (currentState.isCollecting || currentState.isStopping || currentState.isSaving) {
-        console.log('[Collect][Restore] Resetting transient flags from restored workflow')
-        updateWorkflowField('isCollecting', false)
-        updateWorkflowField('isStopping', false)
-        updateWorkflowField('isSaving', false)
+        // The backend keeps recording across a reload; only resume if it still is
+        invoke<RecordingStatus | null>('get_recording_status')
+          .catch(() => null)
+          .then((status) => {
+            if (status) {
+              console.log('[Collect][Restore] Resuming display of active recording', status.id)
+              setRecordingStatus(status)
+            } else {
+              console.log('[Collect][Restore] Resetting transient flags from restored workflow')
+              updateWorkflowField('isCollecting', false)
+            }
+            updateWorkflowField('isStopping', false)
+            updateWorkflowField('isSaving', false)
+          })
       }
       restorationAppliedRef.current = true
     }
   }, [isInitialized, hasSavedData, workflowState, updateWorkflowField])
 
-  const dataBuffer = useRef<GaitDataPoint[]>([])
+  useEffect(() => {
+    if (!isCollecting) return
+    let cancelled = false
+    const refresh = async () => {
+      try {
+        const status = await invoke<RecordingStatus | null>('get_recording_status')
+        if (!cancelled) setRecordingStatus(status)
+      } catch (error) {
+        console.warn('[Collect][Warn] Failed to get recording status:', error)
+      }
+    }
+    refresh()
+    const interval = setInterval(refresh, RECORDING_STATUS_POLL_MS)
+    return () => {
+      cancelled = true
+      clearInterval(interval)
+    }
+  }, [isCollecting])
 
   const metadataFormClearRef = useRef<(() => void) | null>(null)
 
@@ -127,53 +151,9 @@ export default function CollectTab({ onNavigateToConnect }: CollectTabProps) {
       </div>
     )
   }
-  const { connectedDevices, startDeviceCollection, stopDeviceCollection, subscribeToGaitData, deviceSides } =
+  const { connectedDevices, startDeviceCollection, stopDeviceCollection, deviceSides } =
     optionalCtx
 
-  useEffect(() => {
-    if (isCollecting) {
-      console.log('[Collect] Setting up gait data subscription')
-      const lastReceiveTimes = new Map<string, number>()
-      const packetCounts = new Map<string, number>()
-
-      const unsubscribe = subscribeToGaitData((data) => {
-        const receiveTime = performance.now()
-
-        const lastReceiveTime = lastReceiveTimes.get(data.device_id) || receiveTime
-        const interval = receiveTime - lastReceiveTime
-        lastReceiveTimes.set(data.device_id, receiveTime)
-
-        packetCounts.set(data.device_id, (packetCounts.get(data.device_id) || 0) + 1)
-        const count = packetCounts.get(data.device_id) || 0
-
-        if (count % 100 === 0) {
-          const avgInterval = interval > 0 ? interval : 0
-          const estimatedHz = avgInterval > 0 ? 1000 / avgInterval : 0
-          console.log(
-            `[Collect][Timing] [${data.device_id}, packet ${count}]: Interval: ${avgInterval.toFixed(1)}ms, Est. Rate: ${estimatedHz.toFixed(1)}Hz`,
-          )
-        }
-
-        console.log('[Collect] Received gait data:', data.device_id, data.timestamp)
-        dataBuffer.current.push({
-          device_id: data.device_id,
-          r1: data.r1,
-          r2: data.r2,
-          r3: data.r3,
-          x: data.x,
-          y: data.y,
-          z: data.z,
-          timestamp: data.timestamp,
-        })
-      })
-
-      return () => {
-        console.log('[Collect] Cleaning up gait data subscription')
-        unsubscribe()
-      }
-    }
-  }, [isCollecting]) // eslint-disable-line react-hooks/exhaustive-deps -- subscribeToGaitData should be stable, excluding to prevent re-subscriptions
-
   const steps = [
     { id: 'metadata', label: 'Metadata', number: 1 },
     { id: 'live', label: 'Live Collection', number: 2 },
@@ -185,16 +165,59 @@ export default function CollectTab({ onNavigateToConnect }: CollectTabProps) {
     subjectId: string
     notes: string
   }) => {
-    dataBuffer.current = []
-
+    setRecordingStatus(null)
     setCollectedData({
       ...metadata,
-      dataPoints: [],
       timestamp: new Date(),
+      session: null,
+      stoppedAt: null,
     })
     setCurrentStep('live')
   }
 
+  // Appends the device side mapping to the notes if available and not already present
+  const notesWithSides = (notes: string, deviceIds: string[]) => {
+    try {
+      if (deviceSides && deviceSides.size) {
+        const sidePairs = deviceIds
+          .map((id) => {
+            const side = deviceSides.get(id)
+            return side ? `${id}=${side}` : null
+          })
+          .filter(Boolean) as string[]
+        if (sidePairs.length && !notes.toLowerCase().includes('sides:')) {
+          const sideLine = `Sides: ${sidePairs.join(', ')}`
+          return notes ? `${notes}\n${sideLine}` : sideLine
+        }
+      }
+    } catch {
+      // Silently ignore side augmentation errors
+    }
+    return notes
+  }
+
+  // Stops the devices that were started for a recording that could not begin
+  const stopDevices = async (deviceIds: string[]) => {
+    await Promise.allSettled(deviceIds.map((deviceId) => stopDeviceCollection(deviceId)))
+  }
+
+  // CSRF and rate limit errors get specific guidance; anything else is reported as is
+  const alertFileOperationError = (action: string, error: unknown) => {
+    const errorMessage = error instanceof Error ? error.message : String(error)
+    if (errorMessage.includes('CSRF')) {
+      alert(
+        `Security Error: ${errorMessage}\n\nThis might be due to an expired session. The page will refresh to get a new security token.`,
+      )
+      window.location.reload()
+    } else if (errorMessage.includes('rate limit')) {
+      alert(`Rate Limit Exceeded: ${errorMessage}\n\nPlease wait a moment before trying again.`)
+    } else {
+      alert(
+        `Failed to ${action}: ${errorMessage}\n\nPlease check the console for more details and try again.`,
+      )
+    }
+  }
+
   const handleStartCollection = async () => {
     try {
       console.log('[Collect] Starting synchronized collection for all connected devices')
@@ -205,9 +228,6 @@ export default function CollectTab({ onNavigateToConnect }: CollectTabProps) {
         return
       }
 
-      dataBuffer.current = []
-      console.log('[Collect] Cleared data buffer for new collection')
-
       const startPromises = connectedDevices.map(async (deviceId) => {
         try {
           console.log(`[Collect] Starting collection for device: ${deviceId}`)
@@ -241,7 +261,24 @@ export default function CollectTab({ onNavigateToConnect }: CollectTabProps) {
         `[Collect] Start results: ${successfulDevices.length} successful, ${failedDevices.length} failed`,
       )
 
-      if (successfulDevices.length > 0) {
+      if (successfulDevices.length > 0 && collectedData) {
+        // The backend writes the samples of these devices straight to the session file
+        try {
+          const status = await protectedOperations.startRecording(
+            collectedData.sessionName,
+            collectedData.subjectId,
+            notesWithSides(collectedData.notes || '', successfulDevices),
+            successfulDevices,
+          )
+          console.log('[Collect] Recording to:', status.file_path)
+          setRecordingStatus(status)
+        } catch (error) {
+          console.error('[Collect][Error] Failed to start recording:', error)
+          await stopDevices(successfulDevices)
+          alertFileOperationError('start recording', error)
+          return
+        }
+
         console.log('[Collect] Successfully started synchronized data collection')
         setIsUsingRealData(true)
         setIsCollecting(true)
@@ -286,14 +323,19 @@ export default function CollectTab({ onNavigateToConnect }: CollectTabProps) {
         console.warn('[Collect][Warn] Unable to parse collectedData.timestamp, raw value:', rawTs)
       }
     }
+    const status = await invoke<RecordingStatus | null>('get_recording_status').catch(
+      () => recordingStatus,
+    )
+    const samplesWritten = status?.samples_written ?? 0
+    const recordingStartMs = status?.started_at ?? sessionStartMs
     const collectionTimeText =
-      dataBuffer.current.length > 0 && sessionStartMs !== undefined
-        ? `${Math.round((Date.now() - sessionStartMs) / 1000)}s`
+      recordingStartMs !== undefined
+        ? `${Math.round((Date.now() - recordingStartMs) / 1000)}s`
         : 'N/A'
 
     const warningText =
-      dataBuffer.current.length > 0
-        ? 'Your collected data will be saved and you can review it in the next step.'
+      samplesWritten > 0
+        ? 'Your collected data has been written to disk and you can review it in the next step.'
         : 'No data has been collected yet. You may want to continue collecting before stopping.'
 
     const confirmed = await showConfirmation({
@@ -301,7 +343,7 @@ export default function CollectTab({ onNavigateToConnect }: CollectTabProps) {
       message: `Are you sure you want to stop collecting data?
 
 Current Session: ${collectedData?.sessionName}
-Data Points Collected: ${dataBuffer.current.length}
+Data Points Collected: ${samplesWritten}
 Collection Time: ${collectionTimeText}
 
 ${warningText}`,
@@ -330,12 +372,8 @@ ${warningText}`,
         isCollecting,
         isUsingRealData,
         connectedDevices: connectedDevices.length,
-        dataBufferLength: dataBuffer.current.length,
       })
 
-      const finalDataPoints = [...dataBuffer.current]
-      console.log(`[Collect] Captured ${finalDataPoints.length} data points before stopping`)
-
       if (isUsingRealData && connectedDevices.length > 0) {
         console.log('[Collect] Stopping BLE notifications for all connected devices')
 
@@ -377,23 +415,7 @@ ${warningText}`,
       setIsCollecting(false)
       console.log('[Collect] Set isCollecting to false')
 
-      if (collectedData) {
-        const updatedData = {
-          ...collectedData,
-          dataPoints: finalDataPoints,
-        }
-        setCollectedData(updatedData)
-        console.log('[Collect] Updated collected data:', {
-          sessionName: updatedData.sessionName,
-          dataPointsLength: updatedData.dataPoints.length,
-          sampleDataPoints: updatedData.dataPoints.slice(0, 2),
-        })
-      } else {
-        console.warn('[Collect][Warn] No collectedData state found')
-      }
-
-      console.log('[Collect] Moving to review step')
-      setCurrentStep('review')
+      await finishRecording()
     } catch (error) {
       console.error('[Collect][Error] Failed to stop collection:', error)
 
@@ -405,110 +427,78 @@ ${warningText}`,
       setIsCollecting(false)
       setIsUsingRealData(false)
 
-      const finalDataPoints = [...dataBuffer.current]
+      await finishRecording()
+    } finally {
+      setIsStopping(false)
+    }
+  }
+
+  // Has the backend finish the session file, then shows what it recorded
+  const finishRecording = async () => {
+    try {
+      const session = await protectedOperations.stopRecording()
+      console.log('[Collect] Recording finished:', {
+        sessionName: session.session_name,
+        dataPoints: session.data_points,
+        filePath: session.file_path,
+      })
       if (collectedData) {
-        setCollectedData({
-          ...collectedData,
-          dataPoints: finalDataPoints,
-        })
-        console.log('[Collect] Force-saved data points despite error:', finalDataPoints.length)
+        setCollectedData({ ...collectedData, session, stoppedAt: Date.now() })
+      } else {
+        console.warn('[Collect][Warn] No collectedData state found')
       }
-
+      setRecordingStatus(null)
+      console.log('[Collect] Moving to review step')
       setCurrentStep('review')
-    } finally {
-      setIsStopping(false)
+    } catch (error) {
+      console.error('[Collect][Error] Failed to finish recording:', error)
+      alertFileOperationError('finish recording', error)
     }
   }
 
+  // The recording is already on disk; saving only keeps it and resets the workflow
   const handleSaveData = async () => {
-    if (!collectedData) {
-      console.error('[Collect][Error] No collected data to save')
+    const session = collectedData?.session
+    if (!session) {
+      console.error('[Collect][Error] No recorded session to save')
       alert('No data to save!')
       return
     }
 
-    if (collectedData.dataPoints.length === 0) {
+    if (session.data_points === 0) {
       console.error('[Collect][Error] No data points to save')
       alert('No data points collected. Please collect some data before saving.')
       return
     }
 
-    console.log('[Collect] Starting to save session...')
-    console.log('Session data to save:', {
-      sessionName: collectedData.sessionName,
-      subjectId: collectedData.subjectId,
-      dataPointsLength: collectedData.dataPoints.length,
-      sampleDataPoints: collectedData.dataPoints.slice(0, 3),
-    })
+    console.log('[Collect] Session saved to:', session.file_path)
+    showSuccess('Session Saved', `File: ${session.file_path}\nData points: ${session.data_points}`)
 
-    setIsSaving(true)
+    completeWorkflow()
+    setRecordingStatus(null)
 
-    try {
-      console.log('[Collect] Saving session with enhanced CSRF protection...')
+    metadataFormClearRef.current?.()
+  }
 
-      // Append device side mapping to notes if available and not already present
-      let notes = collectedData.notes || ''
+  // Deletes the recorded session file and its index entry
+  const handleDiscardData = async () => {
+    const session = collectedData?.session
+    if (session) {
+      setIsSaving(true)
       try {
-        if (deviceSides && deviceSides.size) {
-          const uniqueDeviceIds = Array.from(new Set(collectedData.dataPoints.map(d => d.device_id)))
-          const sidePairs = uniqueDeviceIds
-            .map(id => {
-              const side = deviceSides.get(id)
-              return side ? `${id}=${side}` : null
-            })
-            .filter(Boolean) as string[]
-          if (sidePairs.length) {
-            const sideLine = `Sides: ${sidePairs.join(', ')}`
-            if (!notes.toLowerCase().includes('sides:')) {
-              notes = notes ? `${notes}\n${sideLine}` : sideLine
-            }
-          }
-        }
-      } catch {
-        // Silently ignore side augmentation errors
-      }
-
-      const filePath = await protectedOperations.saveSessionData(
-        collectedData.sessionName,
-        collectedData.subjectId,
-        notes,
-        collectedData.dataPoints,
-      )
-
-      console.log('[Collect] Session saved successfully to:', filePath)
-      showSuccess(
-        'Session Saved',
-        `File: ${filePath}\nData points: ${collectedData.dataPoints.length}`,
-      )
-
-      completeWorkflow()
-      dataBuffer.current = []
-
-      metadataFormClearRef.current?.()
-    } catch (error) {
-      console.error('[Collect][Error] Failed to save session:', error)
-      const errorMessage = error instanceof Error ? error.message : String(error)
-
-      if (errorMessage.includes('CSRF')) {
-        alert(
-          `Security Error: ${errorMessage}\n\nThis might be due to an expired session. The page will refresh to get a new security token.`,
-        )
-        window.location.reload()
-      } else if (errorMessage.includes('rate limit')) {
-        alert(`Rate Limit Exceeded: ${errorMessage}\n\nPlease wait a moment before trying again.`)
-      } else {
-        alert(
-          `Failed to save session: ${errorMessage}\n\nPlease check the console for more details and try again.`,
-        )
+        await protectedOperations.deleteSession(session.id)
+        console.log('[Collect] Discarded session:', session.id)
+      } catch (error) {
+        console.error('[Collect][Error] Failed to discard session:', error)
+        alertFileOperationError('discard session', error)
+        return
+      } finally {
+        setIsSaving(false)
       }
-    } finally {
-      setIsSaving(false)
     }
-  }
 
-  const handleDiscardData = () => {
     completeWorkflow()
-    dataBuffer.current = []
+    setRecordingStatus(null)
 
     metadataFormClearRef.current?.()
   }
@@ -613,6 +603,11 @@ ${warningText}`,
                 <p>
                   <strong>Subject:</strong> {collectedData?.subjectId}
                 </p>
+                {recordingStatus && (
+                  <p>
+                    <strong>Recorded:</strong> {recordingStatus.samples_written} data points
+                  </p>
+                )}
               </div>
               <div className="collection-buttons">
                 {!isCollecting ? (
@@ -628,7 +623,11 @@ ${warningText}`,
                     {isStopping ? 'Stopping All Devices...' : 'Stop All Devices'}
                   </button>
                 )}
-                <button className="btn-tertiary" onClick={() => setCurrentStep('metadata')}>
+                <button
+                  className="btn-tertiary"
+                  onClick={() => setCurrentStep('metadata')}
+                  disabled={isCollecting}
+                >
                   Back to Metadata
                 </button>
               </div>
@@ -682,37 +681,33 @@ ${warningText}`,
                   <strong>Collected:</strong> {collectedData?.timestamp.toLocaleString()}
                 </p>
                 <p>
-                  <strong>Data Points:</strong> {collectedData?.dataPoints.length || 0}
+                  <strong>Data Points:</strong> {collectedData?.session?.data_points || 0}
                 </p>
-                {collectedData && collectedData.dataPoints.length > 0 && (
+                {collectedData?.session && collectedData.session.data_points > 0 && (
                   <div className="data-summary">
                     <p>
                       <strong>Devices:</strong>{' '}
-                      {[...new Set(collectedData.dataPoints.map((d) => d.device_id))].join(', ')}
+                      {collectedData.session.devices.map((d) => d.alias ?? d.id).join(', ')}
                     </p>
                     <p>
                       <strong>Collection Duration:</strong>{' '}
-                      {collectedData.dataPoints.length > 0
-                        ? `${Math.round((collectedData.dataPoints[collectedData.dataPoints.length - 1].timestamp - collectedData.dataPoints[0].timestamp) / 1000)}s`
+                      {collectedData.stoppedAt
+                        ? `${Math.round((collectedData.stoppedAt - collectedData.session.timestamp) / 1000)}s`
                         : 'N/A'}
                     </p>
+                    <p>
+                      <strong>File:</strong> {collectedData.session.file_path}
+                    </p>
                   </div>
                 )}
               </div>
 
               <div className="review-actions">
                 <button className="btn-primary" onClick={handleSaveData} disabled={isSaving}>
-                  {isSaving ? 'Saving...' : 'Save Session'}
+                  Save Session
                 </button>
                 <button className="btn-danger" onClick={handleDiscardData} disabled={isSaving}>
-                  Discard Data
-                </button>
-                <button
-                  className="btn-tertiary"
-                  onClick={() => setCurrentStep('live')}
-                  disabled={isSaving}
-                >
-                  Back to Collection
+                  {isSaving ? 'Discarding...' : 'Discard Data'}
                 </button>
               </div>
             </div>
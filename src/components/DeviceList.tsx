@@ -6,6 +6,7 @@ import { useKeyboardShortcuts } from '../hooks/useKeyboardShortcuts'
 import type { KeyboardShortcut } from '../hooks/useKeyboardShortcuts'
 import ConfirmationModal from './ConfirmationModal'
 import { Icon } from './icons'
+import type { GattService } from '../types'
 
 export default function DeviceList() {
   const [currentPage, setCurrentPage] = useState(1)
@@ -151,13 +152,19 @@ export default function DeviceList() {
       // This functionality would need to be moved to context if needed
       // For now, keeping the direct invoke call
       const { invoke } = await import('@tauri-apps/api/core')
-      const services: string[] = await invoke('debug_device_services', { deviceId })
+      const services: GattService[] = await invoke('get_gatt_tree', { deviceId })
       console.log('Debug: Services found:', services)
 
       const device = scannedDevices.find((d) => d.id === deviceId)
       const deviceName = device?.name || 'Unknown Device'
-
-      showInfo(`Services for ${deviceName}`, `Available services:\n\n${services.join('\n')}`)
+      const lines = services.flatMap((service) => [
+        `${service.name ?? 'Service'} ${service.uuid}`,
+        ...service.characteristics.map(
+          (c) => `    ${c.name ?? 'Characteristic'} ${c.uuid} [${c.properties.join(', ')}]`,
+        ),
+      ])
+
+      showInfo(`Services for ${deviceName}`, `Available services:\n\n${lines.join('\n')}`)
     } catch (e) {
       console.error('Debug services failed:', e)
       showError('Debug Failed', `Debug services failed: ${e}`)
@@ -433,9 +440,10 @@ export default function DeviceList() {
                               <span className="info-label">Manufacturer:</span>
                               <span
                                 className="info-value device-manufacturer"
-                                title={d.manufacturer_data[0]}
+                                title={d.manufacturer_data[0].hex}
                               >
-                                {d.manufacturer_data[0]}
+                                {d.manufacturer_data[0].company_name ??
+                                  `0x${d.manufacturer_data[0].company_id.toString(16).padStart(4, '0')}`}
                               </span>
                             </div>
                           )}
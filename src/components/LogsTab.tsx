@@ -11,6 +11,7 @@ import { protectedOperations } from '../services/csrfProtection'
 import '../styles/tables.css'
 import '../styles/tabs.css'
 import { Icon } from './icons'
+import type { SessionDevice } from '../types'
 
 interface LogEntry {
   id: string
@@ -21,6 +22,7 @@ interface LogEntry {
   file_path: string
   notes?: string
   devices: string[]
+  recovered?: boolean
 }
 
 interface SessionMetadata {
@@ -31,7 +33,8 @@ interface SessionMetadata {
   timestamp: number
   data_points: number
   file_path: string
-  devices: string[]
+  devices: SessionDevice[]
+  recovered?: boolean
 }
 
 export default function LogsTab() {
@@ -70,7 +73,8 @@ function LogsTabContent() {
         data_points: session.data_points,
         file_path: session.file_path,
         notes: session.notes,
-        devices: session.devices,
+        devices: session.devices.map((device) => device.alias ?? device.id),
+        recovered: session.recovered,
       }))
 
       logEntries.sort((a, b) => {
@@ -117,8 +121,9 @@ function LogsTabContent() {
           ? new Date(log.timestamp).toISOString().split('T')[0] // Backend now provides milliseconds directly
           : 'unknown-date'
 
-      const result = await protectedOperations.copyFileToDownloads(
-        log.file_path,
+      // Sessions are stored in the binary format; the backend converts them to CSV
+      const result = await protectedOperations.exportSessionCsv(
+        log.id,
         `${log.session_name}_${log.subject_id}_${safeDate}.csv`,
       )
 
@@ -132,6 +137,47 @@ function LogsTabContent() {
     }
   }
 
+  const handleExportEdf = async (log: LogEntry) => {
+    try {
+      const safeDate =
+        log.timestamp && log.timestamp > 0
+          ? new Date(log.timestamp).toISOString().split('T')[0]
+          : 'unknown-date'
+
+      // Resampled onto a fixed rate per device, with link gaps as annotations
+      const result = await protectedOperations.exportSessionEdf(
+        log.id,
+        `${log.session_name}_${log.subject_id}_${safeDate}.edf`,
+        'edf',
+      )
+
+      showSuccess('File Exported Successfully', `EDF+ file exported to Downloads folder: ${result}`)
+    } catch (error) {
+      console.error('Failed to export EDF+ file:', error)
+      showError('EDF+ Export Failed', String(error))
+    }
+  }
+
+  const handleExportC3d = async (log: LogEntry) => {
+    try {
+      const safeDate =
+        log.timestamp && log.timestamp > 0
+          ? new Date(log.timestamp).toISOString().split('T')[0]
+          : 'unknown-date'
+
+      // One analog channel per device channel, for motion analysis software
+      const result = await protectedOperations.exportSessionC3d(
+        log.id,
+        `${log.session_name}_${log.subject_id}_${safeDate}.c3d`,
+      )
+
+      showSuccess('File Exported Successfully', `C3D file exported to Downloads folder: ${result}`)
+    } catch (error) {
+      console.error('Failed to export C3D file:', error)
+      showError('C3D Export Failed', String(error))
+    }
+  }
+
   const handleDeleteLog = async (logId: string) => {
     const confirmed = await showConfirmation({
       title: 'Delete Session',
@@ -293,7 +339,18 @@ function LogsTabContent() {
               <tbody>
                 {logs.map((log) => (
                     <tr key={log.id}>
-                      <td className="session-name" data-label="Session Name">{log.session_name}</td>
+                      <td className="session-name" data-label="Session Name">
+                        {log.session_name}
+                        {log.recovered && (
+                          <span
+                            className="recovered-badge"
+                            title="Recovered after the app quit mid-recording"
+                          >
+                            {' '}
+                            (recovered)
+                          </span>
+                        )}
+                      </td>
                       <td data-label="Subject ID">{log.subject_id}</td>
                       <td data-label="Date & Time">{formatTimestamp(log.timestamp, 'full')}</td>
                       <td data-label="Data Points">{log.data_points.toLocaleString()}</td>
@@ -334,6 +391,26 @@ function LogsTabContent() {
                           <Icon.Download title="Download" />
                         </span>
                       </button>
+                      <button
+                        className="btn-small btn-secondary"
+                        onClick={() => handleExportEdf(log)}
+                        title="Export EDF+"
+                        aria-label={`Export ${log.session_name} as EDF+`}
+                      >
+                        <span aria-hidden="true" className="btn-icon">
+                          <Icon.Export title="Export EDF+" />
+                        </span>
+                      </button>
+                      <button
+                        className="btn-small btn-secondary"
+                        onClick={() => handleExportC3d(log)}
+                        title="Export C3D"
+                        aria-label={`Export ${log.session_name} as C3D`}
+                      >
+                        <span aria-hidden="true" className="btn-icon">
+                          <Icon.Export title="Export C3D" />
+                        </span>
+                      </button>
                       <button
                         className="btn-small btn-danger"
                         onClick={() => handleDeleteLog(log.id)}
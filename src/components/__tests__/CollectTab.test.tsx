@@ -28,6 +28,8 @@ jest.mock('../../services/csrfProtection', () => {
 
   const mockProtectedOperations = {
     saveSessionData: jest.fn(),
+    startRecording: jest.fn(),
+    stopRecording: jest.fn(),
     deleteSession: jest.fn(),
     copyFileToDownloads: jest.fn(),
     saveFilteredData: jest.fn(),
@@ -107,17 +109,17 @@ jest.mock('../ErrorBoundary', () => {
 import { useDeviceConnection } from '../../contexts/DeviceConnectionContext'
 import { securityMonitor } from '../../services/csrfProtection'
 
-interface GaitDataPoint {
-  device_id: string
-  r1: number
-  r2: number
-  r3: number
-  x: number
-  y: number
-  z: number
-  timestamp: number
-  sample_rate?: number
-}
+// Session returned by stop_recording
+const recordedSession = (dataPoints: number) => ({
+  id: 'session-1',
+  session_name: 'Test Session',
+  subject_id: 'Test Subject',
+  notes: 'Test Notes',
+  timestamp: 1000,
+  data_points: dataPoints,
+  file_path: '/path/to/saved/file.csv',
+  devices: [{ id: 'device1', alias: null, role: null }],
+})
 
 describe('CollectTab', () => {
   let container: HTMLDivElement
@@ -351,6 +353,20 @@ describe('CollectTab', () => {
 
     mockSecurityMonitor.startMonitoring.mockImplementation(() => {})
     mockSecurityMonitor.stopMonitoring.mockImplementation(() => {})
+
+    const { protectedOperations: mockProtectedOperations } = jest.requireMock(
+      '../../services/csrfProtection',
+    )
+    mockProtectedOperations.startRecording.mockResolvedValue({
+      id: 'session-1',
+      session_name: 'Test Session',
+      subject_id: 'Test Subject',
+      device_ids: ['device1'],
+      started_at: 1000,
+      file_path: '/path/to/saved/file.csv',
+      samples_written: 0,
+    })
+    mockProtectedOperations.stopRecording.mockResolvedValue(recordedSession(0))
   })
 
   afterEach(() => {
@@ -812,7 +828,7 @@ describe('CollectTab', () => {
       const { protectedOperations: mockProtectedOperations } = jest.requireMock(
         '../../services/csrfProtection',
       )
-      mockProtectedOperations.saveSessionData.mockResolvedValue('/path/to/saved/file.csv')
+      mockProtectedOperations.stopRecording.mockResolvedValue(recordedSession(1))
 
       flushSync(() => {
         root.render(<CollectTab />)
@@ -821,8 +837,18 @@ describe('CollectTab', () => {
       // Navigate through the full flow with simulated data
       await navigateToReviewStepWithData()
 
+      // The backend recorded the session while collecting; nothing is sent from the webview
+      expect(mockProtectedOperations.startRecording).toHaveBeenCalledWith(
+        'Test Session',
+        'Test Subject',
+        'Test Notes',
+        ['device1'],
+      )
+      expect(mockProtectedOperations.stopRecording).toHaveBeenCalled()
+
       // Should be in review step
       expect(container.textContent).toContain('Step 3: Review & Save')
+      expect(container.textContent).toContain('/path/to/saved/file.csv')
 
       // Save data
       const saveButton = Array.from(container.querySelectorAll('button')).find((btn) =>
@@ -837,11 +863,11 @@ describe('CollectTab', () => {
 
       await new Promise((resolve) => setTimeout(resolve, 20))
 
-      expect(mockProtectedOperations.saveSessionData).toHaveBeenCalled()
-      expect(alertSpy).toHaveBeenCalledWith(expect.stringContaining('Session saved successfully!'))
+      expect(mockProtectedOperations.saveSessionData).not.toHaveBeenCalled()
+      expect(container.textContent).toContain('Step 1: Enter Session Metadata')
     })
 
-    it('should handle CSRF error during save', async () => {
+    it('should handle CSRF error while finishing the recording', async () => {
       const mockReload = jest.fn()
       Object.defineProperty(window, 'location', {
         value: { reload: mockReload },
@@ -851,64 +877,32 @@ describe('CollectTab', () => {
       const { protectedOperations: mockProtectedOperations } = jest.requireMock(
         '../../services/csrfProtection',
       )
-      mockProtectedOperations.saveSessionData.mockRejectedValue(new Error('CSRF token invalid'))
+      mockProtectedOperations.stopRecording.mockRejectedValue(new Error('CSRF token invalid'))
 
       flushSync(() => {
         root.render(<CollectTab />)
       })
 
-      // Navigate through the full flow with simulated data
+      // The session is finished when collection stops, so that is where the error surfaces
       await navigateToReviewStepWithData()
 
-      // Should be in review step
-      expect(container.textContent).toContain('Step 3: Review & Save')
-
-      // Save data
-      const saveButton = Array.from(container.querySelectorAll('button')).find((btn) =>
-        btn.textContent?.includes('Save Session'),
-      ) as HTMLButtonElement
-
-      expect(saveButton).toBeTruthy()
-
-      flushSync(() => {
-        saveButton.click()
-      })
-
-      await new Promise((resolve) => setTimeout(resolve, 20))
-
       expect(alertSpy).toHaveBeenCalledWith(expect.stringContaining('Security Error'))
       expect(mockReload).toHaveBeenCalled()
     })
 
-    it('should handle rate limit error during save', async () => {
+    it('should handle rate limit error while finishing the recording', async () => {
       const { protectedOperations: mockProtectedOperations } = jest.requireMock(
         '../../services/csrfProtection',
       )
-      mockProtectedOperations.saveSessionData.mockRejectedValue(new Error('rate limit exceeded'))
+      mockProtectedOperations.stopRecording.mockRejectedValue(new Error('rate limit exceeded'))
 
       flushSync(() => {
         root.render(<CollectTab />)
       })
 
-      // Navigate through the full flow with simulated data
+      // The session is finished when collection stops, so that is where the error surfaces
       await navigateToReviewStepWithData()
 
-      // Should be in review step
-      expect(container.textContent).toContain('Step 3: Review & Save')
-
-      // Save data
-      const saveButton = Array.from(container.querySelectorAll('button')).find((btn) =>
-        btn.textContent?.includes('Save Session'),
-      ) as HTMLButtonElement
-
-      expect(saveButton).toBeTruthy()
-
-      flushSync(() => {
-        saveButton.click()
-      })
-
-      await new Promise((resolve) => setTimeout(resolve, 20))
-
       expect(alertSpy).toHaveBeenCalledWith(expect.stringContaining('Rate Limit Exceeded'))
     })
 
@@ -967,6 +961,12 @@ describe('CollectTab', () => {
           // Wait for state transition back to metadata
           await new Promise((resolve) => setTimeout(resolve, 100))
 
+          // The recorded session is deleted along with its file
+          const { protectedOperations: mockProtectedOperations } = jest.requireMock(
+            '../../services/csrfProtection',
+          )
+          expect(mockProtectedOperations.deleteSession).toHaveBeenCalledWith('session-1')
+
           // Re-render to get latest state
           flushSync(() => {
             root.render(<CollectTab />)
@@ -1005,36 +1005,13 @@ describe('CollectTab', () => {
     })
   })
 
-  describe('Gait Data Subscription', () => {
-    let mockSubscribeToGaitData: jest.Mock
-    let mockUnsubscribe: jest.Mock
-    let dataCallback: (data: GaitDataPoint) => void
-
+  describe('Backend Recording', () => {
     beforeEach(() => {
-      mockUnsubscribe = jest.fn()
-      mockSubscribeToGaitData = jest.fn((callback) => {
-        dataCallback = callback
-        return mockUnsubscribe
-      })
-
-      mockDeviceConnection.mockReturnValue({
-        connectedDevices: ['device1'],
-        startDeviceCollection: jest.fn().mockResolvedValue(undefined),
-        stopDeviceCollection: jest.fn().mockResolvedValue(undefined),
-        subscribeToGaitData: mockSubscribeToGaitData,
-        connectionStatus: 'connected',
-      })
-
       jest.spyOn(console, 'log').mockImplementation(() => {})
     })
 
-    it('should handle gait data reception and deduplication', async () => {
-      // Set up the mock properly within this test scope
-      const mockUnsubscribeFn = jest.fn()
-      const mockSubscribeFn = jest.fn((callback) => {
-        dataCallback = callback
-        return mockUnsubscribeFn
-      })
+    it('should record through the backend instead of buffering samples', async () => {
+      const mockSubscribeFn = jest.fn(() => jest.fn())
 
       mockDeviceConnection.mockReturnValue({
         connectedDevices: ['device1'],
@@ -1048,53 +1025,38 @@ describe('CollectTab', () => {
         root.render(<CollectTab />)
       })
 
-      // Navigate to live collection and start collecting to activate subscription
+      // Navigate to live collection and start collecting
       await navigateToLiveCollection()
 
       await startCollection()
 
-      // Wait a bit for the collection to start and subscription to be set up
       await new Promise((resolve) => setTimeout(resolve, 50))
 
-      // Now the subscription should be active
-      expect(mockSubscribeFn).toHaveBeenCalled()
-
-      // Simulate receiving gait data through the callback
-      const gaitData = {
-        device_id: 'device1',
-        r1: 1.0,
-        r2: 2.0,
-        r3: 3.0,
-        x: 0.1,
-        y: 0.2,
-        z: 0.3,
-        timestamp: 1000,
-      }
-
-      // Send data once
-      if (dataCallback) {
-        dataCallback(gaitData)
-      }
-
-      // Send same data again (should be deduplicated)
-      if (dataCallback) {
-        dataCallback(gaitData)
-      }
+      const { protectedOperations: mockProtectedOperations } = jest.requireMock(
+        '../../services/csrfProtection',
+      )
+      expect(mockProtectedOperations.startRecording).toHaveBeenCalledWith(
+        'Test Session',
+        'Test Subject',
+        'Test Notes',
+        ['device1'],
+      )
+      expect(mockSubscribeFn).not.toHaveBeenCalled()
     })
 
-    it('should handle performance monitoring during data collection', async () => {
-      // Set up the mock properly within this test scope
-      const mockUnsubscribeFn = jest.fn()
-      const mockSubscribeFn = jest.fn((callback) => {
-        dataCallback = callback
-        return mockUnsubscribeFn
-      })
+    it('should stop the devices when the recording cannot start', async () => {
+      const alertSpy = jest.spyOn(window, 'alert').mockImplementation(() => {})
+      const mockStopDevice = jest.fn().mockResolvedValue(undefined)
+      const { protectedOperations: mockProtectedOperations } = jest.requireMock(
+        '../../services/csrfProtection',
+      )
+      mockProtectedOperations.startRecording.mockRejectedValue(new Error('Disk full'))
 
       mockDeviceConnection.mockReturnValue({
         connectedDevices: ['device1'],
         startDeviceCollection: jest.fn().mockResolvedValue(undefined),
-        stopDeviceCollection: jest.fn().mockResolvedValue(undefined),
-        subscribeToGaitData: mockSubscribeFn,
+        stopDeviceCollection: mockStopDevice,
+        subscribeToGaitData: jest.fn(() => jest.fn()),
         connectionStatus: 'connected',
       })
 
@@ -1102,40 +1064,14 @@ describe('CollectTab', () => {
         root.render(<CollectTab />)
       })
 
-      // Navigate to live collection and start collecting
       await navigateToLiveCollection()
-
       await startCollection()
 
-      // Wait for subscription to be set up
       await new Promise((resolve) => setTimeout(resolve, 50))
 
-      expect(mockSubscribeFn).toHaveBeenCalled()
-
-      // Simulate receiving 101 data points to trigger performance logging
-      if (dataCallback) {
-        for (let i = 0; i < 101; i++) {
-          dataCallback({
-            device_id: 'device1',
-            r1: 1.0,
-            r2: 2.0,
-            r3: 3.0,
-            x: 0.1,
-            y: 0.2,
-            z: 0.3,
-            timestamp: 1000 + i,
-          })
-        }
-      }
-    })
-
-    it('should cleanup subscription on unmount', () => {
-      flushSync(() => {
-        root.render(<CollectTab />)
-      })
-
-      root.unmount()
-      // mockUnsubscribe should be called during cleanup, but only if collection was started
+      expect(mockStopDevice).toHaveBeenCalledWith('device1')
+      expect(alertSpy).toHaveBeenCalledWith(expect.stringContaining('Disk full'))
+      alertSpy.mockRestore()
     })
   })
 
@@ -1162,7 +1098,7 @@ describe('CollectTab', () => {
       expect(container.textContent).toContain('Step 1: Enter Session Metadata')
     })
 
-    it('should handle back to collection from review', async () => {
+    it('should not go back to collection from review', async () => {
       flushSync(() => {
         root.render(<CollectTab />)
       })
@@ -1170,18 +1106,12 @@ describe('CollectTab', () => {
       // Navigate to review step
       await navigateToReviewStep()
 
-      // Click back to collection
+      // The recording is already finished; collecting again starts a new session
       const backToCollectionButton = Array.from(container.querySelectorAll('button')).find((btn) =>
         btn.textContent?.includes('Back to Collection'),
-      ) as HTMLButtonElement
-
-      if (backToCollectionButton) {
-        flushSync(() => {
-          backToCollectionButton.click()
-        })
-      }
+      )
 
-      expect(container.textContent).toContain('Step 2: Live Data Collection')
+      expect(backToCollectionButton).toBeUndefined()
     })
   })
 
@@ -35,7 +35,7 @@ const mockDeviceConnection = {
       address_type: 'random',
       connectable: true,
       services: ['180A', '180F'],
-      manufacturer_data: ['Apple'],
+      manufacturer_data: [{ company_id: 0x004c, company_name: 'Apple, Inc.', hex: '', base64: '' }],
     },
     {
       id: 'device2',
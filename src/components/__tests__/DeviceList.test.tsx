@@ -27,7 +27,7 @@ const mockDeviceConnection = {
       address_type: 'random',
       connectable: true,
       services: ['180A', '180F'],
-      manufacturer_data: ['Apple'],
+      manufacturer_data: [{ company_id: 0x004c, company_name: 'Apple, Inc.', hex: '', base64: '' }],
     },
     {
       id: 'device2',
@@ -47,7 +47,7 @@ const mockDeviceConnection = {
       address_type: 'random',
       connectable: true,
       services: [],
-      manufacturer_data: ['Samsung'],
+      manufacturer_data: [{ company_id: 0x0075, company_name: 'Samsung Electronics Co. Ltd.', hex: '', base64: '' }],
     },
   ],
   connectedDevices: [
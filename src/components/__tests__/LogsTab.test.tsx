@@ -84,7 +84,10 @@ const mockLogEntries = [
     data_points: 1500,
     file_path: '/path/to/session1.json',
     notes: 'Test notes',
-    devices: ['GaitBLE_Left', 'GaitBLE_Right'],
+    devices: [
+      { id: 'GaitBLE_Left', alias: null, role: null },
+      { id: 'GaitBLE_Right', alias: null, role: null },
+    ],
   },
   {
     id: 'session2',
@@ -93,7 +96,7 @@ const mockLogEntries = [
     timestamp: 1640995800000,
     data_points: 2000,
     file_path: '/path/to/session2.json',
-    devices: ['GaitBLE_Left'],
+    devices: [{ id: 'GaitBLE_Left', alias: null, role: null }],
   },
 ]
 
@@ -1,8 +1,10 @@
 import React, { createContext, useContext, useState, useEffect, useCallback } from 'react'
 import type { ReactNode } from 'react'
 import { listen } from '@tauri-apps/api/event'
-import { invoke } from '@tauri-apps/api/core'
+import { Channel, invoke } from '@tauri-apps/api/core'
 import { config, isDebugEnabled } from '../config'
+import type { DeviceRole, ManufacturerDataRecord, ServiceDataRecord } from '../types'
+import { decodeSampleBatch } from '../utils/sampleBatch'
 
 // Types
 interface GaitDataPayload {
@@ -16,6 +18,9 @@ interface GaitDataPayload {
   timestamp: number // legacy ms
   timestamp_us?: number // high-res absolute (microseconds)
   monotonic_s?: number // high-res relative seconds
+  session_time_s?: number // seconds since the shared recording time base
+  device_timestamp_ms?: number
+  reconstructed_timestamp_us?: number
   sample_rate?: number
 }
 
@@ -23,11 +28,17 @@ interface BLEDeviceInfo {
   id: string
   name: string
   rssi?: number
+  tx_power?: number | null
   connectable: boolean
   address_type: string
   services: string[]
-  manufacturer_data: string[]
-  service_data: string[]
+  manufacturer_data: ManufacturerDataRecord[]
+  service_data: ServiceDataRecord[]
+  advertised?: Record<string, number | string> // values decoded by the device profile
+  last_seen?: number // epoch ms of the most recent advertisement
+  known?: boolean // registered in the known-device registry
+  alias?: string | null
+  role?: DeviceRole | null
 }
 
 type ConnectionStatus = 'connected' | 'timeout' | 'disconnected'
@@ -372,10 +383,30 @@ export const DeviceConnectionProvider: React.FC<DeviceConnectionProviderProps> =
     }
   }, [])
 
+  const notifyGaitDataSubscribers = useCallback((payload: GaitDataPayload) => {
+    gaitDataSubscribers.current.forEach((callback) => {
+      try {
+        callback(payload)
+      } catch (error) {
+        console.error('Error in gait data subscriber:', error)
+      }
+    })
+  }, [])
+
   // Set up event listeners
   useEffect(() => {
     let unlistenGaitData: (() => void) | null = null
     let unlistenConnectionStatus: (() => void) | null = null
+    let sampleStreamId: number | null = null
+    let disposed = false
+
+    const unsubscribeSampleStream = async (subscriptionId: number) => {
+      try {
+        await invoke('unsubscribe_sample_stream', { subscriptionId })
+      } catch (error) {
+        console.error('[Cleanup] Failed to unsubscribe from the sample stream:', error)
+      }
+    }
 
     const setupEventListeners = async () => {
       try {
@@ -406,32 +437,56 @@ export const DeviceConnectionProvider: React.FC<DeviceConnectionProviderProps> =
           }
 
           // Notify all subscribers
-          gaitDataSubscribers.current.forEach((callback) => {
-            try {
-              callback(payload)
-            } catch (error) {
-              console.error('Error in gait data subscriber:', error)
-            }
-          })
+          notifyGaitDataSubscribers(payload)
         })
 
         console.log('[Init] Global event listeners setup complete')
       } catch (error) {
         console.error('[Init] Failed to setup global event listeners:', error)
       }
+
+      // Batched binary samples (the gait-data event only fires with legacy events enabled)
+      try {
+        const sampleChannel = new Channel<ArrayBuffer>()
+        sampleChannel.onmessage = (buffer) => {
+          for (const batch of decodeSampleBatch(buffer)) {
+            updateGaitDataTime(batch.deviceId)
+            if (batch.sampleRate !== undefined) {
+              setDeviceSampleRates((prev) => {
+                const newMap = new Map(prev)
+                newMap.set(batch.deviceId, batch.sampleRate!)
+                return newMap
+              })
+            }
+            batch.samples.forEach(notifyGaitDataSubscribers)
+          }
+        }
+        const subscriptionId: number = await invoke('subscribe_sample_stream', { onBatch: sampleChannel })
+        if (disposed) {
+          await unsubscribeSampleStream(subscriptionId)
+        } else {
+          sampleStreamId = subscriptionId
+        }
+      } catch (error) {
+        console.error('[Init] Failed to subscribe to the sample stream:', error)
+      }
     }
 
     setupEventListeners()
 
     return () => {
+      disposed = true
       if (unlistenGaitData) {
         unlistenGaitData()
       }
       if (unlistenConnectionStatus) {
         unlistenConnectionStatus()
       }
+      if (sampleStreamId !== null) {
+        unsubscribeSampleStream(sampleStreamId)
+      }
     }
-  }, [addDevice, updateGaitDataTime, setConnectedDevices])
+  }, [addDevice, updateGaitDataTime, setConnectedDevices, notifyGaitDataSubscribers])
 
   // Load initial connected devices and set up periodic refresh
   useEffect(() => {
@@ -443,6 +443,67 @@ describe('CSRF Protection Service', () => {
       })
     })
 
+    test('should export session CSV with CSRF protection', async () => {
+      const sessionId = 'session-123'
+      const fileName = 'session.csv'
+      const expectedPath = '/downloads/session.csv'
+
+      mockInvoke
+        .mockResolvedValueOnce('test-token-123') // get_csrf_token
+        .mockResolvedValueOnce(true) // validate_csrf_token
+        .mockResolvedValueOnce(expectedPath) // export_session_csv
+
+      const result = await protectedOperations.exportSessionCsv(sessionId, fileName)
+
+      expect(result).toBe(expectedPath)
+      expect(mockInvoke).toHaveBeenCalledWith('export_session_csv', {
+        sessionId,
+        fileName,
+        csrfToken: 'test-token-123',
+      })
+    })
+
+    test('should export session EDF+ with CSRF protection', async () => {
+      const sessionId = 'session-123'
+      const fileName = 'session.bdf'
+      const expectedPath = '/downloads/session.bdf'
+
+      mockInvoke
+        .mockResolvedValueOnce('test-token-123') // get_csrf_token
+        .mockResolvedValueOnce(true) // validate_csrf_token
+        .mockResolvedValueOnce(expectedPath) // export_session_edf
+
+      const result = await protectedOperations.exportSessionEdf(sessionId, fileName, 'bdf')
+
+      expect(result).toBe(expectedPath)
+      expect(mockInvoke).toHaveBeenCalledWith('export_session_edf', {
+        sessionId,
+        fileName,
+        format: 'bdf',
+        csrfToken: 'test-token-123',
+      })
+    })
+
+    test('should export session C3D with CSRF protection', async () => {
+      const sessionId = 'session-123'
+      const fileName = 'session.c3d'
+      const expectedPath = '/downloads/session.c3d'
+
+      mockInvoke
+        .mockResolvedValueOnce('test-token-123') // get_csrf_token
+        .mockResolvedValueOnce(true) // validate_csrf_token
+        .mockResolvedValueOnce(expectedPath) // export_session_c3d
+
+      const result = await protectedOperations.exportSessionC3d(sessionId, fileName)
+
+      expect(result).toBe(expectedPath)
+      expect(mockInvoke).toHaveBeenCalledWith('export_session_c3d', {
+        sessionId,
+        fileName,
+        csrfToken: 'test-token-123',
+      })
+    })
+
     test('should save filtered data with CSRF protection', async () => {
       const fileName = 'filtered-data.csv'
       const content = 'timestamp,value\n1234567890,123\n'
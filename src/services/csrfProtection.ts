@@ -1,6 +1,7 @@
 // CSRF Protection Service with token management, refresh, and event monitoring
 
 import { invoke } from '@tauri-apps/api/core'
+import type { RecordingStatus, SessionMetadata } from '../types'
 
 export type SecurityEvent =
   | { TokenGenerated: { timestamp: number; token_id: string } }
@@ -245,6 +246,33 @@ export const protectedOperations = {
     })
   },
 
+  startRecording: async (
+    sessionName: string,
+    subjectId: string,
+    notes: string,
+    deviceIds: string[],
+    storagePath?: string,
+  ): Promise<RecordingStatus> => {
+    return withCSRFProtection(async (token) => {
+      return invoke<RecordingStatus>('start_recording', {
+        sessionName,
+        subjectId,
+        notes,
+        deviceIds,
+        storagePath,
+        csrfToken: token,
+      })
+    })
+  },
+
+  stopRecording: async (): Promise<SessionMetadata> => {
+    return withCSRFProtection(async (token) => {
+      return invoke<SessionMetadata>('stop_recording', {
+        csrfToken: token,
+      })
+    })
+  },
+
   deleteSession: async (sessionId: string) => {
     return withCSRFProtection(async (token) => {
       return invoke('delete_session', {
@@ -264,6 +292,37 @@ export const protectedOperations = {
     })
   },
 
+  exportSessionCsv: async (sessionId: string, fileName: string) => {
+    return withCSRFProtection(async (token) => {
+      return invoke<string>('export_session_csv', {
+        sessionId,
+        fileName,
+        csrfToken: token,
+      })
+    })
+  },
+
+  exportSessionEdf: async (sessionId: string, fileName: string, format: 'edf' | 'bdf' = 'edf') => {
+    return withCSRFProtection(async (token) => {
+      return invoke<string>('export_session_edf', {
+        sessionId,
+        fileName,
+        format,
+        csrfToken: token,
+      })
+    })
+  },
+
+  exportSessionC3d: async (sessionId: string, fileName: string) => {
+    return withCSRFProtection(async (token) => {
+      return invoke<string>('export_session_c3d', {
+        sessionId,
+        fileName,
+        csrfToken: token,
+      })
+    })
+  },
+
   saveFilteredData: async (fileName: string, content: string) => {
     return withCSRFProtection(async (token) => {
       return invoke('save_filtered_data', {
@@ -27,7 +27,18 @@ export const invoke = jest.fn().mockImplementation((command: string, args?: unkn
         file_path: '/mock/path/session_data.csv',
       })
 
+    case 'get_recording_status':
+      return Promise.resolve(null)
+    case 'get_recovered_sessions':
+      return Promise.resolve([])
+
     default:
       return Promise.resolve({ success: true })
   }
 })
+
+// Mock for the IPC channel used by the sample stream
+export class Channel<T = unknown> {
+  id = 0
+  onmessage: (response: T) => void = () => {}
+}
@@ -8,7 +8,172 @@ export interface SessionMetadata {
   timestamp: number
   data_points: number
   file_path: string
-  devices: string[]
+  devices: SessionDevice[]
+  packet_loss?: Record<string, PacketLossStats>
+  time_offsets_ms?: Record<string, number>
+  gaps?: StreamGap[]
+  device_info?: Record<string, DeviceIdentity>
+  // Set while a backend recording is still writing the session
+  in_progress?: boolean
+  // Finished by the recovery pass after the app quit mid-recording
+  recovered?: boolean
+  // Host timestamps (epoch ms) of the first and last sample in the file
+  captured_range_ms?: [number, number] | null
+}
+
+// Session being recorded by the backend (start_recording / get_recording_status)
+export interface RecordingStatus {
+  id: string
+  session_name: string
+  subject_id: string
+  device_ids: string[]
+  started_at: number
+  file_path: string
+  // Rows written to the session file so far
+  samples_written: number
+}
+
+export type DeviceRole = 'left' | 'right' | 'pelvis'
+
+export interface SessionDevice {
+  id: string
+  alias: string | null
+  role: DeviceRole | null
+}
+
+// Entry of the known-device registry
+export interface KnownDevice {
+  address: string
+  alias: string
+  role?: DeviceRole | null
+  profile_id?: string | null
+  calibration_id?: string | null
+  notes?: string
+  last_seen?: number | null
+}
+
+export interface DeviceIdentity {
+  serial_number: string | null
+  firmware_revision: string | null
+}
+
+// Battery and Device Information readout (get_device_info, device-info-updated)
+export interface DeviceHardwareInfo {
+  battery_level: number | null
+  battery_updated_at: number | null
+  manufacturer_name: string | null
+  model_number: string | null
+  serial_number: string | null
+  firmware_revision: string | null
+  hardware_revision: string | null
+}
+
+export interface StreamGap {
+  device_id: string
+  start_ms: number
+  end_ms: number
+  duration_ms: number
+  attempts: number
+  resumed: boolean
+}
+
+export interface DeviceScanFilter {
+  service_uuids?: string[]
+  name_pattern?: string | null
+  name_pattern_kind?: 'glob' | 'regex'
+  min_rssi?: number | null
+  connectable_only?: boolean
+}
+
+export interface PacketLossStats {
+  received: number
+  lost: number
+  gaps: number
+  largest_gap: number
+  duplicates: number
+  reordered: number
+  wraps: number
+  loss_percent: number
+}
+
+export interface LatencyPercentiles {
+  p50: number
+  p95: number
+  p99: number
+  max: number
+}
+
+// Payload of get_stream_diagnostics and the stream-diagnostics event
+export interface StreamDiagnostics {
+  device_id: string
+  started_at: number
+  packets_received: number
+  channel_overflow_drops: number
+  packets_decoded: number
+  decode_failures: number
+  duplicate_packets: number
+  duplicate_samples: number
+  samples_emitted: number
+  parse_latency_us: LatencyPercentiles
+  emit_latency_us: LatencyPercentiles
+  bursts: {
+    count: number
+    average_size: number
+    max_size: number
+    // Sizes 1-9, then 10 or more
+    histogram: number[]
+    current_size: number
+  }
+  inter_arrival: {
+    mean_ms: number
+    jitter_ms: number
+    max_ms: number
+  }
+  sample_rate_hz: number | null
+}
+
+// Result of run_stream_benchmark
+export interface StreamBenchmarkReport {
+  device_count: number
+  rate_hz: number
+  duration_s: number
+  devices: {
+    device_id: string
+    expected_samples: number
+    samples_delivered: number
+    delivered_percent: number
+    achieved_rate_hz: number
+    packets_received: number
+    channel_overflow_drops: number
+    duplicate_samples: number
+    parse_latency_us: LatencyPercentiles
+    emit_latency_us: LatencyPercentiles
+  }[]
+  total_samples: number
+  samples_per_second: number
+  frames_encoded: number
+  bytes_encoded: number
+}
+
+// Sample delivery to the frontend (get_stream_settings / set_stream_settings)
+export interface StreamSettings {
+  flush_interval_ms: number
+  max_batch_samples: number
+  // Emit one gait-data event per sample instead of binary batches
+  legacy_events: boolean
+  // A stream without samples for this long is reported as stalled
+  stall_timeout_ms: number
+}
+
+export type StreamState = 'starting' | 'streaming' | 'stalled' | 'stopping' | 'stopped'
+
+// Payload of the stream-state-changed event
+export interface StreamStateEvent {
+  device_id: string
+  state: StreamState
+  // Why the stream stalled or failed to start
+  reason: string | null
+  at: number
 }
 
 export interface LogEntry {
@@ -34,14 +199,61 @@ export interface GaitDataPoint {
   sequence?: number
 }
 
+export interface ManufacturerDataRecord {
+  company_id: number
+  company_name: string | null
+  hex: string
+  base64: string
+}
+
+export interface ServiceDataRecord {
+  uuid: string
+  service_name: string | null
+  hex: string
+  base64: string
+}
+
 export interface DeviceInfo {
   id: string
   name: string | null
   address_type: string
   rssi?: number
+  tx_power?: number | null
   connectable: boolean
   services: string[]
-  manufacturer_data: string[]
+  manufacturer_data: ManufacturerDataRecord[]
+  service_data?: ServiceDataRecord[]
+  advertised?: Record<string, number | string>
+  known?: boolean
+  alias?: string | null
+  role?: DeviceRole | null
+}
+
+// GATT explorer (get_gatt_tree); btleplug does not expose descriptors
+export interface GattCharacteristic {
+  uuid: string
+  name: string | null
+  properties: string[]
+}
+
+export interface GattService {
+  uuid: string
+  name: string | null
+  primary: boolean
+  characteristics: GattCharacteristic[]
+}
+
+export interface GattValue {
+  hex: string
+  text: string | null
+}
+
+// Payload of the gatt-notification event
+export interface GattNotification {
+  device_id: string
+  characteristic_uuid: string
+  hex: string
+  timestamp: number
 }
 
 export interface DeviceHeartbeat {
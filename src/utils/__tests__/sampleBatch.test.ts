@@ -0,0 +1,96 @@
+import { decodeSampleBatch } from '../sampleBatch'
+
+// Builds a frame the way the backend does
+function encodeBatch(
+  devices: { id: string; rate: number; columns: number[][]; values: number[][] }[],
+): ArrayBuffer {
+  const size = devices.reduce(
+    (sum, d) => sum + 2 + d.id.length + 4 + 8 + d.columns.length * d.columns[0].length * 8 + d.values.length * d.values[0].length * 4,
+    4,
+  )
+  const buffer = new ArrayBuffer(size)
+  const view = new DataView(buffer)
+  view.setUint8(0, 1)
+  view.setUint16(2, devices.length, true)
+  let offset = 4
+  for (const device of devices) {
+    view.setUint16(offset, device.id.length, true)
+    offset += 2
+    for (let i = 0; i < device.id.length; i++) view.setUint8(offset++, device.id.charCodeAt(i))
+    view.setUint32(offset, device.columns[0].length, true)
+    offset += 4
+    view.setFloat64(offset, device.rate, true)
+    offset += 8
+    for (const column of device.columns) {
+      for (const v of column) {
+        view.setFloat64(offset, v, true)
+        offset += 8
+      }
+    }
+    for (const column of device.values) {
+      for (const v of column) {
+        view.setFloat32(offset, v, true)
+        offset += 4
+      }
+    }
+  }
+  return buffer
+}
+
+describe('decodeSampleBatch', () => {
+  it('decodes columns of several devices into samples', () => {
+    const buffer = encodeBatch([
+      {
+        id: 'left',
+        rate: 100,
+        columns: [
+          [1_760_000_000_000_000, 1_760_000_000_010_000],
+          [0, 0.01],
+          [1.5, 1.51],
+          [20, NaN],
+          [1_760_000_000_000_500, NaN],
+        ],
+        values: [
+          [1, 2],
+          [3, 4],
+          [5, 6],
+          [0.5, 0.25],
+          [-1, -2],
+          [9, 10],
+        ],
+      },
+      { id: 'right', rate: NaN, columns: [[7000], [0.5], [0.25], [NaN], [NaN]], values: [[1], [1], [1], [1], [1], [1]] },
+    ])
+
+    const batches = decodeSampleBatch(buffer)
+
+    expect(batches).toHaveLength(2)
+    expect(batches[0].deviceId).toBe('left')
+    expect(batches[0].sampleRate).toBe(100)
+    expect(batches[0].samples[1]).toEqual({
+      device_id: 'left',
+      r1: 2,
+      r2: 4,
+      r3: 6,
+      x: 0.25,
+      y: -2,
+      z: 10,
+      timestamp: 1_760_000_000_010,
+      timestamp_us: 1_760_000_000_010_000,
+      monotonic_s: 0.01,
+      session_time_s: 1.51,
+      device_timestamp_ms: undefined,
+      reconstructed_timestamp_us: undefined,
+      sample_rate: 100,
+    })
+    expect(batches[0].samples[0].device_timestamp_ms).toBe(20)
+    expect(batches[1].sampleRate).toBeUndefined()
+    expect(batches[1].samples[0].timestamp).toBe(7)
+  })
+
+  it('rejects frames of another version', () => {
+    const buffer = new ArrayBuffer(4)
+    new DataView(buffer).setUint8(0, 2)
+    expect(() => decodeSampleBatch(buffer)).toThrow('Unsupported sample batch version 2')
+  })
+})
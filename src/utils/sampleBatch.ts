@@ -0,0 +1,102 @@
+// Decoder for the binary sample batches sent over the sample stream channel.
+// Layout (little endian, column oriented; see src-tauri/src/sample_stream.rs):
+//   u8 version, u8 reserved, u16 device count
+//   per device: u16 id length, id (UTF-8), u32 sample count n, f64 sample rate (NaN = unknown),
+//     f64[n] timestamp_us, monotonic_s, session_time_s, device_timestamp_ms, reconstructed_timestamp_us,
+//     f32[n] r1, r2, r3, x, y, z
+
+export const SAMPLE_BATCH_VERSION = 1
+
+export interface BatchedGaitSample {
+  device_id: string
+  r1: number
+  r2: number
+  r3: number
+  x: number
+  y: number
+  z: number
+  timestamp: number // ms, derived from timestamp_us
+  timestamp_us: number
+  monotonic_s: number
+  session_time_s: number
+  device_timestamp_ms?: number
+  reconstructed_timestamp_us?: number
+  sample_rate?: number
+}
+
+export interface DeviceSampleBatch {
+  deviceId: string
+  sampleRate?: number
+  samples: BatchedGaitSample[]
+}
+
+// Device ids are ASCII in practice; TextDecoder is missing from some test environments
+const decodeId = (bytes: Uint8Array): string =>
+  typeof TextDecoder !== 'undefined' ? new TextDecoder().decode(bytes) : String.fromCharCode(...bytes)
+
+const optional = (value: number): number | undefined => (Number.isNaN(value) ? undefined : value)
+
+export function decodeSampleBatch(buffer: ArrayBuffer): DeviceSampleBatch[] {
+  const view = new DataView(buffer)
+  const version = view.getUint8(0)
+  if (version !== SAMPLE_BATCH_VERSION) {
+    throw new Error(`Unsupported sample batch version ${version}`)
+  }
+  const deviceCount = view.getUint16(2, true)
+  let offset = 4
+  const batches: DeviceSampleBatch[] = []
+
+  for (let d = 0; d < deviceCount; d++) {
+    const idLength = view.getUint16(offset, true)
+    offset += 2
+    const deviceId = decodeId(new Uint8Array(buffer, offset, idLength))
+    offset += idLength
+    const count = view.getUint32(offset, true)
+    offset += 4
+    const sampleRate = optional(view.getFloat64(offset, true))
+    offset += 8
+
+    const readF64Column = () => {
+      const column = new Array<number>(count)
+      for (let i = 0; i < count; i++) column[i] = view.getFloat64(offset + i * 8, true)
+      offset += count * 8
+      return column
+    }
+    const readF32Column = () => {
+      const column = new Array<number>(count)
+      for (let i = 0; i < count; i++) column[i] = view.getFloat32(offset + i * 4, true)
+      offset += count * 4
+      return column
+    }
+
+    const timestampUs = readF64Column()
+    const monotonicS = readF64Column()
+    const sessionTimeS = readF64Column()
+    const deviceTimestampMs = readF64Column()
+    const reconstructedUs = readF64Column()
+    const [r1, r2, r3, x, y, z] = [0, 1, 2, 3, 4, 5].map(readF32Column)
+
+    const samples: BatchedGaitSample[] = new Array(count)
+    for (let i = 0; i < count; i++) {
+      samples[i] = {
+        device_id: deviceId,
+        r1: r1[i],
+        r2: r2[i],
+        r3: r3[i],
+        x: x[i],
+        y: y[i],
+        z: z[i],
+        timestamp: Math.floor(timestampUs[i] / 1000),
+        timestamp_us: timestampUs[i],
+        monotonic_s: monotonicS[i],
+        session_time_s: sessionTimeS[i],
+        device_timestamp_ms: optional(deviceTimestampMs[i]),
+        reconstructed_timestamp_us: optional(reconstructedUs[i]),
+        sample_rate: sampleRate,
+      }
+    }
+    batches.push({ deviceId, sampleRate, samples })
+  }
+
+  return batches
+}